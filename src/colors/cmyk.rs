@@ -0,0 +1,326 @@
+use crate::colors::rgb::{from_linear, to_linear, Color};
+use crate::colors::WHITE;
+use crate::lifehash::LifeHash;
+use std::collections::HashSet;
+
+/// Newton steps when separating a color into inks.
+const ITERATIONS: usize = 24;
+/// Change of ink coverage for the finite differences of the separation.
+const STEP: f64 = 1e-6;
+/// Points along each axis of the grid of a separation table.
+const TABLE_POINTS: usize = 17;
+/// Newton steps from the inks interpolated in a separation table.
+const TABLE_STEPS: usize = 2;
+
+/// Coverage of the four process inks, each from 0 to 1.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Cmyk {
+    pub c: f64,
+    pub m: f64,
+    pub y: f64,
+    pub k: f64,
+}
+impl Cmyk {
+    pub const fn new(c: f64, m: f64, y: f64, k: f64) -> Self {
+        Self { c, m, y, k }
+    }
+    /// The total ink coverage, from 0 to 4.
+    pub fn total(&self) -> f64 {
+        self.c + self.m + self.y + self.k
+    }
+}
+
+/// A simple model of a press without an ICC profile. Each ink filters the light like a colored
+/// glass over the paper, in proportion to its coverage, and inks that overlap multiply.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CmykProfile {
+    pub paper: Color,
+    /// The colors of the solid inks printed on the paper.
+    pub cyan: Color,
+    pub magenta: Color,
+    pub yellow: Color,
+    pub black: Color,
+    /// The most ink on any spot, from 0 to 4. Presses for coated paper take around 3.
+    pub total_ink: f64,
+    /// How much of the gray of a color is printed with black instead of the other inks, 0 to 1.
+    pub black_generation: f64,
+}
+impl Default for CmykProfile {
+    /// Process inks on coated paper, as they are commonly shown on screen.
+    fn default() -> Self {
+        Self {
+            paper: WHITE,
+            cyan: Color::new_u8(0, 174, 239),
+            magenta: Color::new_u8(236, 0, 140),
+            yellow: Color::new_u8(255, 242, 0),
+            black: Color::new_u8(35, 31, 32),
+            total_ink: 3.0,
+            black_generation: 1.0,
+        }
+    }
+}
+impl CmykProfile {
+    /// The color the inks print on the paper.
+    pub fn print(&self, inks: Cmyk) -> Color {
+        // Rounding drops the error of the transfer functions, so the bare paper keeps its color.
+        let [r, g, b] = self
+            .print_linear(inks)
+            .map(|c| (from_linear(c.clamp(0.0, 1.0)) * 1e9).round() / 1e9);
+        Color::new(r, g, b)
+    }
+    fn print_linear(&self, inks: Cmyk) -> [f64; 3] {
+        let paper = linear(self.paper);
+        let mut color = paper;
+        for (coverage, ink) in [
+            (inks.c, self.cyan),
+            (inks.m, self.magenta),
+            (inks.y, self.yellow),
+            (inks.k, self.black),
+        ] {
+            let ink = linear(ink);
+            for i in 0..3 {
+                let filter = if paper[i] > 0.0 {
+                    ink[i] / paper[i]
+                } else {
+                    0.0
+                };
+                color[i] *= 1.0 - coverage.clamp(0.0, 1.0) * (1.0 - filter);
+            }
+        }
+        color
+    }
+    /// The inks that print closest to the color. Black covers the gray of the color by the
+    /// black generation, the other three inks are solved for and then cut back to the total ink
+    /// limit. Colors outside of the gamut of the inks print as near as the model gets.
+    pub fn separate(&self, color: Color) -> Cmyk {
+        let k = self.black_ink(color);
+        self.limit(self.solve_inks(color, k, ITERATIONS), k)
+    }
+    fn black_ink(&self, color: Color) -> f64 {
+        (1.0 - color.r.max(color.g).max(color.b)) * self.black_generation.clamp(0.0, 1.0)
+    }
+    /// The cyan, magenta and yellow inks that print the color with the black ink, before the
+    /// total ink limit.
+    fn solve_inks(&self, color: Color, k: f64, steps: usize) -> [f64; 3] {
+        // The ideal filter formula is the starting point.
+        let inks = [color.r, color.g, color.b].map(|c| {
+            if k < 1.0 {
+                ((1.0 - c - k) / (1.0 - k)).clamp(0.0, 1.0)
+            } else {
+                0.0
+            }
+        });
+        self.refine_inks(color, k, inks, steps)
+    }
+    /// Newton steps from the cyan, magenta and yellow inks toward the ones that print the color
+    /// with the black ink.
+    fn refine_inks(&self, color: Color, k: f64, mut inks: [f64; 3], steps: usize) -> [f64; 3] {
+        let target = linear(color);
+        let print = |inks: [f64; 3]| self.print_linear(Cmyk::new(inks[0], inks[1], inks[2], k));
+        for _ in 0..steps {
+            let printed = print(inks);
+            let residual: [f64; 3] = std::array::from_fn(|i| printed[i] - target[i]);
+            if residual.iter().all(|r| r.abs() < 1e-9) {
+                break;
+            }
+            // Each column is how the printed color changes with one ink.
+            let mut jacobian = [[0.0; 3]; 3];
+            for (ink, column) in jacobian.iter_mut().enumerate() {
+                let mut moved = inks;
+                let step = if inks[ink] + STEP > 1.0 { -STEP } else { STEP };
+                moved[ink] += step;
+                let changed = print(moved);
+                *column = std::array::from_fn(|i| (changed[i] - printed[i]) / step);
+            }
+            let Some(delta) = solve(jacobian, residual) else {
+                break;
+            };
+            for (ink, delta) in inks.iter_mut().zip(delta) {
+                *ink = (*ink - delta).clamp(0.0, 1.0);
+            }
+        }
+        inks
+    }
+    /// Cuts the cyan, magenta and yellow inks back to the total ink limit.
+    fn limit(&self, mut inks: [f64; 3], k: f64) -> Cmyk {
+        let room = (self.total_ink - k).max(0.0);
+        let cmy: f64 = inks.iter().sum();
+        if cmy > room {
+            inks = inks.map(|ink| ink * room / cmy);
+        }
+        Cmyk::new(inks[0], inks[1], inks[2], k)
+    }
+    /// Separations of a grid of colors, for images with too many colors to separate one by one.
+    pub fn table(&self) -> SeparationTable {
+        let last = (TABLE_POINTS - 1) as f64;
+        let mut inks = Vec::with_capacity(TABLE_POINTS.pow(3));
+        for r in 0..TABLE_POINTS {
+            for g in 0..TABLE_POINTS {
+                for b in 0..TABLE_POINTS {
+                    let color = Color::new(r as f64 / last, g as f64 / last, b as f64 / last);
+                    inks.push(self.solve_inks(color, self.black_ink(color), ITERATIONS));
+                }
+            }
+        }
+        SeparationTable {
+            profile: *self,
+            inks,
+        }
+    }
+    /// The color after it is separated into inks and printed.
+    pub fn round_trip(&self, color: Color) -> Color {
+        self.print(self.separate(color))
+    }
+}
+
+/// Separations precomputed on a grid of colors by [`CmykProfile::table`]. A color starts from
+/// the inks interpolated between the grid points around it and takes a few Newton steps from
+/// there, so separating costs the same for every color.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeparationTable {
+    profile: CmykProfile,
+    inks: Vec<[f64; 3]>,
+}
+impl SeparationTable {
+    /// The inks that print closest to the color, like [`CmykProfile::separate`].
+    pub fn separate(&self, color: Color) -> Cmyk {
+        let last = (TABLE_POINTS - 1) as f64;
+        let axes = [color.r, color.g, color.b].map(|c| {
+            let position = c.clamp(0.0, 1.0) * last;
+            let low = (position.floor() as usize).min(TABLE_POINTS - 2);
+            (low, position - low as f64)
+        });
+        let mut inks = [0.0; 3];
+        for corner in 0..8 {
+            let mut index = 0;
+            let mut weight = 1.0;
+            for (axis, (low, fraction)) in axes.iter().enumerate() {
+                let high = corner >> (2 - axis) & 1 == 1;
+                index = index * TABLE_POINTS + low + high as usize;
+                weight *= if high { *fraction } else { 1.0 - fraction };
+            }
+            for (ink, point) in inks.iter_mut().zip(self.inks[index]) {
+                *ink += point * weight;
+            }
+        }
+        let k = self.profile.black_ink(color);
+        let inks = self.profile.refine_inks(color, k, inks, TABLE_STEPS);
+        self.profile.limit(inks, k)
+    }
+}
+
+fn linear(color: Color) -> [f64; 3] {
+    [color.r, color.g, color.b].map(to_linear)
+}
+
+/// Solves the 3x3 system given by its columns with Cramer's rule, unless it is singular.
+fn solve(columns: [[f64; 3]; 3], rhs: [f64; 3]) -> Option<[f64; 3]> {
+    let det = |[a, b, c]: [[f64; 3]; 3]| {
+        a[0] * (b[1] * c[2] - b[2] * c[1]) - b[0] * (a[1] * c[2] - a[2] * c[1])
+            + c[0] * (a[1] * b[2] - a[2] * b[1])
+    };
+    let d = det(columns);
+    if d.abs() < 1e-12 {
+        return None;
+    }
+    Some(std::array::from_fn(|i| {
+        let mut replaced = columns;
+        replaced[i] = rhs;
+        det(replaced) / d
+    }))
+}
+
+/// How one color comes out of a round trip through CMYK.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GamutEntry {
+    pub color: Color,
+    pub inks: Cmyk,
+    pub printed: Color,
+    pub delta_e: f64,
+}
+
+/// The colors of a lifehash checked against a CMYK profile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GamutReport {
+    /// The largest change of a color that still counts as in gamut.
+    pub max_delta_e: f64,
+    pub total_ink: f64,
+    pub entries: Vec<GamutEntry>,
+}
+impl GamutReport {
+    /// Checks each color, in order.
+    pub fn new(colors: &[Color], profile: &CmykProfile, max_delta_e: f64) -> Self {
+        let entries = colors
+            .iter()
+            .map(|color| {
+                let inks = profile.separate(*color);
+                let printed = profile.print(inks);
+                GamutEntry {
+                    color: *color,
+                    inks,
+                    printed,
+                    delta_e: color.delta_e(&printed),
+                }
+            })
+            .collect();
+        Self {
+            max_delta_e,
+            total_ink: profile.total_ink,
+            entries,
+        }
+    }
+    /// The entries that change by more than the maximum delta E.
+    pub fn out_of_gamut(&self) -> Vec<&GamutEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.delta_e > self.max_delta_e)
+            .collect()
+    }
+    /// One line per color and a count of those out of gamut.
+    pub fn summary(&self) -> String {
+        let percent = |ink: f64| (ink * 100.0).round();
+        let mut lines: Vec<String> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{} C {:.0}% M {:.0}% Y {:.0}% K {:.0}% prints {} delta E {:.1}{}",
+                    entry.color.hex(),
+                    percent(entry.inks.c),
+                    percent(entry.inks.m),
+                    percent(entry.inks.y),
+                    percent(entry.inks.k),
+                    entry.printed.hex(),
+                    entry.delta_e,
+                    if entry.delta_e > self.max_delta_e {
+                        " out of gamut"
+                    } else {
+                        ""
+                    }
+                )
+            })
+            .collect();
+        lines.push(format!(
+            "{} of {} colors change by more than delta E {:.1} in CMYK with {:.0}% total ink",
+            self.out_of_gamut().len(),
+            self.entries.len(),
+            self.max_delta_e,
+            percent(self.total_ink)
+        ));
+        lines.join("\n")
+    }
+}
+
+/// Checks the key colors of the gradient of the lifehash and then every other color of its color
+/// grid, each color once.
+pub fn gamut_report(lifehash: &LifeHash, profile: &CmykProfile, max_delta_e: f64) -> GamutReport {
+    let mut seen = HashSet::new();
+    let colors: Vec<Color> = lifehash
+        .gradient
+        .key_colors()
+        .into_iter()
+        .chain(lifehash.color_grid.storage.iter().copied())
+        .filter(|color| seen.insert(color.hex()))
+        .collect();
+    GamutReport::new(&colors, profile, max_delta_e)
+}
@@ -1,5 +1,5 @@
 use crate::colors::gradient::make_hue;
-use crate::colors::{rgb::Color, BLACK};
+use crate::colors::{rgb::Color, BLACK, WHITE};
 use crate::utils::modulo;
 
 pub enum ColorFunction<'a> {
@@ -21,6 +21,30 @@ impl ColorFunction<'_> {
             }
         }
     }
+    /// The colors the gradient blends between, in order. Hues are sampled at the primary and
+    /// secondary colors.
+    pub fn key_colors(&self) -> Vec<Color> {
+        match self {
+            ColorFunction::Blend(color1, color2) => vec![*color1, *color2],
+            ColorFunction::BlendVec(colors) => colors.clone(),
+            ColorFunction::BlendRef(colors) => colors.to_vec(),
+            ColorFunction::Reverse(func) => func.key_colors().into_iter().rev().collect(),
+            ColorFunction::MakeHue => (0..6).map(|i| self.apply(i as f64 / 6.0)).collect(),
+        }
+    }
+    /// White or black, whichever stands out more against the key colors of the gradient.
+    pub fn contrast_color(&self) -> Color {
+        let colors = self.key_colors();
+        if colors.is_empty() {
+            return WHITE;
+        }
+        let luminance = colors.iter().map(Color::luminance).sum::<f64>() / colors.len() as f64;
+        if luminance > WHITE.luminance() / 2.0 {
+            BLACK
+        } else {
+            WHITE
+        }
+    }
 }
 fn blend_vals(t: f64, colors: &[Color]) -> Color {
     if colors.is_empty() {
@@ -281,39 +281,60 @@ fn adjust_colors(index: usize, colors: &mut [Color], neutral_color: Color) {
     }
 }
 
-pub fn select_gradient<'a>(
+/// The kind of gradient a lifehash is colored with, chosen from the digest.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum GradientFamily {
+    Grayscale,
+    Monochromatic,
+    Complementary,
+    Triadic,
+    Analogous,
+}
+
+pub fn select_gradient_family(
     entropy: &mut Enumerator,
     version: Version,
-) -> Result<ColorFunction<'a>, Error> {
+) -> Result<GradientFamily, Error> {
     if version == Version::GrayscaleFiducial {
-        return select_grayscale(entropy);
+        return Ok(GradientFamily::Grayscale);
     }
-    let value = entropy.next_u2()?;
-    match value {
-        0 => match version {
+    Ok(match entropy.next_u2()? {
+        0 => GradientFamily::Monochromatic,
+        1 => GradientFamily::Complementary,
+        2 => GradientFamily::Triadic,
+        _ => GradientFamily::Analogous,
+    })
+}
+
+pub fn select_gradient<'a>(
+    entropy: &mut Enumerator,
+    version: Version,
+) -> Result<ColorFunction<'a>, Error> {
+    match select_gradient_family(entropy, version)? {
+        GradientFamily::Grayscale => select_grayscale(entropy),
+        GradientFamily::Monochromatic => match version {
             Version::Version1 => monochromatic(entropy, &ColorFunction::MakeHue),
             Version::Version2 | Version::Detailed => monochromatic(entropy, &SPECTRUM_CMYK_SAFE),
             Version::Fiducial => monochromatic_fiducial(entropy),
             Version::GrayscaleFiducial => unreachable!(),
         },
-        1 => match version {
+        GradientFamily::Complementary => match version {
             Version::Version1 => complementary(entropy, &SPECTRUM),
             Version::Version2 | Version::Detailed => complementary(entropy, &SPECTRUM_CMYK_SAFE),
             Version::Fiducial => complementary_fiducial(entropy),
             Version::GrayscaleFiducial => unreachable!(),
         },
-        2 => match version {
+        GradientFamily::Triadic => match version {
             Version::Version1 => triadic(entropy, &SPECTRUM),
             Version::Version2 | Version::Detailed => triadic(entropy, &SPECTRUM_CMYK_SAFE),
             Version::Fiducial => triadic_fiducial(entropy),
             Version::GrayscaleFiducial => unreachable!(),
         },
-        3 => match version {
+        GradientFamily::Analogous => match version {
             Version::Version1 => analogous(entropy, &SPECTRUM),
             Version::Version2 | Version::Detailed => analogous(entropy, &SPECTRUM_CMYK_SAFE),
             Version::Fiducial => analogous_fiducial(entropy),
             Version::GrayscaleFiducial => unreachable!(),
         },
-        _ => Ok(GRAYSCALE),
     }
 }
@@ -1,6 +1,7 @@
 use crate::colors::functions::ColorFunction;
 use crate::colors::rgb::Color;
 
+pub mod cmyk;
 pub mod functions;
 pub mod gradient;
 pub mod hsb;
@@ -1,4 +1,5 @@
 use crate::colors::{BLACK, WHITE};
+use crate::lifehash::quantize8;
 
 #[derive(Copy, Clone, Debug)]
 pub struct Color {
@@ -25,10 +26,55 @@ impl Color {
             b: b as f64 / 255.0,
         }
     }
+    /// The color as `#rrggbb`, quantized the same way as raster images.
+    pub fn hex(&self) -> String {
+        format!(
+            "#{:02x}{:02x}{:02x}",
+            quantize8(self.r),
+            quantize8(self.g),
+            quantize8(self.b)
+        )
+    }
+    /// Parses `#rrggbb` or `rrggbb`.
+    pub fn from_hex(value: &str) -> Option<Color> {
+        let digits = value.strip_prefix('#').unwrap_or(value);
+        match hex::decode(digits).ok()?.as_slice() {
+            [r, g, b] => Some(Color::new_u8(*r, *g, *b)),
+            _ => None,
+        }
+    }
     pub fn luminance(&self) -> f64 {
         ((0.299 * self.r).powf(2.0) + (0.587 * self.g).powf(2.0) + (0.114 * self.b).powf(2.0))
             .sqrt()
     }
+    /// The share of the light of white that the color gives off, from 0 to 1, in linear light.
+    pub fn relative_luminance(&self) -> f64 {
+        let [r, g, b] = [self.r, self.g, self.b].map(to_linear);
+        0.2126 * r + 0.7152 * g + 0.0722 * b
+    }
+    /// The color in CIELAB under a D65 white point, `[L, a, b]` with L from 0 to 100.
+    pub fn lab(&self) -> [f64; 3] {
+        let [r, g, b] = [self.r, self.g, self.b].map(to_linear);
+        let xyz = [
+            (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.9505,
+            self.relative_luminance(),
+            (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.089,
+        ];
+        let [x, y, z] = xyz.map(|t| {
+            if t > 216.0 / 24389.0 {
+                t.cbrt()
+            } else {
+                (24389.0 / 27.0 * t + 16.0) / 116.0
+            }
+        });
+        [116.0 * y - 16.0, 500.0 * (x - y), 200.0 * (y - z)]
+    }
+    /// The CIE76 color difference, the distance between the colors in CIELAB. Around 2.3 is
+    /// just noticeable.
+    pub fn delta_e(&self, other: &Color) -> f64 {
+        let (a, b) = (self.lab(), other.lab());
+        ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+    }
     pub fn burn(&self, t: f64) -> Color {
         let f = (1.0 - t).max(1.0e-7);
         Color::new(
@@ -51,6 +97,24 @@ impl Color {
         self.lerp_to(&BLACK, t)
     }
 }
+/// Converts an sRGB channel to linear light.
+pub(crate) fn to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts a linear light channel to sRGB.
+pub(crate) fn from_linear(c: f64) -> f64 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
 impl Default for Color {
     fn default() -> Self {
         Self {
@@ -0,0 +1,74 @@
+use crate::encoders::{rgba, ImageEncoder};
+use crate::Image;
+use std::io::{Error, ErrorKind, Write};
+
+const FILE_HEADER_SIZE: usize = 14;
+const INFO_HEADER_SIZE: usize = 40;
+const V4_HEADER_SIZE: usize = 108;
+// 72 DPI
+const PIXELS_PER_METER: u32 = 2835;
+
+/// Windows bitmap encoder. Writes 24-bit BGR, or 32-bit BGRA with a V4 header when the image has alpha.
+pub struct BmpEncoder;
+impl ImageEncoder for BmpEncoder {
+    fn encode(&self, bitmap: &Image, writer: &mut dyn Write) -> Result<(), Error> {
+        let has_alpha = bitmap.channels == 2 || bitmap.channels == 4;
+        let bytes_per_pixel = if has_alpha { 4 } else { 3 };
+        // Rows are padded to a multiple of 4 bytes.
+        let row_size = (bitmap.width * bytes_per_pixel).div_ceil(4) * 4;
+        let header_size = if has_alpha {
+            V4_HEADER_SIZE
+        } else {
+            INFO_HEADER_SIZE
+        };
+        let data_offset = FILE_HEADER_SIZE + header_size;
+        let image_size = row_size * bitmap.height;
+        let file_size = u32::try_from(data_offset + image_size)
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, "Image too large for BMP"))?;
+        let mut header = Vec::with_capacity(data_offset);
+        header.extend(b"BM");
+        header.extend(file_size.to_le_bytes());
+        header.extend(0u32.to_le_bytes());
+        header.extend((data_offset as u32).to_le_bytes());
+        header.extend((header_size as u32).to_le_bytes());
+        header.extend((bitmap.width as i32).to_le_bytes());
+        // A positive height stores the rows bottom-up.
+        header.extend((bitmap.height as i32).to_le_bytes());
+        header.extend(1u16.to_le_bytes());
+        header.extend((bytes_per_pixel as u16 * 8).to_le_bytes());
+        // BI_BITFIELDS for the alpha mask, BI_RGB otherwise
+        header.extend((if has_alpha { 3u32 } else { 0u32 }).to_le_bytes());
+        header.extend((image_size as u32).to_le_bytes());
+        header.extend(PIXELS_PER_METER.to_le_bytes());
+        header.extend(PIXELS_PER_METER.to_le_bytes());
+        header.extend(0u32.to_le_bytes());
+        header.extend(0u32.to_le_bytes());
+        if has_alpha {
+            header.extend(0x00FF0000u32.to_le_bytes());
+            header.extend(0x0000FF00u32.to_le_bytes());
+            header.extend(0x000000FFu32.to_le_bytes());
+            header.extend(0xFF000000u32.to_le_bytes());
+            header.extend(b"BGRs");
+            // Endpoints and gamma are unused for sRGB.
+            header.extend([0u8; 48]);
+        }
+        writer.write_all(&header)?;
+        let mut row = vec![0u8; row_size];
+        for y in (0..bitmap.height).rev() {
+            let start = y * bitmap.width * bitmap.channels;
+            let source = &bitmap.pixels[start..start + bitmap.width * bitmap.channels];
+            for (x, pixel) in source.chunks_exact(bitmap.channels).enumerate() {
+                let [r, g, b, a] = rgba(pixel, u8::MAX);
+                let offset = x * bytes_per_pixel;
+                row[offset] = b;
+                row[offset + 1] = g;
+                row[offset + 2] = r;
+                if has_alpha {
+                    row[offset + 3] = a;
+                }
+            }
+            writer.write_all(&row)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,28 @@
+use crate::grids::array::GridArray;
+use std::io::{Error, Write};
+
+/// Writes the array as CSV in long form, with a header and one line per position: its index on
+/// each axis followed by its fields.
+pub fn encode_csv<W: Write>(array: &GridArray, mut writer: W) -> Result<(), Error> {
+    let names: Vec<&str> = array
+        .axes
+        .iter()
+        .map(|(name, _)| *name)
+        .chain(array.fields.iter().copied())
+        .collect();
+    writeln!(writer, "{}", names.join(","))?;
+    let fields = array.fields.len();
+    let mut index = vec![0; array.axes.len()];
+    for position in 0..array.len() / fields {
+        // The index on each axis, innermost last.
+        let mut rest = position;
+        for (i, (_, length)) in array.axes.iter().enumerate().rev() {
+            index[i] = rest % length;
+            rest /= length;
+        }
+        let values = (0..fields).map(|field| array.format_value(position * fields + field));
+        let line: Vec<String> = index.iter().map(usize::to_string).chain(values).collect();
+        writeln!(writer, "{}", line.join(","))?;
+    }
+    Ok(())
+}
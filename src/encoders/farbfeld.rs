@@ -0,0 +1,33 @@
+use crate::encoders::{rgba, ImageEncoder};
+use crate::Image;
+use std::io::{Error, ErrorKind, Write};
+
+/// farbfeld encoder, always 16-bit RGBA.
+pub struct FarbfeldEncoder;
+impl ImageEncoder for FarbfeldEncoder {
+    fn encode(&self, bitmap: &Image, writer: &mut dyn Write) -> Result<(), Error> {
+        self.encode16(&bitmap.to_u16(), writer)
+    }
+    fn encode16(&self, bitmap: &Image<u16>, writer: &mut dyn Write) -> Result<(), Error> {
+        let width = u32::try_from(bitmap.width)
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, "Image too large for farbfeld"))?;
+        let height = u32::try_from(bitmap.height)
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, "Image too large for farbfeld"))?;
+        writer.write_all(b"farbfeld")?;
+        writer.write_all(&width.to_be_bytes())?;
+        writer.write_all(&height.to_be_bytes())?;
+        for row in bitmap
+            .pixels
+            .chunks_exact(bitmap.width * bitmap.channels)
+            .take(bitmap.height)
+        {
+            let bytes: Vec<u8> = row
+                .chunks_exact(bitmap.channels)
+                .flat_map(|pixel| rgba(pixel, u16::MAX))
+                .flat_map(u16::to_be_bytes)
+                .collect();
+            writer.write_all(&bytes)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+use crate::encoders::{rgba, ImageEncoder};
+use crate::lifehash::{quantize8, LifeHash, MAX_IMAGE_SIZE};
+use crate::{Image, Point};
+use clap::ValueEnum;
+use std::io::{Error, ErrorKind, Write};
+
+/// Pixel layouts of display controllers, named by their bytes in memory order.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum PixelFormat {
+    /// 16 bits of 5 red, 6 green and 5 blue, least significant byte first.
+    Rgb565Le,
+    /// 16 bits of 5 red, 6 green and 5 blue, most significant byte first.
+    Rgb565Be,
+    /// Bytes red, green, blue.
+    Rgb888,
+    /// Bytes blue, green, red.
+    Bgr888,
+    /// Bytes alpha, red, green, blue.
+    Argb8888,
+}
+impl PixelFormat {
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Rgb565Le | PixelFormat::Rgb565Be => 2,
+            PixelFormat::Rgb888 | PixelFormat::Bgr888 => 3,
+            PixelFormat::Argb8888 => 4,
+        }
+    }
+    /// Writes an RGBA pixel to the start of `out`. Formats without alpha blend the pixel over
+    /// black, the color of a display that is off.
+    pub fn pack(self, [r, g, b, a]: [u8; 4], out: &mut [u8]) {
+        let [r, g, b] = if self == PixelFormat::Argb8888 {
+            [r, g, b]
+        } else {
+            [r, g, b].map(|c| ((c as u16 * a as u16 + 127) / 255) as u8)
+        };
+        // Rounded to the nearest of the fewer levels.
+        let reduce = |c: u8, max: u16| (c as u16 * max + 127) / 255;
+        let rgb565 = reduce(r, 31) << 11 | reduce(g, 63) << 5 | reduce(b, 31);
+        match self {
+            PixelFormat::Rgb565Le => out[..2].copy_from_slice(&rgb565.to_le_bytes()),
+            PixelFormat::Rgb565Be => out[..2].copy_from_slice(&rgb565.to_be_bytes()),
+            PixelFormat::Rgb888 => out[..3].copy_from_slice(&[r, g, b]),
+            PixelFormat::Bgr888 => out[..3].copy_from_slice(&[b, g, r]),
+            PixelFormat::Argb8888 => out[..4].copy_from_slice(&[a, r, g, b]),
+        }
+    }
+}
+
+/// The largest stride in bytes, a row of the widest image in the largest pixel format.
+pub const MAX_STRIDE: usize = MAX_IMAGE_SIZE * 4;
+
+/// How rows of pixels are laid out in a framebuffer.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct FramebufferOptions {
+    pub format: PixelFormat,
+    /// Bytes from the start of one row to the next, by default the width of a row. The bytes
+    /// past the pixels are zero.
+    pub stride: Option<usize>,
+}
+impl FramebufferOptions {
+    /// The stride for rows of `width` pixels, an error if the rows don't fit in it or it is
+    /// larger than [`MAX_STRIDE`].
+    pub fn stride(&self, width: usize) -> Result<usize, Error> {
+        let row = width
+            .checked_mul(self.format.bytes_per_pixel())
+            .filter(|row| *row <= MAX_STRIDE)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Invalid Image Size"))?;
+        match self.stride {
+            Some(stride) if stride > MAX_STRIDE => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("stride of {stride} bytes is more than the largest of {MAX_STRIDE}"),
+            )),
+            Some(stride) if stride < row => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("stride of {stride} bytes is less than a row of {row}"),
+            )),
+            Some(stride) => Ok(stride),
+            None => Ok(row),
+        }
+    }
+}
+
+/// Writes the image as a raw framebuffer, with no header.
+pub struct FramebufferEncoder {
+    pub options: FramebufferOptions,
+}
+impl ImageEncoder for FramebufferEncoder {
+    fn encode(&self, bitmap: &Image, writer: &mut dyn Write) -> Result<(), Error> {
+        encode_framebuffer(bitmap, &self.options, writer)
+    }
+}
+
+pub fn encode_framebuffer<W: Write>(
+    bitmap: &Image,
+    options: &FramebufferOptions,
+    mut writer: W,
+) -> Result<(), Error> {
+    framebuffer_rows(bitmap, options, |_, row| writer.write_all(row))
+}
+
+/// Converts the image a row at a time, calling `row` with the index and bytes of each row padded
+/// to the stride.
+pub fn framebuffer_rows<F>(
+    bitmap: &Image,
+    options: &FramebufferOptions,
+    mut row: F,
+) -> Result<(), Error>
+where
+    F: FnMut(usize, &[u8]) -> Result<(), Error>,
+{
+    let format = options.format;
+    let bytes_per_pixel = format.bytes_per_pixel();
+    let mut buffer = vec![0u8; options.stride(bitmap.width)?];
+    for (y, pixels) in bitmap
+        .pixels
+        .chunks_exact(bitmap.width * bitmap.channels)
+        .take(bitmap.height)
+        .enumerate()
+    {
+        for (x, pixel) in pixels.chunks_exact(bitmap.channels).enumerate() {
+            format.pack(rgba(pixel, u8::MAX), &mut buffer[x * bytes_per_pixel..]);
+        }
+        row(y, &buffer)?;
+    }
+    Ok(())
+}
+
+/// Renders the lifehash straight from its color grid one row at a time, each module scaled to
+/// `module_size` pixels, so only a single row of the framebuffer is ever in memory. The rows are
+/// the same as those of [`LifeHash::image`] passed to [`framebuffer_rows`].
+pub fn render_framebuffer_rows<F>(
+    lifehash: &LifeHash,
+    module_size: usize,
+    options: &FramebufferOptions,
+    mut row: F,
+) -> Result<(), Error>
+where
+    F: FnMut(usize, &[u8]) -> Result<(), Error>,
+{
+    let grid = &lifehash.color_grid;
+    let (width, height) = (grid.dimensions.width, grid.dimensions.height);
+    if module_size == 0
+        || width
+            .max(height)
+            .checked_mul(module_size)
+            .is_none_or(|size| size > MAX_IMAGE_SIZE)
+    {
+        return Err(Error::new(ErrorKind::InvalidData, "Invalid Module Size"));
+    }
+    let format = options.format;
+    let bytes_per_pixel = format.bytes_per_pixel();
+    let mut buffer = vec![0u8; options.stride(width * module_size)?];
+    for y in 0..height {
+        for x in 0..width {
+            let color = grid.get_value(Point { x, y });
+            let pixel = [color.r, color.g, color.b].map(quantize8);
+            for i in 0..module_size {
+                let offset = (x * module_size + i) * bytes_per_pixel;
+                format.pack(
+                    [pixel[0], pixel[1], pixel[2], u8::MAX],
+                    &mut buffer[offset..],
+                );
+            }
+        }
+        for i in 0..module_size {
+            row(y * module_size + i, &buffer)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use crate::encoders::png::{encode_png_with, PngOptions};
+use crate::encoders::{rgba, ImageEncoder};
+use crate::Image;
+use std::io::{Error, ErrorKind, Write};
+
+const ICO_HEADER_SIZE: usize = 6;
+const ICO_ENTRY_SIZE: usize = 16;
+pub const ICO_MAX_SIZE: usize = 256;
+
+/// Windows icon encoder, stores the image as a single PNG compressed entry.
+pub struct IcoEncoder;
+impl ImageEncoder for IcoEncoder {
+    fn encode(&self, bitmap: &Image, writer: &mut dyn Write) -> Result<(), Error> {
+        encode_ico(std::slice::from_ref(bitmap), writer)
+    }
+}
+
+/// Writes a multi-resolution icon with one PNG compressed entry per image.
+pub fn encode_ico<W: Write>(images: &[Image], mut writer: W) -> Result<(), Error> {
+    if images.is_empty() || images.len() > u16::MAX as usize {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "An icon needs between 1 and 65535 images",
+        ));
+    }
+    let mut entries = Vec::with_capacity(images.len());
+    for image in images {
+        if image.width == 0
+            || image.height == 0
+            || image.width > ICO_MAX_SIZE
+            || image.height > ICO_MAX_SIZE
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Icon sizes must be between 1 and {ICO_MAX_SIZE} pixels"),
+            ));
+        }
+        // Not every reader handles PNG entries other than 32-bit RGBA.
+        let rgba_image = Image {
+            width: image.width,
+            height: image.height,
+            channels: 4,
+            pixels: image
+                .pixels
+                .chunks_exact(image.channels)
+                .flat_map(|pixel| rgba(pixel, u8::MAX))
+                .collect(),
+        };
+        let mut png = vec![];
+        let options = PngOptions {
+            reduce_grayscale: false,
+            ..PngOptions::default()
+        };
+        encode_png_with(&rgba_image, &mut png, &options)?;
+        entries.push((image.width, image.height, png));
+    }
+    let mut header = Vec::with_capacity(ICO_HEADER_SIZE + ICO_ENTRY_SIZE * entries.len());
+    header.extend(0u16.to_le_bytes());
+    // Icon resource type
+    header.extend(1u16.to_le_bytes());
+    header.extend((entries.len() as u16).to_le_bytes());
+    let mut offset = ICO_HEADER_SIZE + ICO_ENTRY_SIZE * entries.len();
+    for (width, height, png) in &entries {
+        // A size of 256 is stored as 0.
+        header.push(*width as u8);
+        header.push(*height as u8);
+        // No color palette, reserved
+        header.push(0);
+        header.push(0);
+        header.extend(1u16.to_le_bytes());
+        header.extend(32u16.to_le_bytes());
+        header.extend((png.len() as u32).to_le_bytes());
+        header.extend((offset as u32).to_le_bytes());
+        offset += png.len();
+    }
+    writer.write_all(&header)?;
+    for (_, _, png) in &entries {
+        writer.write_all(png)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use crate::grids::array::GridArray;
+use std::io::{Error, Write};
+
+/// Writes the array as a JSON object with the axis and field names, the shape and the values
+/// as nested arrays of that shape.
+pub fn encode_json<W: Write>(array: &GridArray, mut writer: W) -> Result<(), Error> {
+    let quote = |names: &mut dyn Iterator<Item = &str>| {
+        names
+            .map(|name| format!("\"{name}\""))
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    let shape = array.shape();
+    write!(
+        writer,
+        r#"{{"axes":[{}],"fields":[{}],"shape":[{}],"data":"#,
+        quote(&mut array.axes.iter().map(|(name, _)| *name)),
+        quote(&mut array.fields.iter().copied()),
+        shape
+            .iter()
+            .map(usize::to_string)
+            .collect::<Vec<_>>()
+            .join(",")
+    )?;
+    write_nested(array, &shape, 0, &mut writer)?;
+    writeln!(writer, "}}")
+}
+
+/// Writes the values from the flat index `start` nested by the dimensions in `shape`.
+fn write_nested<W: Write>(
+    array: &GridArray,
+    shape: &[usize],
+    start: usize,
+    writer: &mut W,
+) -> Result<(), Error> {
+    let Some((length, inner)) = shape.split_first() else {
+        return write!(writer, "{}", array.format_value(start));
+    };
+    let stride: usize = inner.iter().product();
+    write!(writer, "[")?;
+    for i in 0..*length {
+        if i > 0 {
+            write!(writer, ",")?;
+        }
+        write_nested(array, inner, start + i * stride, writer)?;
+    }
+    write!(writer, "]")
+}
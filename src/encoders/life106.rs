@@ -0,0 +1,14 @@
+use crate::grids::cell_grid::Cellgrid;
+use std::io::{Error, Write};
+
+/// Writes the grid as Life 1.06 text, one line with the `x y` coordinates of each live cell
+/// counted from the top left corner.
+pub fn encode_life106<W: Write>(grid: &Cellgrid, mut writer: W) -> Result<(), Error> {
+    writeln!(writer, "#Life 1.06")?;
+    for point in grid.get_points() {
+        if *grid.get_value(point) {
+            writeln!(writer, "{} {}", point.x, point.y)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,250 @@
+use crate::colors::cmyk::CmykProfile;
+use crate::encoders::bmp::BmpEncoder;
+use crate::encoders::csv::encode_csv;
+use crate::encoders::farbfeld::FarbfeldEncoder;
+use crate::encoders::ico::IcoEncoder;
+use crate::encoders::json::encode_json;
+use crate::encoders::life106::encode_life106;
+use crate::encoders::npy::encode_npy;
+use crate::encoders::obj::encode_obj;
+use crate::encoders::pdf::PdfEncoder;
+use crate::encoders::ply::encode_ply;
+use crate::encoders::png::{PngEncoder, PngOptions};
+use crate::encoders::pnm::{MonoEncoder, PbmEncoder, PnmEncoder};
+use crate::encoders::qoi::QoiEncoder;
+use crate::encoders::rle::encode_rle;
+use crate::encoders::stl::encode_stl;
+use crate::encoders::svg::SvgEncoder;
+use crate::encoders::tiff::TiffEncoder;
+use crate::encoders::webp::WebpEncoder;
+use crate::grids::array::GridArray;
+use crate::grids::cell_grid::Cellgrid;
+use crate::mesh::Mesh;
+use crate::utils::base64;
+use crate::Image;
+use clap::ValueEnum;
+use std::io::{Error, Write};
+use std::path::Path;
+
+pub mod bmp;
+pub mod csv;
+pub mod farbfeld;
+pub mod framebuffer;
+pub mod ico;
+pub mod json;
+pub mod life106;
+pub mod npy;
+pub mod obj;
+pub mod pdf;
+pub mod ply;
+pub mod png;
+pub mod pnm;
+pub mod print;
+pub mod qoi;
+pub mod rle;
+pub mod stl;
+pub mod svg;
+pub mod tiff;
+pub mod webp;
+
+pub trait ImageEncoder {
+    fn encode(&self, bitmap: &Image, writer: &mut dyn Write) -> Result<(), Error>;
+    /// Formats that can store more than 8 bits per channel override this, by default the low byte is dropped.
+    fn encode16(&self, bitmap: &Image<u16>, writer: &mut dyn Write) -> Result<(), Error> {
+        self.encode(&bitmap.to_u8(), writer)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, ValueEnum)]
+pub enum ImageFormat {
+    #[default]
+    Png,
+    /// Binary PPM, or PGM for grayscale images.
+    Ppm,
+    /// Plain text PPM, or PGM for grayscale images.
+    PpmAscii,
+    /// Binary 1-bit PBM, black where a pixel is darker than half.
+    Pbm,
+    /// Packed 1-bit rows without a header for display drivers, the leftmost pixel in the most
+    /// significant bit and 1 for black.
+    Mono,
+    Bmp,
+    Qoi,
+    Farbfeld,
+    /// Lossless WebP.
+    Webp,
+    /// Windows icon, with one entry per icon size.
+    Ico,
+    /// Scalable vector graphics, pixel exact unless rendered as contours.
+    Svg,
+    /// Single page PDF, pixel exact unless rendered as contours.
+    Pdf,
+    /// Uncompressed TIFF with the colors separated into CMYK inks for print.
+    Tiff,
+}
+impl ImageFormat {
+    /// Guesses the format from the file extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        Some(match extension.as_str() {
+            "png" => ImageFormat::Png,
+            "ppm" | "pgm" | "pnm" => ImageFormat::Ppm,
+            "pbm" => ImageFormat::Pbm,
+            "bmp" => ImageFormat::Bmp,
+            "qoi" => ImageFormat::Qoi,
+            "ff" | "farbfeld" => ImageFormat::Farbfeld,
+            "webp" => ImageFormat::Webp,
+            "ico" => ImageFormat::Ico,
+            "svg" => ImageFormat::Svg,
+            "pdf" => ImageFormat::Pdf,
+            "tif" | "tiff" => ImageFormat::Tiff,
+            _ => return None,
+        })
+    }
+    /// The media type of the format, as used in data URIs.
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Ppm | ImageFormat::PpmAscii => "image/x-portable-anymap",
+            ImageFormat::Pbm => "image/x-portable-bitmap",
+            ImageFormat::Mono => "application/octet-stream",
+            ImageFormat::Bmp => "image/bmp",
+            ImageFormat::Qoi => "image/qoi",
+            ImageFormat::Farbfeld => "image/x-farbfeld",
+            ImageFormat::Webp => "image/webp",
+            ImageFormat::Ico => "image/vnd.microsoft.icon",
+            ImageFormat::Svg => "image/svg+xml",
+            ImageFormat::Pdf => "application/pdf",
+            ImageFormat::Tiff => "image/tiff",
+        }
+    }
+    pub fn encoder(self, png_options: PngOptions) -> Box<dyn ImageEncoder> {
+        match self {
+            ImageFormat::Png => Box::new(PngEncoder {
+                options: png_options,
+            }),
+            ImageFormat::Ppm => Box::new(PnmEncoder { ascii: false }),
+            ImageFormat::PpmAscii => Box::new(PnmEncoder { ascii: true }),
+            ImageFormat::Pbm => Box::new(PbmEncoder),
+            ImageFormat::Mono => Box::new(MonoEncoder),
+            ImageFormat::Bmp => Box::new(BmpEncoder),
+            ImageFormat::Qoi => Box::new(QoiEncoder),
+            ImageFormat::Farbfeld => Box::new(FarbfeldEncoder),
+            ImageFormat::Webp => Box::new(WebpEncoder),
+            ImageFormat::Ico => Box::new(IcoEncoder),
+            ImageFormat::Svg => Box::new(SvgEncoder),
+            ImageFormat::Pdf => Box::new(PdfEncoder),
+            ImageFormat::Tiff => Box::new(TiffEncoder {
+                profile: CmykProfile::default(),
+            }),
+        }
+    }
+}
+
+/// 3D mesh formats for reliefs.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum MeshFormat {
+    /// Binary STL, without colors.
+    Stl,
+    /// Wavefront OBJ text, with optional vertex colors.
+    Obj,
+    /// Binary PLY, with optional vertex colors.
+    Ply,
+}
+impl MeshFormat {
+    /// Guesses the format from the file extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        Some(match extension.as_str() {
+            "stl" => MeshFormat::Stl,
+            "obj" => MeshFormat::Obj,
+            "ply" => MeshFormat::Ply,
+            _ => return None,
+        })
+    }
+    pub fn encode(self, mesh: &Mesh, writer: &mut dyn Write) -> Result<(), Error> {
+        match self {
+            MeshFormat::Stl => encode_stl(mesh, writer),
+            MeshFormat::Obj => encode_obj(mesh, writer),
+            MeshFormat::Ply => encode_ply(mesh, writer),
+        }
+    }
+}
+
+/// Formats for numeric grid arrays.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, ValueEnum)]
+pub enum DataFormat {
+    /// Comma separated values with a header, one line per grid position.
+    #[default]
+    Csv,
+    /// A JSON object with the values as nested arrays.
+    Json,
+    /// A NumPy array.
+    Npy,
+}
+impl DataFormat {
+    /// Guesses the format from the file extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        Some(match extension.as_str() {
+            "csv" => DataFormat::Csv,
+            "json" => DataFormat::Json,
+            "npy" => DataFormat::Npy,
+            _ => return None,
+        })
+    }
+    pub fn encode(self, array: &GridArray, writer: &mut dyn Write) -> Result<(), Error> {
+        match self {
+            DataFormat::Csv => encode_csv(array, writer),
+            DataFormat::Json => encode_json(array, writer),
+            DataFormat::Npy => encode_npy(array, writer),
+        }
+    }
+}
+
+/// Formats for Life patterns.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum LifeFormat {
+    /// Run length encoded, with the toroidal bounds of the grid.
+    Rle,
+    /// Life 1.06, the coordinates of each live cell.
+    Life106,
+}
+impl LifeFormat {
+    /// Guesses the format from the file extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        Some(match extension.as_str() {
+            "rle" => LifeFormat::Rle,
+            "lif" | "life" => LifeFormat::Life106,
+            _ => return None,
+        })
+    }
+    /// Writes the grid, with the comments where the format has room for them.
+    pub fn encode(
+        self,
+        grid: &Cellgrid,
+        comments: &[String],
+        writer: &mut dyn Write,
+    ) -> Result<(), Error> {
+        match self {
+            LifeFormat::Rle => encode_rle(grid, comments, writer),
+            LifeFormat::Life106 => encode_life106(grid, writer),
+        }
+    }
+}
+
+/// A `data:` URI with the base64 encoded data, for inlining into HTML, CSS or JSON.
+pub fn data_uri(mime_type: &str, data: &[u8]) -> String {
+    format!("data:{mime_type};base64,{}", base64::encode(data))
+}
+
+/// Expands a pixel of any channel count to RGBA.
+pub(crate) fn rgba<T: Copy>(pixel: &[T], opaque: T) -> [T; 4] {
+    match pixel.len() {
+        1 => [pixel[0], pixel[0], pixel[0], opaque],
+        2 => [pixel[0], pixel[0], pixel[0], pixel[1]],
+        3 => [pixel[0], pixel[1], pixel[2], opaque],
+        _ => [pixel[0], pixel[1], pixel[2], pixel[3]],
+    }
+}
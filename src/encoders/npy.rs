@@ -0,0 +1,49 @@
+use crate::grids::array::{ArrayValues, GridArray};
+use std::io::{Error, ErrorKind, Write};
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+/// The header is padded so the data starts at a multiple of this.
+const ALIGNMENT: usize = 64;
+
+/// Writes the array in the NumPy `.npy` format version 1.0, as little endian 64-bit floats or
+/// booleans.
+pub fn encode_npy<W: Write>(array: &GridArray, mut writer: W) -> Result<(), Error> {
+    let descr = match array.values {
+        ArrayValues::Float(_) => "<f8",
+        ArrayValues::Bits(_) => "|b1",
+    };
+    let shape = array.shape();
+    // One dimensional shapes are tuples with a trailing comma.
+    let shape = match shape.as_slice() {
+        [length] => format!("({length},)"),
+        _ => format!(
+            "({})",
+            shape
+                .iter()
+                .map(usize::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut header = format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': {shape}, }}");
+    // Magic, version and header length come first, and the header ends in a newline.
+    let unpadded = MAGIC.len() + 4 + header.len() + 1;
+    header.push_str(&" ".repeat(unpadded.next_multiple_of(ALIGNMENT) - unpadded));
+    header.push('\n');
+    let header_length = u16::try_from(header.len())
+        .map_err(|_| Error::new(ErrorKind::InvalidInput, "Array shape too long for NPY"))?;
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[1, 0])?;
+    writer.write_all(&header_length.to_le_bytes())?;
+    writer.write_all(header.as_bytes())?;
+    match &array.values {
+        ArrayValues::Float(values) => {
+            let data: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+            writer.write_all(&data)
+        }
+        ArrayValues::Bits(values) => {
+            let data: Vec<u8> = values.iter().map(|v| u8::from(*v)).collect();
+            writer.write_all(&data)
+        }
+    }
+}
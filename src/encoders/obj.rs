@@ -0,0 +1,28 @@
+use crate::mesh::Mesh;
+use crate::vector::number;
+use std::io::{Error, Write};
+
+/// Writes the mesh as Wavefront OBJ text. Vertex colors are written as the widely supported
+/// `v x y z r g b` extension.
+pub fn encode_obj<W: Write>(mesh: &Mesh, mut writer: W) -> Result<(), Error> {
+    writeln!(writer, "# LifeHash relief, in millimeters")?;
+    for (i, [x, y, z]) in mesh.vertices.iter().enumerate() {
+        write!(writer, "v {} {} {}", number(*x), number(*y), number(*z))?;
+        if let Some(colors) = &mesh.colors {
+            let color = colors[i];
+            write!(
+                writer,
+                " {} {} {}",
+                number(color.r),
+                number(color.g),
+                number(color.b)
+            )?;
+        }
+        writeln!(writer)?;
+    }
+    for [a, b, c] in &mesh.triangles {
+        // Indices start at 1.
+        writeln!(writer, "f {} {} {}", a + 1, b + 1, c + 1)?;
+    }
+    Ok(())
+}
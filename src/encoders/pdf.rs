@@ -0,0 +1,170 @@
+use crate::colors::cmyk::CmykProfile;
+use crate::encoders::ImageEncoder;
+use crate::vector::{number, VectorImage};
+use crate::Image;
+use std::fmt::Write as _;
+use std::io::{Error, ErrorKind, Write};
+
+/// Writes the image as a single page PDF of vector rectangles, one point per pixel.
+pub struct PdfEncoder;
+impl ImageEncoder for PdfEncoder {
+    fn encode(&self, bitmap: &Image, writer: &mut dyn Write) -> Result<(), Error> {
+        encode_pdf(&VectorImage::from_image(bitmap), 1.0, writer)
+    }
+}
+
+/// Writes the vector image as a single page PDF, `scale` is the number of points per unit.
+pub fn encode_pdf<W: Write>(image: &VectorImage, scale: f64, mut writer: W) -> Result<(), Error> {
+    let width = image.width * scale;
+    let height = image.height * scale;
+    let mut pdf = PdfWriter::new(4);
+    pdf.object(1, "<< /Type /Catalog /Pages 2 0 R >>");
+    pdf.object(2, "<< /Type /Pages /Kids [3 0 R] /Count 1 >>");
+    let (content, opacities) = page_content(image, scale, None);
+    let mut states = String::new();
+    for (i, opacity) in opacities.iter().enumerate() {
+        write!(states, " /G{i} << /ca {} >>", number(*opacity)).unwrap();
+    }
+    pdf.object(
+        3,
+        &format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /ExtGState <<{states} >> >> /Contents 4 0 R >>",
+            number(width),
+            number(height)
+        ),
+    );
+    pdf.stream(4, "", content.as_bytes());
+    writer.write_all(&pdf.finish(1, None)?)
+}
+
+/// Draws the shapes in unit coordinates with the origin at the top left, returns the content and
+/// the opacities of the graphics states it refers to. With a profile the colors are separated into
+/// CMYK inks instead of written as RGB.
+pub(crate) fn page_content(
+    image: &VectorImage,
+    scale: f64,
+    cmyk: Option<&CmykProfile>,
+) -> (String, Vec<f64>) {
+    let mut content = String::new();
+    let mut opacities: Vec<f64> = vec![];
+    writeln!(
+        content,
+        "q {} 0 0 {} 0 {} cm",
+        number(scale),
+        number(-scale),
+        number(image.height * scale)
+    )
+    .unwrap();
+    writeln!(
+        content,
+        "0 0 {} {} re W n",
+        number(image.width),
+        number(image.height)
+    )
+    .unwrap();
+    for shape in &image.shapes {
+        if shape.opacity < 1.0 {
+            let state = match opacities.iter().position(|o| *o == shape.opacity) {
+                Some(state) => state,
+                None => {
+                    opacities.push(shape.opacity);
+                    opacities.len() - 1
+                }
+            };
+            writeln!(content, "q /G{state} gs").unwrap();
+        }
+        let color = shape.color;
+        if let Some(profile) = cmyk {
+            let inks = profile.separate(color);
+            writeln!(
+                content,
+                "{} {} {} {} k",
+                number(inks.c),
+                number(inks.m),
+                number(inks.y),
+                number(inks.k)
+            )
+            .unwrap();
+        } else {
+            writeln!(
+                content,
+                "{} {} {} rg",
+                number(color.r),
+                number(color.g),
+                number(color.b)
+            )
+            .unwrap();
+        }
+        for path in &shape.paths {
+            for (i, (x, y)) in path.iter().enumerate() {
+                let operator = if i == 0 { "m" } else { "l" };
+                writeln!(content, "{} {} {operator}", number(*x), number(*y)).unwrap();
+            }
+            writeln!(content, "h").unwrap();
+        }
+        writeln!(content, "f*").unwrap();
+        if shape.opacity < 1.0 {
+            writeln!(content, "Q").unwrap();
+        }
+    }
+    writeln!(content, "Q").unwrap();
+    (content, opacities)
+}
+
+/// Assembles numbered PDF objects and the cross-reference table that locates them.
+pub(crate) struct PdfWriter {
+    buffer: Vec<u8>,
+    offsets: Vec<Option<usize>>,
+}
+impl PdfWriter {
+    pub(crate) fn new(objects: usize) -> Self {
+        Self {
+            // The comment with high bytes marks the file as binary for transfer tools.
+            buffer: b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec(),
+            offsets: vec![None; objects],
+        }
+    }
+    pub(crate) fn object(&mut self, number: usize, body: &str) {
+        self.offsets[number - 1] = Some(self.buffer.len());
+        self.buffer
+            .extend_from_slice(format!("{number} 0 obj\n{body}\nendobj\n").as_bytes());
+    }
+    /// Adds a stream object, `dictionary` holds any entries besides the length.
+    pub(crate) fn stream(&mut self, number: usize, dictionary: &str, data: &[u8]) {
+        self.offsets[number - 1] = Some(self.buffer.len());
+        self.buffer.extend_from_slice(
+            format!(
+                "{number} 0 obj\n<< /Length {}{dictionary} >>\nstream\n",
+                data.len()
+            )
+            .as_bytes(),
+        );
+        self.buffer.extend_from_slice(data);
+        self.buffer.extend_from_slice(b"\nendstream\nendobj\n");
+    }
+    pub(crate) fn finish(mut self, root: usize, info: Option<usize>) -> Result<Vec<u8>, Error> {
+        let xref_offset = self.buffer.len();
+        let mut xref = format!("xref\n0 {}\n0000000000 65535 f \n", self.offsets.len() + 1);
+        for (i, offset) in self.offsets.iter().enumerate() {
+            let offset = offset.ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("PDF object {} missing", i + 1),
+                )
+            })?;
+            writeln!(xref, "{offset:010} 00000 n ").unwrap();
+        }
+        write!(
+            xref,
+            "trailer\n<< /Size {} /Root {root} 0 R",
+            self.offsets.len() + 1
+        )
+        .unwrap();
+        if let Some(info) = info {
+            write!(xref, " /Info {info} 0 R").unwrap();
+        }
+        write!(xref, " >>\nstartxref\n{xref_offset}\n%%EOF\n").unwrap();
+        self.buffer.extend_from_slice(xref.as_bytes());
+        Ok(self.buffer)
+    }
+}
@@ -0,0 +1,45 @@
+use crate::lifehash::quantize8;
+use crate::mesh::Mesh;
+use std::io::{Error, ErrorKind, Write};
+
+/// Writes the mesh as binary little endian PLY, with 8-bit vertex colors if it is colored.
+pub fn encode_ply<W: Write>(mesh: &Mesh, mut writer: W) -> Result<(), Error> {
+    if u32::try_from(mesh.vertices.len()).is_err() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Mesh too large for PLY",
+        ));
+    }
+    writeln!(writer, "ply")?;
+    writeln!(writer, "format binary_little_endian 1.0")?;
+    writeln!(writer, "comment LifeHash relief, in millimeters")?;
+    writeln!(writer, "element vertex {}", mesh.vertices.len())?;
+    for axis in ["x", "y", "z"] {
+        writeln!(writer, "property float {axis}")?;
+    }
+    if mesh.colors.is_some() {
+        for channel in ["red", "green", "blue"] {
+            writeln!(writer, "property uchar {channel}")?;
+        }
+    }
+    writeln!(writer, "element face {}", mesh.triangles.len())?;
+    writeln!(writer, "property list uchar uint vertex_indices")?;
+    writeln!(writer, "end_header")?;
+    let mut data = Vec::with_capacity(mesh.vertices.len() * 15 + mesh.triangles.len() * 13);
+    for (i, vertex) in mesh.vertices.iter().enumerate() {
+        for c in vertex {
+            data.extend((*c as f32).to_le_bytes());
+        }
+        if let Some(colors) = &mesh.colors {
+            let color = colors[i];
+            data.extend([color.r, color.g, color.b].map(quantize8));
+        }
+    }
+    for triangle in &mesh.triangles {
+        data.push(3);
+        for i in triangle {
+            data.extend((*i as u32).to_le_bytes());
+        }
+    }
+    writer.write_all(&data)
+}
@@ -0,0 +1,311 @@
+use crate::encoders::{data_uri, ImageEncoder};
+use crate::render::dither::MonoImage;
+use crate::Image;
+use clap::ValueEnum;
+use png::{
+    AdaptiveFilterType, BitDepth, ColorType, Compression, Decoder, Encoder, FilterType,
+    Transformations,
+};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{Error, Read, Write};
+
+/// A channel type that can be stored in a PNG.
+pub trait PngSample: Copy + PartialEq {
+    const BIT_DEPTH: BitDepth;
+    /// The samples in PNG byte order (big endian).
+    fn to_png_bytes(samples: &[Self]) -> Cow<'_, [u8]>;
+    /// The palette form of the image, if it can be stored as one.
+    fn to_indexed(bitmap: &Image<Self>) -> Option<IndexedImage>;
+    /// Reads decoded PNG samples of 8 or 16 bits, converting them to this depth.
+    fn from_png_bytes(bytes: &[u8], bit_depth: BitDepth) -> Vec<Self>;
+}
+impl PngSample for u8 {
+    const BIT_DEPTH: BitDepth = BitDepth::Eight;
+    fn to_png_bytes(samples: &[Self]) -> Cow<'_, [u8]> {
+        Cow::Borrowed(samples)
+    }
+    fn to_indexed(bitmap: &Image<Self>) -> Option<IndexedImage> {
+        IndexedImage::from_image(bitmap)
+    }
+    fn from_png_bytes(bytes: &[u8], bit_depth: BitDepth) -> Vec<Self> {
+        match bit_depth {
+            BitDepth::Sixteen => bytes.chunks_exact(2).map(|b| b[0]).collect(),
+            _ => bytes.to_vec(),
+        }
+    }
+}
+impl PngSample for u16 {
+    const BIT_DEPTH: BitDepth = BitDepth::Sixteen;
+    fn to_png_bytes(samples: &[Self]) -> Cow<'_, [u8]> {
+        Cow::Owned(samples.iter().flat_map(|s| s.to_be_bytes()).collect())
+    }
+    fn to_indexed(_: &Image<Self>) -> Option<IndexedImage> {
+        None
+    }
+    fn from_png_bytes(bytes: &[u8], bit_depth: BitDepth) -> Vec<Self> {
+        match bit_depth {
+            BitDepth::Sixteen => bytes
+                .chunks_exact(2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]]))
+                .collect(),
+            _ => bytes.iter().map(|b| *b as u16 * 257).collect(),
+        }
+    }
+}
+
+/// An image stored as a palette of at most 256 colors and one palette index per pixel.
+pub struct IndexedImage {
+    pub width: usize,
+    pub height: usize,
+    pub channels: usize,
+    pub palette: Vec<Vec<u8>>,
+    pub indices: Vec<u8>,
+}
+impl IndexedImage {
+    /// Builds the palette from the distinct colors of the image, in order of first appearance.
+    /// Returns None if the image has more than 256 colors.
+    pub fn from_image(bitmap: &Image) -> Option<Self> {
+        let mut lookup: HashMap<&[u8], u8> = HashMap::new();
+        let mut palette = vec![];
+        let mut indices = Vec::with_capacity(bitmap.width * bitmap.height);
+        for pixel in bitmap.pixels.chunks_exact(bitmap.channels) {
+            let index = match lookup.get(pixel) {
+                Some(index) => *index,
+                None => {
+                    if palette.len() == 256 {
+                        return None;
+                    }
+                    let index = palette.len() as u8;
+                    lookup.insert(pixel, index);
+                    palette.push(pixel.to_vec());
+                    index
+                }
+            };
+            indices.push(index);
+        }
+        Some(Self {
+            width: bitmap.width,
+            height: bitmap.height,
+            channels: bitmap.channels,
+            palette,
+            indices,
+        })
+    }
+    /// The smallest PNG bit depth that can address every palette entry.
+    pub fn bit_depth(&self) -> u8 {
+        match self.palette.len() {
+            0..=2 => 1,
+            3..=4 => 2,
+            5..=16 => 4,
+            _ => 8,
+        }
+    }
+    /// The indices packed into rows at the given bit depth, each row starting on a byte boundary.
+    pub fn packed_rows(&self, bit_depth: u8) -> Vec<u8> {
+        if self.width == 0 {
+            return vec![];
+        }
+        let per_byte = 8 / bit_depth as usize;
+        let row_bytes = self.width.div_ceil(per_byte);
+        let mut packed = vec![0u8; row_bytes * self.height];
+        for (y, row) in self.indices.chunks_exact(self.width).enumerate() {
+            for (x, index) in row.iter().enumerate() {
+                let shift = 8 - bit_depth as usize * (x % per_byte + 1);
+                packed[y * row_bytes + x / per_byte] |= index << shift;
+            }
+        }
+        packed
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, ValueEnum)]
+pub enum PngCompression {
+    Fast,
+    #[default]
+    Default,
+    Best,
+}
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, ValueEnum)]
+pub enum PngFilter {
+    None,
+    #[default]
+    Sub,
+    Up,
+    Avg,
+    Paeth,
+    /// Pick the best filter for every row.
+    Adaptive,
+}
+
+#[derive(Copy, Clone)]
+pub struct PngOptions {
+    /// Store images where every pixel is gray in a single channel instead of three.
+    pub reduce_grayscale: bool,
+    /// Store images with at most 256 colors as indexed color, falls back to RGB for larger palettes.
+    pub palette: bool,
+    pub compression: PngCompression,
+    pub filter: PngFilter,
+}
+impl Default for PngOptions {
+    fn default() -> Self {
+        Self {
+            reduce_grayscale: true,
+            palette: false,
+            compression: PngCompression::default(),
+            filter: PngFilter::default(),
+        }
+    }
+}
+
+pub struct PngEncoder {
+    pub options: PngOptions,
+}
+impl ImageEncoder for PngEncoder {
+    fn encode(&self, bitmap: &Image, writer: &mut dyn Write) -> Result<(), Error> {
+        encode_png_with(bitmap, writer, &self.options)
+    }
+    fn encode16(&self, bitmap: &Image<u16>, writer: &mut dyn Write) -> Result<(), Error> {
+        encode_png_with(bitmap, writer, &self.options)
+    }
+}
+
+pub fn encode_png<T: PngSample, W: Write>(bitmap: &Image<T>, writer: W) -> Result<(), Error> {
+    encode_png_with(bitmap, writer, &PngOptions::default())
+}
+
+pub fn encode_png_with<T: PngSample, W: Write>(
+    bitmap: &Image<T>,
+    writer: W,
+    options: &PngOptions,
+) -> Result<(), Error> {
+    if options.palette {
+        if let Some(indexed) = T::to_indexed(bitmap) {
+            return write_indexed_png(&indexed, writer, options);
+        }
+    }
+    if options.reduce_grayscale && bitmap.channels >= 3 && bitmap.is_grayscale() {
+        return write_png(&bitmap.to_grayscale(), writer, options);
+    }
+    write_png(bitmap, writer, options)
+}
+
+pub fn encode_png_to_vec<T: PngSample>(bitmap: &Image<T>) -> Result<Vec<u8>, Error> {
+    let mut buffer = vec![];
+    encode_png(bitmap, &mut buffer)?;
+    Ok(buffer)
+}
+
+/// The PNG encoded image as a `data:image/png;base64,...` URI.
+pub fn encode_png_to_data_uri<T: PngSample>(bitmap: &Image<T>) -> Result<String, Error> {
+    Ok(data_uri("image/png", &encode_png_to_vec(bitmap)?))
+}
+
+/// Writes a black and white image as a 1-bit grayscale PNG.
+pub fn encode_mono_png<W: Write>(image: &MonoImage, writer: W) -> Result<(), Error> {
+    let mut png = Encoder::new(writer, image.width as u32, image.height as u32);
+    png.set_color(ColorType::Grayscale);
+    png.set_depth(BitDepth::One);
+    configure(&mut png, &PngOptions::default());
+    let mut writer = png.write_header()?;
+    // In 1-bit grayscale 0 is black.
+    let rows: Vec<u8> = image.packed_rows().iter().map(|byte| !byte).collect();
+    writer.write_image_data(&rows)?;
+    Ok(writer.finish()?)
+}
+
+/// Decodes a PNG to grayscale, grayscale with alpha, RGB or RGBA. Palettes, transparency chunks
+/// and bit depths below 8 are expanded.
+pub fn decode_png<T: PngSample, R: Read>(reader: R) -> Result<Image<T>, Error> {
+    let mut decoder = Decoder::new(reader);
+    decoder.set_transformations(Transformations::EXPAND);
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    buffer.truncate(info.buffer_size());
+    Ok(Image {
+        width: info.width as usize,
+        height: info.height as usize,
+        channels: info.color_type.samples(),
+        pixels: T::from_png_bytes(&buffer, info.bit_depth),
+    })
+}
+
+/// The bit depth of the PNG samples, read from the header.
+pub fn png_bit_depth<R: Read>(reader: R) -> Result<u8, Error> {
+    Ok(Decoder::new(reader).read_info()?.info().bit_depth as u8)
+}
+
+fn configure<W: Write>(png: &mut Encoder<W>, options: &PngOptions) {
+    png.set_compression(match options.compression {
+        PngCompression::Fast => Compression::Fast,
+        PngCompression::Default => Compression::Default,
+        PngCompression::Best => Compression::Best,
+    });
+    match options.filter {
+        PngFilter::None => png.set_filter(FilterType::NoFilter),
+        PngFilter::Sub => png.set_filter(FilterType::Sub),
+        PngFilter::Up => png.set_filter(FilterType::Up),
+        PngFilter::Avg => png.set_filter(FilterType::Avg),
+        PngFilter::Paeth => png.set_filter(FilterType::Paeth),
+        PngFilter::Adaptive => png.set_adaptive_filter(AdaptiveFilterType::Adaptive),
+    }
+}
+
+fn write_png<T: PngSample, W: Write>(
+    bitmap: &Image<T>,
+    writer: W,
+    options: &PngOptions,
+) -> Result<(), Error> {
+    let mut png = Encoder::new(writer, bitmap.width as u32, bitmap.height as u32);
+    png.set_color(match bitmap.channels {
+        1 => ColorType::Grayscale,
+        2 => ColorType::GrayscaleAlpha,
+        4 => ColorType::Rgba,
+        _ => ColorType::Rgb,
+    });
+    png.set_depth(T::BIT_DEPTH);
+    configure(&mut png, options);
+    let mut writer = png.write_header()?;
+    writer.write_image_data(&T::to_png_bytes(&bitmap.pixels))?;
+    Ok(writer.finish()?)
+}
+
+fn write_indexed_png<W: Write>(
+    indexed: &IndexedImage,
+    writer: W,
+    options: &PngOptions,
+) -> Result<(), Error> {
+    let mut palette = Vec::with_capacity(indexed.palette.len() * 3);
+    let mut trns = Vec::with_capacity(indexed.palette.len());
+    for color in &indexed.palette {
+        match indexed.channels {
+            1 | 2 => palette.extend([color[0]; 3]),
+            _ => palette.extend(&color[0..3]),
+        }
+        match indexed.channels {
+            2 => trns.push(color[1]),
+            4 => trns.push(color[3]),
+            _ => {}
+        }
+    }
+    let bit_depth = indexed.bit_depth();
+    let mut png = Encoder::new(writer, indexed.width as u32, indexed.height as u32);
+    png.set_color(ColorType::Indexed);
+    png.set_depth(match bit_depth {
+        1 => BitDepth::One,
+        2 => BitDepth::Two,
+        4 => BitDepth::Four,
+        _ => BitDepth::Eight,
+    });
+    png.set_palette(palette);
+    // Only store transparency when some palette entry isn't fully opaque.
+    if trns.iter().any(|a| *a != u8::MAX) {
+        png.set_trns(trns);
+    }
+    configure(&mut png, options);
+    let mut writer = png.write_header()?;
+    writer.write_image_data(&indexed.packed_rows(bit_depth))?;
+    Ok(writer.finish()?)
+}
@@ -0,0 +1,94 @@
+use crate::encoders::ImageEncoder;
+use crate::render::dither::MonoImage;
+use crate::Image;
+use std::io::{Error, Write};
+
+/// Netpbm encoder. Writes PGM for grayscale images and PPM otherwise, alpha is dropped.
+pub struct PnmEncoder {
+    pub ascii: bool,
+}
+impl ImageEncoder for PnmEncoder {
+    fn encode(&self, bitmap: &Image, writer: &mut dyn Write) -> Result<(), Error> {
+        write_pnm(bitmap, writer, self.ascii, u8::MAX as u32)
+    }
+    fn encode16(&self, bitmap: &Image<u16>, writer: &mut dyn Write) -> Result<(), Error> {
+        write_pnm(bitmap, writer, self.ascii, u16::MAX as u32)
+    }
+}
+
+/// Binary PBM encoder, black where a pixel is darker than half.
+pub struct PbmEncoder;
+impl ImageEncoder for PbmEncoder {
+    fn encode(&self, bitmap: &Image, writer: &mut dyn Write) -> Result<(), Error> {
+        encode_pbm(&MonoImage::threshold(bitmap), writer)
+    }
+}
+
+/// Packed 1-bit rows without a header, for display drivers, black where a pixel is darker than
+/// half.
+pub struct MonoEncoder;
+impl ImageEncoder for MonoEncoder {
+    fn encode(&self, bitmap: &Image, writer: &mut dyn Write) -> Result<(), Error> {
+        writer.write_all(&MonoImage::threshold(bitmap).packed_rows())
+    }
+}
+
+/// Writes a black and white image as a binary PBM.
+pub fn encode_pbm<W: Write>(image: &MonoImage, mut writer: W) -> Result<(), Error> {
+    write!(writer, "P4\n{} {}\n", image.width, image.height)?;
+    writer.write_all(&image.packed_rows())
+}
+
+fn write_pnm<T: Copy + PartialEq + Into<u32>>(
+    bitmap: &Image<T>,
+    writer: &mut dyn Write,
+    ascii: bool,
+    max_value: u32,
+) -> Result<(), Error> {
+    let grayscale = bitmap.is_grayscale();
+    let magic = match (grayscale, ascii) {
+        (true, false) => "P5",
+        (false, false) => "P6",
+        (true, true) => "P2",
+        (false, true) => "P3",
+    };
+    write!(
+        writer,
+        "{magic}\n{} {}\n{max_value}\n",
+        bitmap.width, bitmap.height
+    )?;
+    let components = if grayscale { 1 } else { 3 };
+    for row in bitmap
+        .pixels
+        .chunks_exact(bitmap.width * bitmap.channels)
+        .take(bitmap.height)
+    {
+        let samples = row
+            .chunks_exact(bitmap.channels)
+            .flat_map(|pixel| pixel[..components].iter().map(|s| (*s).into()));
+        let mut line = vec![];
+        if ascii {
+            // Plain PNM lines should not be longer than 70 characters.
+            let mut line_length = 0;
+            for sample in samples {
+                let text = sample.to_string();
+                if line_length > 0 && line_length + text.len() + 1 > 70 {
+                    line.push(b'\n');
+                    line_length = 0;
+                } else if line_length > 0 {
+                    line.push(b' ');
+                    line_length += 1;
+                }
+                line.extend(text.as_bytes());
+                line_length += text.len();
+            }
+            line.push(b'\n');
+        } else if max_value > u8::MAX as u32 {
+            line.extend(samples.flat_map(|s: u32| (s as u16).to_be_bytes()));
+        } else {
+            line.extend(samples.map(|s: u32| s as u8));
+        }
+        writer.write_all(&line)?;
+    }
+    Ok(())
+}
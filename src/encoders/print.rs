@@ -0,0 +1,195 @@
+use crate::colors::cmyk::CmykProfile;
+use crate::encoders::pdf::{page_content, PdfWriter};
+use crate::lifehash::LifeHash;
+use crate::render::card::fingerprint;
+use crate::vector::{number, VectorImage};
+use std::fmt::Write as _;
+use std::io::{Error, ErrorKind, Write};
+
+/// PDF points per inch.
+const POINTS_PER_INCH: f64 = 72.0;
+/// Size of the caption text in points.
+const FONT_SIZE: f64 = 8.0;
+/// Width of a Courier glyph as a fraction of the font size.
+const GLYPH_WIDTH: f64 = 0.6;
+/// Space between the image and the edge of the page when there are crop marks, in points.
+const MARK_MARGIN: f64 = 18.0;
+/// Space between a crop mark and the corner it marks, in points.
+const MARK_GAP: f64 = 3.0;
+const MARK_LENGTH: f64 = 12.0;
+const MARK_WIDTH: f64 = 0.25;
+
+/// A lifehash on a PDF page at an exact physical size, for printed backups.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrintOptions {
+    /// Width and height of the lifehash in points.
+    pub size: f64,
+    /// Text below the lifehash, in Courier.
+    pub caption: Option<String>,
+    /// Marks around the corners of the lifehash to cut along.
+    pub crop_marks: bool,
+    /// Separates the colors into CMYK inks with the profile instead of writing them as RGB.
+    pub cmyk: Option<CmykProfile>,
+}
+
+/// Reads a length like `40mm`, `4cm`, `1.5in` or `100pt` as points.
+pub fn parse_length(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(text.len());
+    let (value, unit) = text.split_at(split);
+    let value: f64 = value.trim().parse().ok()?;
+    let points = match unit.to_ascii_lowercase().as_str() {
+        "mm" => value / 25.4 * POINTS_PER_INCH,
+        "cm" => value / 2.54 * POINTS_PER_INCH,
+        "in" => value * POINTS_PER_INCH,
+        "pt" => value,
+        _ => return None,
+    };
+    (points.is_finite() && points > 0.0).then_some(points)
+}
+
+/// Writes the color grid of the lifehash as a single page PDF of vector rectangles at the size
+/// of the options. The page has room for the crop marks and the caption, and the lifehash is its
+/// trim box. The digest, version and fingerprint are in the document information.
+pub fn encode_print_pdf<W: Write>(
+    lifehash: &LifeHash,
+    options: &PrintOptions,
+    mut writer: W,
+) -> Result<(), Error> {
+    if !(options.size.is_finite() && options.size > 0.0) {
+        return Err(Error::new(ErrorKind::InvalidInput, "Invalid print size"));
+    }
+    let image = VectorImage::from_image(&lifehash.image(1, false)?);
+    let scale = options.size / image.width;
+    let margin = if options.crop_marks { MARK_MARGIN } else { 0.0 };
+    let caption = options.caption.as_deref().map(pdf_text);
+    let caption_width = caption.as_ref().map_or(0.0, |text| {
+        text.chars().count() as f64 * FONT_SIZE * GLYPH_WIDTH
+    });
+    // The caption is one line below the image, with half a line of space around it.
+    let caption_height = if caption.is_some() {
+        FONT_SIZE * 2.0
+    } else {
+        0.0
+    };
+    let width = options.size.max(caption_width) + 2.0 * margin;
+    let height = options.size + caption_height + 2.0 * margin;
+    let left = (width - options.size) / 2.0;
+    let bottom = margin + caption_height;
+
+    let mut content = String::new();
+    writeln!(content, "q 1 0 0 1 {} {} cm", number(left), number(bottom)).unwrap();
+    let (image_content, opacities) = page_content(&image, scale, options.cmyk.as_ref());
+    content.push_str(&image_content);
+    writeln!(content, "Q").unwrap();
+    if options.crop_marks {
+        // Crop marks are in the registration color, which prints on every plate.
+        let stroke = if options.cmyk.is_some() {
+            "1 1 1 1 K"
+        } else {
+            "0 0 0 RG"
+        };
+        writeln!(content, "{stroke} {} w", number(MARK_WIDTH)).unwrap();
+        let right = left + options.size;
+        let top = bottom + options.size;
+        for (x, y, dx, dy) in [
+            (left, bottom, -1.0, -1.0),
+            (right, bottom, 1.0, -1.0),
+            (left, top, -1.0, 1.0),
+            (right, top, 1.0, 1.0),
+        ] {
+            // One horizontal and one vertical mark pointing away from each corner.
+            let (near, far) = (MARK_GAP, MARK_GAP + MARK_LENGTH);
+            for (x0, y0, x1, y1) in [
+                (x + dx * near, y, x + dx * far, y),
+                (x, y + dy * near, x, y + dy * far),
+            ] {
+                writeln!(
+                    content,
+                    "{} {} m {} {} l",
+                    number(x0),
+                    number(y0),
+                    number(x1),
+                    number(y1)
+                )
+                .unwrap();
+            }
+        }
+        writeln!(content, "S").unwrap();
+    }
+    if let Some(text) = &caption {
+        writeln!(
+            content,
+            "{} BT /F1 {} Tf {} {} Td ({}) Tj ET",
+            if options.cmyk.is_some() {
+                "0 0 0 1 k"
+            } else {
+                "0 g"
+            },
+            number(FONT_SIZE),
+            number((width - caption_width) / 2.0),
+            number(margin + FONT_SIZE * 0.75),
+            pdf_string(text)
+        )
+        .unwrap();
+    }
+
+    let mut pdf = PdfWriter::new(6);
+    pdf.object(1, "<< /Type /Catalog /Pages 2 0 R >>");
+    pdf.object(2, "<< /Type /Pages /Kids [3 0 R] /Count 1 >>");
+    let mut states = String::new();
+    for (i, opacity) in opacities.iter().enumerate() {
+        write!(states, " /G{i} << /ca {} >>", number(*opacity)).unwrap();
+    }
+    pdf.object(
+        3,
+        &format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /TrimBox [{} {} {} {}] /Resources << /Font << /F1 5 0 R >> /ExtGState <<{states} >> >> /Contents 4 0 R >>",
+            number(width),
+            number(height),
+            number(left),
+            number(bottom),
+            number(left + options.size),
+            number(bottom + options.size)
+        ),
+    );
+    pdf.stream(4, "", content.as_bytes());
+    // Courier is one of the standard fonts every PDF reader has, so nothing is embedded.
+    pdf.object(
+        5,
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Courier /Encoding /WinAnsiEncoding >>",
+    );
+    let digest = hex::encode(&lifehash.digest);
+    pdf.object(
+        6,
+        &format!(
+            "<< /Title ({}) /Subject ({}) /Keywords ({}) /Producer (lifehash-rs) /LifeHashDigest ({digest}) /LifeHashVersion ({:?}) >>",
+            pdf_string(&format!("LifeHash {}", fingerprint(&lifehash.digest))),
+            pdf_string(&format!("Digest {digest}")),
+            pdf_string(&format!("lifehash, {:?}", lifehash.version)),
+            lifehash.version
+        ),
+    );
+    writer.write_all(&pdf.finish(1, Some(6))?)
+}
+
+/// Replaces characters outside of printable ASCII, which the standard fonts can't show as is.
+fn pdf_text(text: &str) -> String {
+    text.chars()
+        .map(|c| if (' '..='~').contains(&c) { c } else { '?' })
+        .collect()
+}
+
+/// Escapes the text for a PDF literal string.
+fn pdf_string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '(' | ')' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,89 @@
+use crate::encoders::{rgba, ImageEncoder};
+use crate::Image;
+use std::io::{Error, ErrorKind, Write};
+
+const QOI_OP_INDEX: u8 = 0x00;
+const QOI_OP_DIFF: u8 = 0x40;
+const QOI_OP_LUMA: u8 = 0x80;
+const QOI_OP_RUN: u8 = 0xc0;
+const QOI_OP_RGB: u8 = 0xfe;
+const QOI_OP_RGBA: u8 = 0xff;
+const QOI_END_MARKER: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 1];
+
+/// The "Quite OK Image" encoder, see <https://qoiformat.org/qoi-specification.pdf>.
+pub struct QoiEncoder;
+impl ImageEncoder for QoiEncoder {
+    fn encode(&self, bitmap: &Image, writer: &mut dyn Write) -> Result<(), Error> {
+        let width = u32::try_from(bitmap.width)
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, "Image too large for QOI"))?;
+        let height = u32::try_from(bitmap.height)
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, "Image too large for QOI"))?;
+        let has_alpha = bitmap.channels == 2 || bitmap.channels == 4;
+        let mut data = Vec::with_capacity(14 + bitmap.width * bitmap.height + 8);
+        data.extend(b"qoif");
+        data.extend(width.to_be_bytes());
+        data.extend(height.to_be_bytes());
+        data.push(if has_alpha { 4 } else { 3 });
+        // sRGB with linear alpha
+        data.push(0);
+        let mut index = [[0u8; 4]; 64];
+        let mut previous = [0, 0, 0, u8::MAX];
+        let mut run = 0u8;
+        let pixel_count = bitmap.width * bitmap.height;
+        for (i, pixel) in bitmap
+            .pixels
+            .chunks_exact(bitmap.channels)
+            .take(pixel_count)
+            .enumerate()
+        {
+            let pixel = rgba(pixel, u8::MAX);
+            if pixel == previous {
+                run += 1;
+                if run == 62 || i == pixel_count - 1 {
+                    data.push(QOI_OP_RUN | (run - 1));
+                    run = 0;
+                }
+                continue;
+            }
+            if run > 0 {
+                data.push(QOI_OP_RUN | (run - 1));
+                run = 0;
+            }
+            let [r, g, b, a] = pixel;
+            let hash = (r as usize * 3 + g as usize * 5 + b as usize * 7 + a as usize * 11) % 64;
+            if index[hash] == pixel {
+                data.push(QOI_OP_INDEX | hash as u8);
+            } else {
+                index[hash] = pixel;
+                if a == previous[3] {
+                    let dr = r.wrapping_sub(previous[0]) as i8;
+                    let dg = g.wrapping_sub(previous[1]) as i8;
+                    let db = b.wrapping_sub(previous[2]) as i8;
+                    let dr_dg = dr.wrapping_sub(dg);
+                    let db_dg = db.wrapping_sub(dg);
+                    if (-2..2).contains(&dr) && (-2..2).contains(&dg) && (-2..2).contains(&db) {
+                        data.push(
+                            QOI_OP_DIFF
+                                | ((dr + 2) as u8) << 4
+                                | ((dg + 2) as u8) << 2
+                                | (db + 2) as u8,
+                        );
+                    } else if (-32..32).contains(&dg)
+                        && (-8..8).contains(&dr_dg)
+                        && (-8..8).contains(&db_dg)
+                    {
+                        data.push(QOI_OP_LUMA | (dg + 32) as u8);
+                        data.push(((dr_dg + 8) as u8) << 4 | (db_dg + 8) as u8);
+                    } else {
+                        data.extend([QOI_OP_RGB, r, g, b]);
+                    }
+                } else {
+                    data.extend([QOI_OP_RGBA, r, g, b, a]);
+                }
+            }
+            previous = pixel;
+        }
+        data.extend(QOI_END_MARKER);
+        writer.write_all(&data)
+    }
+}
@@ -0,0 +1,169 @@
+use crate::grids::cell_grid::Cellgrid;
+use crate::{Dimensions, Point};
+use std::io::{Error, ErrorKind, Read, Write};
+
+/// Conway's Life, on a torus the size of the grid as the lifehash simulation runs it.
+const RULE: &str = "B3/S23";
+/// RLE lines should not be longer than 70 characters.
+const LINE_LENGTH: usize = 70;
+/// The most cells a decoded pattern may have, far more than any lifehash needs.
+const MAX_CELLS: usize = 1 << 24;
+
+/// Writes the grid as run length encoded Life, readable by Golly and most other Life programs.
+/// Each comment becomes a `#C` line, and the rule has the toroidal bounds of the grid so the
+/// pattern evolves there the same way it does in the lifehash.
+pub fn encode_rle<W: Write>(
+    grid: &Cellgrid,
+    comments: &[String],
+    mut writer: W,
+) -> Result<(), Error> {
+    let Dimensions { width, height } = grid.dimensions;
+    for comment in comments {
+        writeln!(writer, "#C {comment}")?;
+    }
+    writeln!(writer, "#R 0 0")?;
+    writeln!(
+        writer,
+        "x = {width}, y = {height}, rule = {RULE}:T{width},{height}"
+    )?;
+    // Runs of dead ('b') and alive ('o') cells and of row ends ('$'), leaving out dead cells at
+    // the end of each row and empty rows at the end.
+    let mut runs: Vec<(usize, char)> = vec![];
+    let mut pending_rows = 0;
+    for y in 0..height {
+        let row: Vec<bool> = (0..width)
+            .map(|x| *grid.get_value(Point { x, y }))
+            .collect();
+        let Some(end) = row.iter().rposition(|alive| *alive) else {
+            pending_rows += 1;
+            continue;
+        };
+        if pending_rows > 0 {
+            push(&mut runs, pending_rows, '$');
+        }
+        for alive in &row[..=end] {
+            push(&mut runs, 1, if *alive { 'o' } else { 'b' });
+        }
+        pending_rows = 1;
+    }
+    let mut line = String::new();
+    for (count, tag) in runs {
+        let item = if count == 1 {
+            tag.to_string()
+        } else {
+            format!("{count}{tag}")
+        };
+        if line.len() + item.len() > LINE_LENGTH {
+            writeln!(writer, "{line}")?;
+            line.clear();
+        }
+        line.push_str(&item);
+    }
+    if line.len() == LINE_LENGTH {
+        writeln!(writer, "{line}")?;
+        line.clear();
+    }
+    writeln!(writer, "{line}!")
+}
+
+/// Adds to the last run if it has the same tag.
+fn push(runs: &mut Vec<(usize, char)>, count: usize, tag: char) {
+    match runs.last_mut() {
+        Some((last, last_tag)) if *last_tag == tag => *last += count,
+        _ => runs.push((count, tag)),
+    }
+}
+
+/// Reads a run length encoded Life pattern into a grid. The grid has the toroidal bounds of the
+/// rule if it has any, otherwise the size of the pattern. States other than dead (`b` or `.`)
+/// count as alive.
+pub fn decode_rle<R: Read>(mut reader: R) -> Result<Cellgrid, Error> {
+    let invalid = |message: String| Error::new(ErrorKind::InvalidData, message);
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'));
+    let header = lines
+        .next()
+        .ok_or_else(|| invalid("Missing RLE header".to_string()))?;
+    // The rule comes last and may have commas in its bounds.
+    let (sizes, rule) = match header.split_once("rule") {
+        Some((sizes, rule)) => (sizes, rule.trim_start().trim_start_matches('=').trim()),
+        None => (header, ""),
+    };
+    let parse = |value: &str| {
+        value
+            .trim()
+            .parse::<usize>()
+            .map_err(|e| invalid(format!("Invalid RLE size {value}: {e}")))
+    };
+    let mut size = (None, None);
+    for field in sizes.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+        match field.split_once('=') {
+            Some((key, value)) if key.trim() == "x" => size.0 = Some(parse(value)?),
+            Some((key, value)) if key.trim() == "y" => size.1 = Some(parse(value)?),
+            _ => {}
+        }
+    }
+    // Toroidal bounds as in B3/S23:T16,16. Other kinds of bounded grids are read as unbounded.
+    let bounds = rule
+        .split_once(":T")
+        .and_then(|(_, bounds)| bounds.split_once(','))
+        .and_then(|(width, height)| Some((parse(width).ok()?, parse(height).ok()?)))
+        .filter(|(width, height)| *width > 0 && *height > 0);
+    let (Some(width), Some(height)) = size else {
+        return Err(invalid("RLE header without x and y".to_string()));
+    };
+    let (width, height) = bounds.unwrap_or((width, height));
+    if width == 0 || height == 0 {
+        return Err(invalid("Empty RLE pattern".to_string()));
+    }
+    if width
+        .checked_mul(height)
+        .is_none_or(|cells| cells > MAX_CELLS)
+    {
+        return Err(invalid(format!(
+            "RLE pattern of {width}x{height} is too large"
+        )));
+    }
+    let mut grid = Cellgrid::new(Dimensions { width, height });
+    let (mut x, mut y) = (0usize, 0usize);
+    let mut count = 0usize;
+    'body: for line in lines {
+        for c in line.chars() {
+            match c {
+                '0'..='9' => {
+                    count = count
+                        .checked_mul(10)
+                        .and_then(|count| count.checked_add(c.to_digit(10).unwrap_or(0) as usize))
+                        .ok_or_else(|| invalid("RLE run count too large".to_string()))?;
+                }
+                '!' => break 'body,
+                '$' => {
+                    y = y.saturating_add(count.max(1));
+                    x = 0;
+                    count = 0;
+                }
+                c if c.is_whitespace() => {}
+                c => {
+                    let run = count.max(1);
+                    if c != 'b' && c != '.' {
+                        if y >= height || x.checked_add(run).is_none_or(|end| end > width) {
+                            return Err(invalid(format!(
+                                "RLE pattern larger than {width}x{height}"
+                            )));
+                        }
+                        for x in x..x + run {
+                            grid.set_value(true, Point { x, y });
+                        }
+                    }
+                    x = x.saturating_add(run);
+                    count = 0;
+                }
+            }
+        }
+    }
+    Ok(grid)
+}
@@ -0,0 +1,29 @@
+use crate::mesh::Mesh;
+use std::io::{Error, ErrorKind, Write};
+
+/// Binary STL headers must not start with "solid", which marks the text format.
+const HEADER: &[u8] = b"LifeHash relief";
+
+/// Writes the mesh as binary STL, in millimeters. STL has no colors.
+pub fn encode_stl<W: Write>(mesh: &Mesh, mut writer: W) -> Result<(), Error> {
+    let count = u32::try_from(mesh.triangles.len())
+        .map_err(|_| Error::new(ErrorKind::InvalidInput, "Mesh too large for STL"))?;
+    let mut header = [0u8; 80];
+    header[..HEADER.len()].copy_from_slice(HEADER);
+    writer.write_all(&header)?;
+    writer.write_all(&count.to_le_bytes())?;
+    let mut record = Vec::with_capacity(50);
+    for triangle in &mesh.triangles {
+        record.clear();
+        let corners = triangle.map(|i| mesh.vertices[i]);
+        for point in [mesh.normal(*triangle)].iter().chain(&corners) {
+            for c in point {
+                record.extend((*c as f32).to_le_bytes());
+            }
+        }
+        // Attribute byte count, unused.
+        record.extend(0u16.to_le_bytes());
+        writer.write_all(&record)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use crate::encoders::{data_uri, ImageEncoder};
+use crate::vector::{number, VectorImage};
+use crate::Image;
+use std::io::{Error, Write};
+
+/// Writes the image as SVG with one rectangle run per row of same colored pixels.
+pub struct SvgEncoder;
+impl ImageEncoder for SvgEncoder {
+    fn encode(&self, bitmap: &Image, writer: &mut dyn Write) -> Result<(), Error> {
+        encode_svg(&VectorImage::from_image(bitmap), 1.0, writer)
+    }
+}
+
+/// Writes the vector image as SVG, `scale` is the number of pixels per unit.
+pub fn encode_svg<W: Write>(image: &VectorImage, scale: f64, mut writer: W) -> Result<(), Error> {
+    write!(
+        writer,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}""#,
+        number(image.width * scale),
+        number(image.height * scale),
+        number(image.width),
+        number(image.height)
+    )?;
+    if image.crisp_edges {
+        write!(writer, r#" shape-rendering="crispEdges""#)?;
+    }
+    writeln!(writer, ">")?;
+    for shape in &image.shapes {
+        write!(writer, r#"<path fill="{}""#, shape.color.hex())?;
+        if shape.opacity < 1.0 {
+            write!(writer, r#" fill-opacity="{}""#, number(shape.opacity))?;
+        }
+        write!(writer, r#" fill-rule="evenodd" d=""#)?;
+        for path in &shape.paths {
+            for (i, (x, y)) in path.iter().enumerate() {
+                let command = if i == 0 { "M" } else { "L" };
+                write!(writer, "{command}{} {}", number(*x), number(*y))?;
+            }
+            write!(writer, "Z")?;
+        }
+        writeln!(writer, r#""/>"#)?;
+    }
+    writeln!(writer, "</svg>")
+}
+
+pub fn encode_svg_to_string(image: &VectorImage, scale: f64) -> Result<String, Error> {
+    let mut buffer = vec![];
+    encode_svg(image, scale, &mut buffer)?;
+    Ok(String::from_utf8(buffer).expect("SVG output is ASCII"))
+}
+
+/// The SVG encoded vector image as a `data:image/svg+xml;base64,...` URI.
+pub fn encode_svg_to_data_uri(image: &VectorImage, scale: f64) -> Result<String, Error> {
+    let mut buffer = vec![];
+    encode_svg(image, scale, &mut buffer)?;
+    Ok(data_uri("image/svg+xml", &buffer))
+}
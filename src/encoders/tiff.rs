@@ -0,0 +1,115 @@
+use crate::colors::cmyk::CmykProfile;
+use crate::colors::rgb::Color;
+use crate::encoders::{rgba, ImageEncoder};
+use crate::lifehash::quantize8;
+use crate::Image;
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Write};
+
+const SHORT: u16 = 3;
+const LONG: u16 = 4;
+const RATIONAL: u16 = 5;
+/// Entries in the image file directory.
+const TAGS: usize = 14;
+/// The header, then the directory with its count and the offset of the next one.
+const DIRECTORY_END: usize = 8 + 2 + TAGS * 12 + 4;
+
+/// Uncompressed baseline TIFF with the colors separated into CMYK inks, for print.
+pub struct TiffEncoder {
+    pub profile: CmykProfile,
+}
+impl ImageEncoder for TiffEncoder {
+    fn encode(&self, bitmap: &Image, writer: &mut dyn Write) -> Result<(), Error> {
+        encode_cmyk_tiff(bitmap, &self.profile, writer)
+    }
+}
+
+/// Writes the image as an 8-bit CMYK TIFF, 72 pixels per inch. Pixels with alpha are blended
+/// over the paper color of the profile.
+pub fn encode_cmyk_tiff<W: Write>(
+    bitmap: &Image,
+    profile: &CmykProfile,
+    mut writer: W,
+) -> Result<(), Error> {
+    let too_large = || Error::new(ErrorKind::InvalidInput, "Image too large for TIFF");
+    let width = u32::try_from(bitmap.width).map_err(|_| too_large())?;
+    let height = u32::try_from(bitmap.height).map_err(|_| too_large())?;
+    let length = u32::try_from(bitmap.width * bitmap.height * 4).map_err(|_| too_large())?;
+    // Bits per sample, then the two resolutions.
+    let bits_offset = DIRECTORY_END as u32;
+    let x_resolution_offset = bits_offset + 8;
+    let y_resolution_offset = x_resolution_offset + 8;
+    let pixels_offset = y_resolution_offset + 8;
+
+    let mut header = b"II*\0".to_vec();
+    header.extend_from_slice(&8u32.to_le_bytes());
+    header.extend_from_slice(&(TAGS as u16).to_le_bytes());
+    let entries: [(u16, u16, u32, u32); TAGS] = [
+        (256, LONG, 1, width),
+        (257, LONG, 1, height),
+        (258, SHORT, 4, bits_offset),
+        // No compression.
+        (259, SHORT, 1, 1),
+        // Separated, which with the default ink set is CMYK.
+        (262, SHORT, 1, 5),
+        (273, LONG, 1, pixels_offset),
+        (277, SHORT, 1, 4),
+        (278, LONG, 1, height),
+        (279, LONG, 1, length),
+        (282, RATIONAL, 1, x_resolution_offset),
+        (283, RATIONAL, 1, y_resolution_offset),
+        // Chunky, the four inks of a pixel together.
+        (284, SHORT, 1, 1),
+        // Inches.
+        (296, SHORT, 1, 2),
+        // CMYK ink set.
+        (332, SHORT, 1, 1),
+    ];
+    for (tag, kind, count, value) in entries {
+        header.extend_from_slice(&tag.to_le_bytes());
+        header.extend_from_slice(&kind.to_le_bytes());
+        header.extend_from_slice(&count.to_le_bytes());
+        // Values that fit are stored left-justified in the entry.
+        if kind == SHORT && count == 1 {
+            header.extend_from_slice(&(value as u16).to_le_bytes());
+            header.extend_from_slice(&[0, 0]);
+        } else {
+            header.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    header.extend_from_slice(&0u32.to_le_bytes());
+    for _ in 0..4 {
+        header.extend_from_slice(&8u16.to_le_bytes());
+    }
+    for _ in 0..2 {
+        header.extend_from_slice(&72u32.to_le_bytes());
+        header.extend_from_slice(&1u32.to_le_bytes());
+    }
+    writer.write_all(&header)?;
+
+    // Separating is slow and smooth images have many colors, so each color only takes a few
+    // steps from the table and is separated once.
+    let table = profile.table();
+    let mut inks: HashMap<[u8; 3], [u8; 4]> = HashMap::new();
+    let paper = [profile.paper.r, profile.paper.g, profile.paper.b];
+    for row in bitmap
+        .pixels
+        .chunks_exact(bitmap.width * bitmap.channels)
+        .take(bitmap.height)
+    {
+        let mut bytes = Vec::with_capacity(bitmap.width * 4);
+        for pixel in row.chunks_exact(bitmap.channels) {
+            let [r, g, b, a] = rgba(pixel, u8::MAX).map(|c| c as f64 / 255.0);
+            let color = [r, g, b];
+            let rgb: [u8; 3] =
+                std::array::from_fn(|i| quantize8(color[i] * a + paper[i] * (1.0 - a)));
+            let cmyk = inks.entry(rgb).or_insert_with(|| {
+                let cmyk = table.separate(Color::new_u8(rgb[0], rgb[1], rgb[2]));
+                [cmyk.c, cmyk.m, cmyk.y, cmyk.k].map(quantize8)
+            });
+            bytes.extend_from_slice(cmyk);
+        }
+        writer.write_all(&bytes)?;
+    }
+    Ok(())
+}
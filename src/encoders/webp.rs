@@ -0,0 +1,401 @@
+use crate::encoders::{rgba, ImageEncoder};
+use crate::Image;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::{Error, ErrorKind, Write};
+
+const VP8L_SIGNATURE: u8 = 0x2f;
+const VP8L_MAX_DIMENSION: usize = 1 << 14;
+const SUBTRACT_GREEN: u32 = 2;
+/// Index bits of the color cache, which holds recently seen colors.
+const COLOR_CACHE_BITS: u32 = 8;
+/// Prefix codes of backward reference lengths, which follow the green literals.
+const LENGTH_CODES: usize = 24;
+// Green, red, blue, alpha and distance alphabets.
+const ALPHABET_SIZES: [usize; 5] = [
+    256 + LENGTH_CODES + (1 << COLOR_CACHE_BITS),
+    256,
+    256,
+    256,
+    40,
+];
+const MAX_COPY_LENGTH: usize = 4096;
+/// Shorter copies take more bits than the pixels they replace.
+const MIN_COPY_LENGTH: usize = 3;
+/// Distance codes of the two places a copy is looked for, as (x, y) offsets they are (1, 0) for
+/// the pixel to the left and (0, 1) for the one above.
+const LEFT_DISTANCE_CODE: usize = 2;
+const ABOVE_DISTANCE_CODE: usize = 1;
+const MAX_CODE_LENGTH: u8 = 15;
+const MAX_CODE_LENGTH_CODE_LENGTH: u8 = 7;
+const CODE_LENGTH_CODE_ORDER: [usize; 19] = [
+    17, 18, 0, 1, 2, 3, 4, 5, 16, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+];
+
+/// Lossless WebP (VP8L) encoder. Green is subtracted from red and blue, and the pixels are coded
+/// as copies of the runs to their left or above, colors from a color cache or entropy coded
+/// literals. Modules are repeated runs of pixels, so most of the image is copies.
+pub struct WebpEncoder;
+impl ImageEncoder for WebpEncoder {
+    fn encode(&self, bitmap: &Image, writer: &mut dyn Write) -> Result<(), Error> {
+        if bitmap.width == 0
+            || bitmap.height == 0
+            || bitmap.width > VP8L_MAX_DIMENSION
+            || bitmap.height > VP8L_MAX_DIMENSION
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "WebP dimensions must be between 1 and 16384",
+            ));
+        }
+        let has_alpha = bitmap.channels == 2 || bitmap.channels == 4;
+        let pixels: Vec<[u8; 4]> = bitmap
+            .pixels
+            .chunks_exact(bitmap.channels)
+            .take(bitmap.width * bitmap.height)
+            .map(|pixel| {
+                let [r, g, b, a] = rgba(pixel, u8::MAX);
+                [r.wrapping_sub(g), g, b.wrapping_sub(g), a]
+            })
+            .collect();
+        let mut bits = BitWriter::default();
+        bits.write(VP8L_SIGNATURE as u32, 8);
+        bits.write(bitmap.width as u32 - 1, 14);
+        bits.write(bitmap.height as u32 - 1, 14);
+        bits.write(has_alpha as u32, 1);
+        // Version
+        bits.write(0, 3);
+        // The subtract green transform and no others.
+        bits.write(1, 1);
+        bits.write(SUBTRACT_GREEN, 2);
+        bits.write(0, 1);
+        // A color cache and a single group of prefix codes.
+        bits.write(1, 1);
+        bits.write(COLOR_CACHE_BITS, 4);
+        bits.write(0, 1);
+        let tokens = tokenize(&pixels, bitmap.width);
+        let mut histograms: Vec<Vec<u32>> = ALPHABET_SIZES.iter().map(|s| vec![0; *s]).collect();
+        for token in &tokens {
+            match *token {
+                Token::Literal([r, g, b, a]) => {
+                    histograms[0][g as usize] += 1;
+                    histograms[1][r as usize] += 1;
+                    histograms[2][b as usize] += 1;
+                    histograms[3][a as usize] += 1;
+                }
+                Token::Cache(index) => histograms[0][256 + LENGTH_CODES + index] += 1,
+                Token::Copy { length, distance } => {
+                    histograms[0][256 + prefix(length).0] += 1;
+                    histograms[4][prefix(distance).0] += 1;
+                }
+            }
+        }
+        let codes: Vec<PrefixCode> = histograms
+            .iter()
+            .map(|histogram| write_prefix_code(&mut bits, histogram))
+            .collect();
+        for token in tokens {
+            match token {
+                Token::Literal([r, g, b, a]) => {
+                    codes[0].write_symbol(&mut bits, g as usize);
+                    codes[1].write_symbol(&mut bits, r as usize);
+                    codes[2].write_symbol(&mut bits, b as usize);
+                    codes[3].write_symbol(&mut bits, a as usize);
+                }
+                Token::Cache(index) => {
+                    codes[0].write_symbol(&mut bits, 256 + LENGTH_CODES + index);
+                }
+                Token::Copy { length, distance } => {
+                    let (symbol, extra_bits, extra) = prefix(length);
+                    codes[0].write_symbol(&mut bits, 256 + symbol);
+                    bits.write(extra, extra_bits);
+                    let (symbol, extra_bits, extra) = prefix(distance);
+                    codes[4].write_symbol(&mut bits, symbol);
+                    bits.write(extra, extra_bits);
+                }
+            }
+        }
+        let data = bits.finish();
+        let padding = data.len() % 2;
+        let riff_size = u32::try_from(4 + 8 + data.len() + padding)
+            .map_err(|_| Error::new(ErrorKind::InvalidInput, "Image too large for WebP"))?;
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&riff_size.to_le_bytes())?;
+        writer.write_all(b"WEBPVP8L")?;
+        writer.write_all(&(data.len() as u32).to_le_bytes())?;
+        writer.write_all(&data)?;
+        if padding == 1 {
+            writer.write_all(&[0])?;
+        }
+        Ok(())
+    }
+}
+
+/// How a run of pixels is coded.
+enum Token {
+    Literal([u8; 4]),
+    /// A color at an index of the color cache.
+    Cache(usize),
+    /// A copy of earlier pixels, `distance` as a distance code.
+    Copy {
+        length: usize,
+        distance: usize,
+    },
+}
+
+/// Codes the pixels greedily, with the longest copy of the pixels to the left or above where
+/// one is long enough, and otherwise a color from the cache or a literal. The color cache is
+/// updated with every pixel, as the decoder does.
+fn tokenize(pixels: &[[u8; 4]], width: usize) -> Vec<Token> {
+    let mut cache = [None; 1 << COLOR_CACHE_BITS];
+    let cache_index = |[r, g, b, a]: [u8; 4]| {
+        let argb = u32::from_be_bytes([a, r, g, b]);
+        (argb.wrapping_mul(0x1e35a7bd) >> (32 - COLOR_CACHE_BITS)) as usize
+    };
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < pixels.len() {
+        let copy_length = |distance: usize| {
+            if distance > i {
+                return 0;
+            }
+            pixels[i..]
+                .iter()
+                .zip(&pixels[i - distance..])
+                .take(MAX_COPY_LENGTH)
+                .take_while(|(a, b)| a == b)
+                .count()
+        };
+        let (length, distance) = [(width, ABOVE_DISTANCE_CODE), (1, LEFT_DISTANCE_CODE)]
+            .into_iter()
+            .map(|(distance, code)| (copy_length(distance), code))
+            .max_by_key(|(length, _)| *length)
+            .unwrap_or((0, 0));
+        let length = if length >= MIN_COPY_LENGTH {
+            tokens.push(Token::Copy { length, distance });
+            length
+        } else {
+            let pixel = pixels[i];
+            let index = cache_index(pixel);
+            if cache[index] == Some(pixel) {
+                tokens.push(Token::Cache(index));
+            } else {
+                tokens.push(Token::Literal(pixel));
+            }
+            1
+        };
+        for pixel in &pixels[i..i + length] {
+            cache[cache_index(*pixel)] = Some(*pixel);
+        }
+        i += length;
+    }
+    tokens
+}
+
+/// The prefix symbol, extra bit count and extra bits of a copy length or distance code.
+fn prefix(value: usize) -> (usize, u32, u32) {
+    let value = value as u32 - 1;
+    if value < 4 {
+        return (value as usize, 0, 0);
+    }
+    let highest = 31 - value.leading_zeros();
+    let second = (value >> (highest - 1)) & 1;
+    let extra_bits = highest - 1;
+    (
+        (2 * highest + second) as usize,
+        extra_bits,
+        value & ((1 << extra_bits) - 1),
+    )
+}
+
+#[derive(Default)]
+struct BitWriter {
+    data: Vec<u8>,
+    buffer: u64,
+    used: u32,
+}
+impl BitWriter {
+    /// Appends the low `count` bits of `value`, least significant bit first.
+    fn write(&mut self, value: u32, count: u32) {
+        self.buffer |= (value as u64 & ((1 << count) - 1)) << self.used;
+        self.used += count;
+        while self.used >= 8 {
+            self.data.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.used -= 8;
+        }
+    }
+    fn finish(mut self) -> Vec<u8> {
+        if self.used > 0 {
+            self.data.push(self.buffer as u8);
+        }
+        self.data
+    }
+}
+
+/// Canonical prefix code, the codes are stored bit reversed so they can be written least significant bit first.
+struct PrefixCode {
+    lengths: Vec<u8>,
+    codes: Vec<u16>,
+}
+impl PrefixCode {
+    fn from_lengths(lengths: Vec<u8>) -> Self {
+        let max_length = lengths.iter().copied().max().unwrap_or(0) as usize;
+        let mut length_counts = vec![0u16; max_length + 1];
+        for length in &lengths {
+            length_counts[*length as usize] += 1;
+        }
+        length_counts[0] = 0;
+        let mut next_code = vec![0u16; max_length + 1];
+        let mut code = 0u16;
+        for length in 1..=max_length {
+            code = (code + length_counts[length - 1]) << 1;
+            next_code[length] = code;
+        }
+        let codes = lengths
+            .iter()
+            .map(|length| {
+                if *length == 0 {
+                    return 0;
+                }
+                let code = next_code[*length as usize];
+                next_code[*length as usize] += 1;
+                code.reverse_bits() >> (16 - *length as u32)
+            })
+            .collect();
+        Self { lengths, codes }
+    }
+    fn write_symbol(&self, bits: &mut BitWriter, symbol: usize) {
+        bits.write(self.codes[symbol] as u32, self.lengths[symbol] as u32);
+    }
+}
+
+/// Writes the prefix code for the histogram and returns it for coding the symbols.
+fn write_prefix_code(bits: &mut BitWriter, histogram: &[u32]) -> PrefixCode {
+    let used: Vec<usize> = (0..histogram.len()).filter(|s| histogram[*s] > 0).collect();
+    if used.len() <= 1 && used.first().is_none_or(|s| *s < 256) {
+        // A simple code with a single symbol, which takes no bits to write.
+        let symbol = used.first().copied().unwrap_or(0);
+        bits.write(1, 1);
+        bits.write(0, 1);
+        if symbol < 2 {
+            bits.write(0, 1);
+            bits.write(symbol as u32, 1);
+        } else {
+            bits.write(1, 1);
+            bits.write(symbol as u32, 8);
+        }
+        return PrefixCode::from_lengths(vec![0; histogram.len()]);
+    }
+    let lengths = code_lengths(histogram, MAX_CODE_LENGTH);
+    let tokens = code_length_tokens(&lengths);
+    let mut token_histogram = [0u32; 19];
+    for (symbol, _) in &tokens {
+        token_histogram[*symbol] += 1;
+    }
+    let code_length_code =
+        PrefixCode::from_lengths(code_lengths(&token_histogram, MAX_CODE_LENGTH_CODE_LENGTH));
+    let code_length_count = CODE_LENGTH_CODE_ORDER
+        .iter()
+        .rposition(|s| code_length_code.lengths[*s] > 0)
+        .map_or(4, |p| (p + 1).max(4));
+    // Normal code
+    bits.write(0, 1);
+    bits.write(code_length_count as u32 - 4, 4);
+    for symbol in &CODE_LENGTH_CODE_ORDER[..code_length_count] {
+        bits.write(code_length_code.lengths[*symbol] as u32, 3);
+    }
+    // The code lengths of every symbol in the alphabet follow.
+    bits.write(0, 1);
+    for (symbol, extra) in tokens {
+        code_length_code.write_symbol(bits, symbol);
+        match symbol {
+            17 => bits.write(extra, 3),
+            18 => bits.write(extra, 7),
+            _ => {}
+        }
+    }
+    PrefixCode::from_lengths(lengths)
+}
+
+/// Run length codes the code lengths, returning the code length symbol and its extra bits.
+fn code_length_tokens(lengths: &[u8]) -> Vec<(usize, u32)> {
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < lengths.len() {
+        if lengths[i] != 0 {
+            tokens.push((lengths[i] as usize, 0));
+            i += 1;
+            continue;
+        }
+        let run = lengths[i..]
+            .iter()
+            .take_while(|l| **l == 0)
+            .count()
+            .min(138);
+        if run >= 11 {
+            tokens.push((18, run as u32 - 11));
+        } else if run >= 3 {
+            tokens.push((17, run as u32 - 3));
+        } else {
+            tokens.extend(std::iter::repeat_n((0, 0), run));
+        }
+        i += run;
+    }
+    tokens
+}
+
+/// Huffman code lengths limited to `max_length`. Always assigns at least two symbols so the code is complete.
+fn code_lengths(histogram: &[u32], max_length: u8) -> Vec<u8> {
+    let mut counts: Vec<u32> = histogram.to_vec();
+    let used = counts.iter().filter(|c| **c > 0).count();
+    if used < 2 {
+        // Pad with unused symbols, a single symbol can't form a complete code.
+        for count in counts.iter_mut().filter(|c| **c == 0).take(2 - used) {
+            *count = 1;
+        }
+    }
+    let mut shift = 0;
+    loop {
+        let lengths = huffman_lengths(&counts, shift);
+        if lengths.iter().all(|l| *l <= max_length) {
+            return lengths;
+        }
+        // Flatten the distribution until the tree is shallow enough.
+        shift += 1;
+    }
+}
+
+fn huffman_lengths(counts: &[u32], shift: u32) -> Vec<u8> {
+    // Leaves are the symbols, internal nodes follow. Each node keeps its parent to compute the depth.
+    let mut parents: Vec<usize> = vec![usize::MAX; counts.len()];
+    let mut heap = BinaryHeap::new();
+    for (symbol, count) in counts.iter().enumerate() {
+        if *count > 0 {
+            heap.push(Reverse(((*count >> shift).max(1), symbol)));
+        }
+    }
+    while heap.len() > 1 {
+        let Reverse((weight1, node1)) = heap.pop().expect("heap has two nodes");
+        let Reverse((weight2, node2)) = heap.pop().expect("heap has two nodes");
+        let parent = parents.len();
+        parents.push(usize::MAX);
+        parents[node1] = parent;
+        parents[node2] = parent;
+        heap.push(Reverse((weight1 + weight2, parent)));
+    }
+    (0..counts.len())
+        .map(|symbol| {
+            if counts[symbol] == 0 {
+                return 0;
+            }
+            let mut depth = 0;
+            let mut node = symbol;
+            while parents[node] != usize::MAX {
+                node = parents[node];
+                depth += 1;
+            }
+            depth
+        })
+        .collect()
+}
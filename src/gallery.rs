@@ -0,0 +1,154 @@
+use crate::lifehash::LifeHash;
+use clap::ValueEnum;
+use std::collections::BTreeSet;
+use std::io::{Error, Write};
+
+/// How a gallery includes the images.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, ValueEnum)]
+pub enum GalleryImages {
+    /// Data URIs inside the HTML file, so it can be shared on its own.
+    #[default]
+    Embed,
+    /// PNG files next to the HTML file.
+    Link,
+}
+
+/// One lifehash of a gallery.
+pub struct GalleryEntry<'a> {
+    /// The input the lifehash was made from.
+    pub name: String,
+    pub lifehash: &'a LifeHash,
+    /// URL of the image, a data URI or a path relative to the HTML file.
+    pub src: String,
+}
+
+const STYLE: &str = "body{font-family:sans-serif;margin:1em;background:#f4f4f4}
+header{display:flex;flex-wrap:wrap;gap:.5em;align-items:center;margin-bottom:1em}
+main{display:flex;flex-wrap:wrap;gap:1em}
+.entry{margin:0;padding:.5em;background:#fff;border-radius:4px;width:200px}
+.entry[hidden]{display:none}
+.entry img{width:200px;height:200px;image-rendering:pixelated}
+.name{font-weight:bold;overflow-wrap:anywhere}
+code{font-size:.75em;overflow-wrap:anywhere}
+dl{display:grid;grid-template-columns:auto 1fr;gap:0 .5em;margin:.5em 0 0;font-size:.85em}
+dd{margin:0}";
+
+const SCRIPT: &str = r#"const fields = ["version", "family", "pattern"];
+const entries = [...document.querySelectorAll(".entry")];
+function filter() {
+  const search = document.getElementById("search").value.toLowerCase();
+  const min = parseInt(document.getElementById("min-generations").value) || 0;
+  const max = parseInt(document.getElementById("max-generations").value) || Infinity;
+  let shown = 0;
+  for (const entry of entries) {
+    const data = entry.dataset;
+    const generations = Number(data.generations);
+    const visible = (data.name.toLowerCase().includes(search) || data.digest.includes(search))
+      && fields.every(field => {
+        const value = document.getElementById(field).value;
+        return !value || data[field] === value;
+      })
+      && generations >= min && generations <= max;
+    entry.hidden = !visible;
+    shown += visible;
+  }
+  document.getElementById("count").textContent = shown + " of " + entries.length;
+}
+document.querySelectorAll("input, select").forEach(input => input.addEventListener("input", filter));
+filter();"#;
+
+/// Writes a self-contained HTML page with the entries and their digest, version, gradient family,
+/// pattern and generation count, filterable by those fields in the browser.
+pub fn write_gallery<W: Write>(
+    title: &str,
+    entries: &[GalleryEntry],
+    mut writer: W,
+) -> Result<(), Error> {
+    let mut rows = vec![];
+    for entry in entries {
+        let lifehash = entry.lifehash;
+        rows.push([
+            format!("{:?}", lifehash.version),
+            format!("{:?}", lifehash.gradient_family()?),
+            format!("{:?}", lifehash.pattern),
+        ]);
+    }
+    writeln!(writer, "<!DOCTYPE html>")?;
+    writeln!(writer, r#"<html lang="en">"#)?;
+    writeln!(writer, r#"<head><meta charset="utf-8">"#)?;
+    writeln!(writer, "<title>{}</title>", escape_html(title))?;
+    writeln!(writer, "<style>\n{STYLE}\n</style>\n</head>\n<body>")?;
+    writeln!(writer, "<h1>{}</h1>", escape_html(title))?;
+    writeln!(writer, "<header>")?;
+    writeln!(
+        writer,
+        r#"<input id="search" type="search" placeholder="Name or digest">"#
+    )?;
+    for (i, (field, label)) in [
+        ("version", "versions"),
+        ("family", "gradient families"),
+        ("pattern", "patterns"),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let values: BTreeSet<&String> = rows.iter().map(|row| &row[i]).collect();
+        write!(
+            writer,
+            r#"<select id="{field}"><option value="">All {label}</option>"#
+        )?;
+        for value in values {
+            write!(writer, "<option>{}</option>", escape_html(value))?;
+        }
+        writeln!(writer, "</select>")?;
+    }
+    writeln!(
+        writer,
+        r#"<input id="min-generations" type="number" min="0" placeholder="Min generations">"#
+    )?;
+    writeln!(
+        writer,
+        r#"<input id="max-generations" type="number" min="0" placeholder="Max generations">"#
+    )?;
+    writeln!(writer, r#"<span id="count"></span>"#)?;
+    writeln!(writer, "</header>\n<main>")?;
+    for (entry, [version, family, pattern]) in entries.iter().zip(&rows) {
+        let digest = hex::encode(&entry.lifehash.digest);
+        let generations = entry.lifehash.generations();
+        let name = escape_html(&entry.name);
+        writeln!(
+            writer,
+            r#"<figure class="entry" data-name="{name}" data-digest="{digest}" data-version="{version}" data-family="{family}" data-pattern="{pattern}" data-generations="{generations}">"#
+        )?;
+        writeln!(
+            writer,
+            r#"<img src="{}" alt="{name}" loading="lazy">"#,
+            escape_html(&entry.src)
+        )?;
+        writeln!(writer, r#"<figcaption><div class="name">{name}</div>"#)?;
+        writeln!(writer, "<code>{digest}</code>")?;
+        writeln!(
+            writer,
+            "<dl><dt>Version</dt><dd>{version}</dd><dt>Gradient</dt><dd>{family}</dd><dt>Pattern</dt><dd>{pattern}</dd><dt>Generations</dt><dd>{generations}</dd></dl>"
+        )?;
+        writeln!(writer, "</figcaption></figure>")?;
+    }
+    writeln!(writer, "</main>\n<script>\n{SCRIPT}\n</script>")?;
+    writeln!(writer, "</body>\n</html>")
+}
+
+/// Escapes the text for HTML content and quoted attribute values.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
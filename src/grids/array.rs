@@ -0,0 +1,110 @@
+use crate::grids::cell_grid::Cellgrid;
+use crate::lifehash::LifeHash;
+use clap::ValueEnum;
+
+/// The grids of a lifehash that can be exported as numbers.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum GridData {
+    /// The frac grid before it is mirrored by the pattern, from 0 to 1.
+    Frac,
+    /// The cells of every generation, 1 where alive.
+    History,
+    /// The final color grid as RGB from 0 to 1.
+    Color,
+}
+
+/// The numbers of an array, in row-major order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArrayValues {
+    Float(Vec<f64>),
+    Bits(Vec<bool>),
+}
+
+/// A grid as a named n-dimensional array, for analysis outside of this crate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridArray {
+    /// Names and lengths of the axes, outermost first.
+    pub axes: Vec<(&'static str, usize)>,
+    /// Names of the values at each position. With more than one they form the innermost axis.
+    pub fields: Vec<&'static str>,
+    pub values: ArrayValues,
+}
+impl GridArray {
+    /// The length of each dimension, including the fields if there is more than one.
+    pub fn shape(&self) -> Vec<usize> {
+        let mut shape: Vec<usize> = self.axes.iter().map(|(_, length)| *length).collect();
+        if self.fields.len() > 1 {
+            shape.push(self.fields.len());
+        }
+        shape
+    }
+    pub fn len(&self) -> usize {
+        match &self.values {
+            ArrayValues::Float(values) => values.len(),
+            ArrayValues::Bits(values) => values.len(),
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// The value at the flat index as text, the shortest form that reads back the same.
+    pub fn format_value(&self, index: usize) -> String {
+        match &self.values {
+            ArrayValues::Float(values) => values[index].to_string(),
+            ArrayValues::Bits(values) => u8::from(values[index]).to_string(),
+        }
+    }
+}
+
+pub fn grid_array(lifehash: &LifeHash, data: GridData) -> GridArray {
+    match data {
+        GridData::Frac => frac_array(lifehash),
+        GridData::History => history_array(lifehash),
+        GridData::Color => color_array(lifehash),
+    }
+}
+
+/// The frac grid as a `y` by `x` array.
+pub fn frac_array(lifehash: &LifeHash) -> GridArray {
+    let grid = &lifehash.frac_grid;
+    GridArray {
+        axes: vec![("y", grid.dimensions.height), ("x", grid.dimensions.width)],
+        fields: vec!["frac"],
+        values: ArrayValues::Float(grid.storage.clone()),
+    }
+}
+
+/// The cell history as a `generation` by `y` by `x` array.
+pub fn history_array(lifehash: &LifeHash) -> GridArray {
+    let dimensions = lifehash.frac_grid.dimensions;
+    let mut cells = Cellgrid::new(dimensions);
+    let mut values = Vec::with_capacity(lifehash.history.len() * cells.storage.len());
+    for generation in &lifehash.history {
+        cells.set_data(generation);
+        values.extend_from_slice(&cells.storage);
+    }
+    GridArray {
+        axes: vec![
+            ("generation", lifehash.history.len()),
+            ("y", dimensions.height),
+            ("x", dimensions.width),
+        ],
+        fields: vec!["alive"],
+        values: ArrayValues::Bits(values),
+    }
+}
+
+/// The color grid as a `y` by `x` by RGB array.
+pub fn color_array(lifehash: &LifeHash) -> GridArray {
+    let grid = &lifehash.color_grid;
+    GridArray {
+        axes: vec![("y", grid.dimensions.height), ("x", grid.dimensions.width)],
+        fields: vec!["r", "g", "b"],
+        values: ArrayValues::Float(
+            grid.storage
+                .iter()
+                .flat_map(|color| [color.r, color.g, color.b])
+                .collect(),
+        ),
+    }
+}
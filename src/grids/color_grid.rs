@@ -3,13 +3,13 @@ use crate::colors::rgb::Color;
 use crate::grids::frac_grid::FracGrid;
 use crate::grids::Grid;
 use crate::utils::target_size;
-use crate::{ColorValues, Pattern, Point};
+use crate::{ColorValues, Pattern};
 
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub struct Transform {
-    transpose: bool,
-    reflect_x: bool,
-    reflect_y: bool,
+    pub(crate) transpose: bool,
+    pub(crate) reflect_x: bool,
+    pub(crate) reflect_y: bool,
 }
 
 pub const SNOWFLAKE_TRANSFORMS: [Transform; 4] = [
@@ -64,16 +64,39 @@ pub const FIDUCIAL_TRANSFORMS: [Transform; 1] = [Transform {
     reflect_y: false,
 }];
 
+/// The transforms that expand the frac grid into the color grid for the pattern.
+pub fn pattern_transforms(pattern: Pattern) -> &'static [Transform] {
+    match pattern {
+        Pattern::Snowflake => SNOWFLAKE_TRANSFORMS.as_slice(),
+        Pattern::Pinwheel => PINWHEEL_TRANSFORMS.as_slice(),
+        Pattern::Fiducial => FIDUCIAL_TRANSFORMS.as_slice(),
+    }
+}
+
+impl Transform {
+    /// Maps a continuous point in a color grid of `size` cells back to the point it was drawn from,
+    /// the inverse of [`ColorGrid::transform_point`] with cell centers at half cells.
+    pub fn invert(&self, x: f64, y: f64, size: f64) -> (f64, f64) {
+        let mut result = (x, y);
+        if self.reflect_x {
+            result.0 = size - result.0;
+        }
+        if self.reflect_y {
+            result.1 = size - result.1;
+        }
+        if self.transpose {
+            result = (result.1, result.0);
+        }
+        result
+    }
+}
+
 pub type ColorGrid = Grid<Color, ColorGridImpl>;
 pub struct ColorGridImpl;
 impl ColorGrid {
     pub fn create(frac_grid: &FracGrid, gradient: &ColorFunction, pattern: Pattern) -> Self {
         let mut color_grid = ColorGrid::new(target_size(frac_grid.dimensions, pattern));
-        let transforms = match pattern {
-            Pattern::Snowflake => SNOWFLAKE_TRANSFORMS.as_slice(),
-            Pattern::Pinwheel => PINWHEEL_TRANSFORMS.as_slice(),
-            Pattern::Fiducial => FIDUCIAL_TRANSFORMS.as_slice(),
-        };
+        let transforms = pattern_transforms(pattern);
         for point in frac_grid.get_points() {
             let value = *frac_grid.get_value(point);
             let color = gradient.apply(value);
@@ -81,25 +104,6 @@ impl ColorGrid {
         }
         color_grid
     }
-    pub fn transform_point(&self, point: Point, transform: Transform) -> Point {
-        let mut result = point;
-        if transform.transpose {
-            (result.x, result.y) = (result.y, result.x);
-        }
-        if transform.reflect_x {
-            result.x = self.max_x - result.x;
-        }
-        if transform.reflect_y {
-            result.y = self.max_y - result.y;
-        }
-        result
-    }
-    pub fn draw(&mut self, point: Point, color: Color, transforms: &[Transform]) {
-        for t in transforms {
-            let p2 = self.transform_point(point, *t);
-            self.set_value(color, p2);
-        }
-    }
 }
 impl ColorValues<Color> for ColorGridImpl {
     fn color_for_value(value: &Color) -> Color {
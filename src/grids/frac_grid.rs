@@ -1,7 +1,17 @@
 use crate::colors::{rgb::Color, BLACK, WHITE};
 use crate::grids::cell_grid::Cellgrid;
+use crate::grids::color_grid::pattern_transforms;
 use crate::grids::Grid;
-use crate::ColorValues;
+use crate::utils::target_size;
+use crate::{ColorValues, Pattern, Point};
+use clap::ValueEnum;
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, ValueEnum)]
+pub enum Interpolation {
+    Bilinear,
+    #[default]
+    Bicubic,
+}
 
 pub type FracGrid = Grid<f64, FracGridImpl>;
 pub struct FracGridImpl;
@@ -13,6 +23,50 @@ impl FracGrid {
             }
         }
     }
+    /// Mirrors the grid with the pattern transforms, the same way the color grid is drawn.
+    pub fn expand(&self, pattern: Pattern) -> FracGrid {
+        let mut expanded = FracGrid::new(target_size(self.dimensions, pattern));
+        let transforms = pattern_transforms(pattern);
+        for point in self.get_points() {
+            expanded.draw(point, *self.get_value(point), transforms);
+        }
+        expanded
+    }
+    /// Samples the grid at a continuous point with cell centers at half cells. The grid wraps
+    /// toroidally like the Life field, so the result is periodic.
+    pub fn sample(&self, x: f64, y: f64, interpolation: Interpolation) -> f64 {
+        let fx = x - 0.5;
+        let fy = y - 0.5;
+        let x0 = fx.floor();
+        let y0 = fy.floor();
+        let tx = fx - x0;
+        let ty = fy - y0;
+        let value = |ox: i64, oy: i64| {
+            let px = (x0 as i64 + ox).rem_euclid(self.dimensions.width as i64) as usize;
+            let py = (y0 as i64 + oy).rem_euclid(self.dimensions.height as i64) as usize;
+            *self.get_value(Point { x: px, y: py })
+        };
+        match interpolation {
+            Interpolation::Bilinear => {
+                let top = value(0, 0) * (1.0 - tx) + value(1, 0) * tx;
+                let bottom = value(0, 1) * (1.0 - tx) + value(1, 1) * tx;
+                top * (1.0 - ty) + bottom * ty
+            }
+            Interpolation::Bicubic => {
+                let rows =
+                    [-1, 0, 1, 2].map(|oy| catmull_rom([-1, 0, 1, 2].map(|ox| value(ox, oy)), tx));
+                // Catmull-Rom overshoots, keep the value inside the gradient.
+                catmull_rom(rows, ty).clamp(0.0, 1.0)
+            }
+        }
+    }
+}
+
+fn catmull_rom(p: [f64; 4], t: f64) -> f64 {
+    0.5 * (2.0 * p[1]
+        + (p[2] - p[0]) * t
+        + (2.0 * p[0] - 5.0 * p[1] + 4.0 * p[2] - p[3]) * t * t
+        + (3.0 * p[1] - p[0] - 3.0 * p[2] + p[3]) * t * t * t)
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -1,6 +1,8 @@
+use crate::grids::color_grid::Transform;
 use crate::{ColorValues, Dimensions, Point};
 use std::marker::PhantomData;
 
+pub mod array;
 pub mod cell_grid;
 pub mod change_grid;
 pub mod color_grid;
@@ -52,6 +54,25 @@ impl<T: Default + Clone, C: ColorValues<T>> Grid<T, C> {
         }
         points
     }
+    pub fn transform_point(&self, point: Point, transform: Transform) -> Point {
+        let mut result = point;
+        if transform.transpose {
+            (result.x, result.y) = (result.y, result.x);
+        }
+        if transform.reflect_x {
+            result.x = self.max_x - result.x;
+        }
+        if transform.reflect_y {
+            result.y = self.max_y - result.y;
+        }
+        result
+    }
+    pub fn draw(&mut self, point: Point, value: T, transforms: &[Transform]) {
+        for t in transforms {
+            let p2 = self.transform_point(point, *t);
+            self.set_value(value.clone(), p2);
+        }
+    }
     pub fn get_neighborhood(&self, point: Point) -> Vec<(bool, Point)> {
         let mut points = vec![];
         for oy in -1..2 {
@@ -0,0 +1,83 @@
+use crate::lifehash::LifeHash;
+use crate::{save_image, Image};
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+/// The sizes of a typical favicon.
+pub const ICO_SIZES: [usize; 6] = [16, 32, 48, 64, 128, 256];
+/// iOS asset catalog scales.
+pub const IOS_SCALES: [usize; 3] = [1, 2, 3];
+/// Android density buckets and their scale relative to mdpi, in quarters.
+pub const ANDROID_DENSITIES: [(&str, usize); 5] = [
+    ("mdpi", 4),
+    ("hdpi", 6),
+    ("xhdpi", 8),
+    ("xxhdpi", 12),
+    ("xxxhdpi", 16),
+];
+
+/// Renders the lifehash once per pixel size.
+pub fn render_icon_sizes(
+    lifehash: &LifeHash,
+    sizes: &[usize],
+    has_alpha: bool,
+) -> Result<Vec<Image>, Error> {
+    sizes
+        .iter()
+        .map(|size| lifehash.image_sized(*size, has_alpha))
+        .collect()
+}
+
+/// Writes `name.png`, `name@2x.png` and `name@3x.png` for the web, the same images as an iOS
+/// `name.imageset` with its `Contents.json`, and `mipmap-*/name.png` for the Android densities.
+/// `base_size` is the @1x (and mdpi) size in pixels.
+pub fn export_icon_set(
+    lifehash: &LifeHash,
+    directory: &Path,
+    name: &str,
+    base_size: usize,
+    has_alpha: bool,
+) -> Result<(), Error> {
+    // Android resource names are the most restrictive.
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Icon names may only contain lowercase letters, digits and underscores",
+        ));
+    }
+    let image_set = directory.join(format!("{name}.imageset"));
+    fs::create_dir_all(&image_set)?;
+    let mut contents = vec![];
+    for scale in IOS_SCALES {
+        let filename = if scale == 1 {
+            format!("{name}.png")
+        } else {
+            format!("{name}@{scale}x.png")
+        };
+        let image = lifehash.image_sized(base_size * scale, has_alpha)?;
+        save_image(&image, &directory.join(&filename))?;
+        save_image(&image, &image_set.join(&filename))?;
+        contents.push(format!(
+            "    {{\n      \"filename\" : \"{filename}\",\n      \"idiom\" : \"universal\",\n      \"scale\" : \"{scale}x\"\n    }}"
+        ));
+    }
+    fs::write(
+        image_set.join("Contents.json"),
+        format!(
+            "{{\n  \"images\" : [\n{}\n  ],\n  \"info\" : {{\n    \"author\" : \"lifehash\",\n    \"version\" : 1\n  }}\n}}\n",
+            contents.join(",\n")
+        ),
+    )?;
+    for (density, quarters) in ANDROID_DENSITIES {
+        let mipmap = directory.join(format!("mipmap-{density}"));
+        fs::create_dir_all(&mipmap)?;
+        let image = lifehash.image_sized(base_size * quarters / 4, has_alpha)?;
+        save_image(&image, &mipmap.join(format!("{name}.png")))?;
+    }
+    Ok(())
+}
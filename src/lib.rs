@@ -1,32 +1,65 @@
 extern crate core;
 
+use crate::colors::cmyk::{gamut_report, CmykProfile};
 use crate::colors::rgb::Color;
-use crate::lifehash::{from_data, from_digest};
-use clap::Parser;
-use png::ColorType;
+use crate::encoders::framebuffer::{encode_framebuffer, FramebufferOptions, PixelFormat};
+use crate::encoders::ico::encode_ico;
+use crate::encoders::pdf::encode_pdf;
+use crate::encoders::png::{
+    encode_mono_png, encode_png, encode_png_with, PngCompression, PngFilter, PngOptions, PngSample,
+};
+use crate::encoders::print::{encode_print_pdf, parse_length, PrintOptions};
+use crate::encoders::svg::encode_svg;
+use crate::encoders::{data_uri, DataFormat, ImageFormat, LifeFormat, MeshFormat};
+use crate::gallery::{write_gallery, GalleryEntry, GalleryImages};
+use crate::grids::array::{grid_array, GridData};
+use crate::grids::frac_grid::Interpolation;
+use crate::icons::{export_icon_set, render_icon_sizes, ICO_SIZES};
+use crate::lifehash::{quantize16, quantize8, LifeHash, MAX_IMAGE_SIZE};
+use crate::mesh::{relief_mesh, ReliefOptions};
+use crate::render::card::{apply_card, card_image, card_vector, Card};
+use crate::render::compare::{difference, difference_image, CompareWith};
+use crate::render::contour::contour_image;
+use crate::render::dither::{dither_image, Dither};
+use crate::render::frame::{apply_frame, frame_vector, Frame};
+use crate::render::mask::{apply_mask, Mask, MaskShape};
+use crate::render::overlay::{apply_opacity, apply_opacity16, opacity_map, OpacityCurve, Overlay};
+use crate::render::sheet::{sheet_image, sheet_image16, sheet_vector, Sheet, SheetInput};
+use crate::render::smooth::{smooth_image, smooth_image16};
+use crate::render::tile::tile;
+use crate::stamp::{stamp_png, Anchor, StampOptions};
+use crate::vector::VectorImage;
+use clap::{Parser, ValueEnum};
 use std::fs::File;
 use std::io;
-use std::io::{BufWriter, Error, ErrorKind, Read};
+use std::io::{BufWriter, Error, ErrorKind, IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
 
 pub mod colors;
-mod grids;
+pub mod encoders;
+pub mod gallery;
+pub mod grids;
+pub mod icons;
 pub mod lifehash;
+pub mod mesh;
+pub mod render;
+pub mod stamp;
 pub mod utils;
+pub mod vector;
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct Dimensions {
     pub width: usize,
     pub height: usize,
 }
 
 #[derive(Copy, Clone, Eq, PartialEq)]
-struct Point {
+pub struct Point {
     pub x: usize,
     pub y: usize,
 }
 
-trait ColorValues<T> {
+pub trait ColorValues<T> {
     fn color_for_value(value: &T) -> Color;
 }
 
@@ -50,33 +83,118 @@ impl From<u8> for Version {
     }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum Pattern {
     Snowflake, // Mirror around central axes.
     Pinwheel,  // Rotate around center.
     Fiducial,  // Identity.
 }
 
-pub struct Image {
+/// How an image is fitted into a pixel size that isn't a multiple of the grid size.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, ValueEnum)]
+pub enum Fit {
+    /// Spread the remainder over the cells, so their sizes differ by at most one pixel.
+    #[default]
+    Distribute,
+    /// Use the largest whole module size and center the image, padding the remainder.
+    Letterbox,
+}
+
+pub struct Image<T = u8> {
     pub width: usize,
     pub height: usize,
     pub channels: usize,
-    pub pixels: Vec<u8>,
+    pub pixels: Vec<T>,
+}
+impl<T: Copy + PartialEq> Image<T> {
+    /// True if every pixel has equal red, green and blue components.
+    pub fn is_grayscale(&self) -> bool {
+        self.channels < 3
+            || self
+                .pixels
+                .chunks_exact(self.channels)
+                .all(|p| p[0] == p[1] && p[1] == p[2])
+    }
+    /// Collapses the color channels into a single gray channel, keeping alpha if present.
+    pub fn to_grayscale(&self) -> Image<T> {
+        if self.channels < 3 {
+            return Image {
+                width: self.width,
+                height: self.height,
+                channels: self.channels,
+                pixels: self.pixels.clone(),
+            };
+        }
+        let has_alpha = self.channels == 4;
+        let mut pixels = Vec::with_capacity(self.width * self.height * (1 + has_alpha as usize));
+        for pixel in self.pixels.chunks_exact(self.channels) {
+            pixels.push(pixel[0]);
+            if has_alpha {
+                pixels.push(pixel[3]);
+            }
+        }
+        Image {
+            width: self.width,
+            height: self.height,
+            channels: if has_alpha { 2 } else { 1 },
+            pixels,
+        }
+    }
+}
+
+impl Image {
+    pub fn to_u16(&self) -> Image<u16> {
+        Image {
+            width: self.width,
+            height: self.height,
+            channels: self.channels,
+            pixels: self.pixels.iter().map(|p| *p as u16 * 257).collect(),
+        }
+    }
+}
+impl Image<u16> {
+    pub fn to_u8(&self) -> Image {
+        Image {
+            width: self.width,
+            height: self.height,
+            channels: self.channels,
+            pixels: self.pixels.iter().map(|p| (*p >> 8) as u8).collect(),
+        }
+    }
 }
 
-pub fn save_image(bitmap: &Image, filename: &Path) -> Result<(), Error> {
+pub fn save_image<T: PngSample>(bitmap: &Image<T>, filename: &Path) -> Result<(), Error> {
     let file = File::create(filename)?;
-    let buffer = BufWriter::new(file);
-    let mut png = png::Encoder::new(buffer, bitmap.width as u32, bitmap.height as u32);
-    if bitmap.channels == 4 {
-        png.set_color(ColorType::Rgba);
+    encode_png(bitmap, BufWriter::new(file))
+}
+
+/// Writes the PNG encoded image to stdout. Refuses to dump binary data into a terminal unless `force` is set.
+pub fn save_image_to_stdout<T: PngSample>(bitmap: &Image<T>, force: bool) -> Result<(), Error> {
+    let mut writer = open_output(Path::new("-"), force)?;
+    encode_png(bitmap, &mut writer)?;
+    writer.flush()
+}
+
+/// Opens a buffered writer for the file, or for stdout if the filename is `-`.
+/// Refuses to dump binary data into a terminal unless `force` is set.
+pub fn open_output(filename: &Path, force: bool) -> Result<Box<dyn Write>, Error> {
+    if is_stdout(filename) {
+        let stdout = io::stdout();
+        if !force && stdout.is_terminal() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "refusing to write binary image data to a terminal, use --force to override",
+            ));
+        }
+        Ok(Box::new(BufWriter::new(stdout.lock())))
     } else {
-        //Default to RGB if no Alpha, Grayscale uses 3 Channels
-        png.set_color(ColorType::Rgb);
+        Ok(Box::new(BufWriter::new(File::create(filename)?)))
     }
-    let mut writer = png.write_header()?;
-    writer.write_image_data(&bitmap.pixels)?;
-    Ok(writer.finish()?)
+}
+
+/// The output path that selects stdout instead of a file.
+pub fn is_stdout(filename: &Path) -> bool {
+    filename == Path::new("-")
 }
 
 pub enum Either<T, U> {
@@ -85,11 +203,48 @@ pub enum Either<T, U> {
 }
 
 pub struct RuntimeArgs {
-    pub module_size: u8,
+    pub module_size: usize,
+    pub size: Option<usize>,
+    pub fit: Fit,
+    pub smooth: Option<Interpolation>,
+    pub tile: Option<Dimensions>,
+    pub tile_offset: (usize, usize),
+    pub contour: bool,
+    pub contour_levels: Option<usize>,
+    pub mask: Mask,
+    pub frame: Frame,
+    pub card: Option<Card>,
+    pub compare: Option<CompareWith>,
+    pub sheet: Option<SheetInput>,
+    pub gallery: Option<SheetInput>,
+    pub gallery_images: GalleryImages,
+    pub sheet_options: Sheet,
+    pub overlay: Option<Overlay>,
+    pub stamp: Option<PathBuf>,
+    pub stamp_options: StampOptions,
     pub use_alpha: bool,
     pub version: Version,
     pub input: Either<String, Vec<u8>>,
     pub output_file: PathBuf,
+    pub force: bool,
+    pub data_uri: bool,
+    pub bit_depth: u8,
+    pub png_options: PngOptions,
+    pub format: ImageFormat,
+    pub icon_sizes: Vec<usize>,
+    pub icon_set: Option<PathBuf>,
+    pub icon_base_size: usize,
+    pub print: Option<PrintOptions>,
+    /// Print a gamut report with this largest change of a color in gamut.
+    pub gamut_report: Option<f64>,
+    pub export: Option<GridData>,
+    pub data_format: DataFormat,
+    pub life: Option<LifeFormat>,
+    pub generation: usize,
+    pub mesh: Option<MeshFormat>,
+    pub relief: ReliefOptions,
+    pub dither: Option<Dither>,
+    pub framebuffer: Option<FramebufferOptions>,
 }
 
 #[derive(Parser, Debug)]
@@ -99,14 +254,189 @@ struct Cli {
     pub input_file: Option<PathBuf>,
     #[arg(short = 'x', long = "hex")]
     pub hex: Option<String>,
+    /// Output file, use - to write to stdout
     #[arg(short = 'o', long = "out-file", value_name = "FILE")]
     pub output_file: Option<PathBuf>,
+    /// Write binary output to stdout even if it is a terminal
+    #[arg(short = 'f', long = "force")]
+    pub force: bool,
+    /// Write the output as a base64 data URI on one line of text, for embedding in HTML or JSON
+    #[arg(long = "data-uri")]
+    pub data_uri: bool,
     #[arg(short = 'm', long = "module-size")]
-    pub module_size: Option<u8>,
+    pub module_size: Option<usize>,
+    /// Render to exactly this many pixels square instead of a multiple of the module size
+    #[arg(short = 's', long = "size", value_name = "PIXELS")]
+    pub size: Option<usize>,
+    #[arg(long = "fit", value_enum)]
+    pub fit: Option<Fit>,
+    /// Interpolate the lifehash for a smooth image instead of crisp cells
+    #[arg(long = "smooth", value_enum)]
+    pub smooth: Option<Interpolation>,
+    /// Repeat the lifehash into a seamless texture of WIDTHxHEIGHT pixels
+    #[arg(long = "tile", value_name = "WIDTHxHEIGHT", value_parser = parse_dimensions)]
+    pub tile: Option<Dimensions>,
+    /// Shift the tiled texture by X,Y pixels
+    #[arg(long = "tile-offset", value_name = "X,Y", value_parser = parse_offset)]
+    pub tile_offset: Option<(usize, usize)>,
+    /// Trace the lifehash as smooth vector contours, for SVG or PDF output
+    #[arg(long = "contour")]
+    pub contour: bool,
+    /// Number of contour levels, by default one per distinct frac value
+    #[arg(long = "contour-levels", value_name = "LEVELS")]
+    pub contour_levels: Option<usize>,
+    /// Cut the image to a shape with anti-aliased transparent edges
+    #[arg(long = "mask", value_enum)]
+    pub mask: Option<MaskShape>,
+    /// Corner radius of the rounded mask as a fraction of the image size
+    #[arg(long = "mask-radius", value_name = "FRACTION")]
+    pub mask_radius: Option<f64>,
+    /// Transparent margin around the image in pixels
+    #[arg(long = "margin", value_name = "PIXELS")]
+    pub margin: Option<usize>,
+    /// Plain padding around the image in modules, outside of the border
+    #[arg(long = "quiet-zone", value_name = "MODULES")]
+    pub quiet_zone: Option<usize>,
+    /// Width in modules of a contrasting ring around the image
+    #[arg(long = "border", value_name = "MODULES")]
+    pub border: Option<usize>,
+    /// Put the image on a card above a caption with the digest fingerprint
+    #[arg(long = "card")]
+    pub card: bool,
+    /// Caption of the card instead of the fingerprint, implies --card
+    #[arg(long = "label", value_name = "TEXT")]
+    pub label: Option<String>,
+    /// Make the image translucent, with the opacity following the lifehash structure
+    #[arg(long = "overlay")]
+    pub overlay: bool,
+    /// How the overlay maps the lifehash structure to opacity
+    #[arg(long = "overlay-curve", value_enum)]
+    pub overlay_curve: Option<OpacityCurve>,
+    /// Overlay opacity from 0 to 1 where the lifehash structure is lowest
+    #[arg(long = "min-opacity")]
+    pub min_opacity: Option<f64>,
+    /// Overlay opacity from 0 to 1 where the lifehash structure is highest
+    #[arg(long = "max-opacity")]
+    pub max_opacity: Option<f64>,
+    /// Compare with the lifehash of this hex digest, side by side with a difference panel
+    #[arg(long = "compare-hex", value_name = "HEX")]
+    pub compare_hex: Option<String>,
+    /// Compare with the lifehash of this version, side by side with a difference panel
+    #[arg(long = "compare-version", value_name = "VERSION")]
+    pub compare_version: Option<u8>,
+    /// Render a contact sheet with one lifehash per line of the input
+    #[arg(long = "sheet", value_enum, value_name = "LINES")]
+    pub sheet: Option<SheetInput>,
+    /// Write an HTML gallery with one lifehash per line of the input
+    #[arg(long = "gallery", value_enum, value_name = "LINES")]
+    pub gallery: Option<SheetInput>,
+    /// Embed the gallery images or link PNG files written next to the HTML file
+    #[arg(long = "gallery-images", value_enum)]
+    pub gallery_images: Option<GalleryImages>,
+    /// Lifehashes per row of the contact sheet
+    #[arg(long = "columns")]
+    pub columns: Option<usize>,
+    /// Space between the lifehashes of the contact sheet in pixels
+    #[arg(long = "spacing", value_name = "PIXELS")]
+    pub spacing: Option<usize>,
+    /// Background of the contact sheet as #rrggbb
+    #[arg(long = "background", value_name = "COLOR", value_parser = parse_color)]
+    pub background: Option<Color>,
+    /// Stamp the lifehash onto this PNG and write the result as PNG
+    #[arg(long = "stamp", value_name = "PNG")]
+    pub stamp: Option<PathBuf>,
+    /// Where to place the stamp
+    #[arg(long = "anchor", value_enum)]
+    pub anchor: Option<Anchor>,
+    /// Distance of the stamp from the edges of the base image in pixels
+    #[arg(long = "stamp-margin", value_name = "PIXELS")]
+    pub stamp_margin: Option<usize>,
+    /// Width of a border around the stamp in pixels
+    #[arg(long = "stamp-border", value_name = "PIXELS")]
+    pub stamp_border: Option<usize>,
+    /// Offset and blur of a drop shadow under the stamp in pixels
+    #[arg(long = "shadow", value_name = "PIXELS")]
+    pub shadow: Option<usize>,
     #[arg(short = 'a', long = "use-alpha")]
     pub use_alpha: Option<bool>,
     #[arg(short = 'v', long = "lh-version")]
     pub lh_version: Option<u8>,
+    /// Bits per channel of the PNG, 8 or 16
+    #[arg(short = 'd', long = "bit-depth")]
+    pub bit_depth: Option<u8>,
+    /// Store the PNG with a color palette when it has at most 256 colors
+    #[arg(short = 'p', long = "palette")]
+    pub palette: bool,
+    #[arg(long = "compression", value_enum)]
+    pub compression: Option<PngCompression>,
+    #[arg(long = "filter", value_enum)]
+    pub filter: Option<PngFilter>,
+    /// Image format, guessed from the output file extension if not set
+    #[arg(long = "format", value_enum)]
+    pub format: Option<ImageFormat>,
+    /// Comma separated pixel sizes of the entries in an .ico file
+    #[arg(long = "icon-sizes", value_delimiter = ',')]
+    pub icon_sizes: Option<Vec<usize>>,
+    /// Write @1x/@2x/@3x PNGs, an iOS image set and Android mipmaps to this directory
+    #[arg(long = "icon-set", value_name = "DIR")]
+    pub icon_set: Option<PathBuf>,
+    /// The @1x pixel size of the icon set
+    #[arg(long = "icon-base-size")]
+    pub icon_base_size: Option<usize>,
+    /// Print the color grid at this physical size in a PDF, like 40mm, 4cm, 1.5in or 100pt
+    #[arg(long = "print-size", value_name = "SIZE", value_parser = parse_print_size)]
+    pub print_size: Option<f64>,
+    /// Text below the printed lifehash
+    #[arg(long = "caption", value_name = "TEXT")]
+    pub caption: Option<String>,
+    /// Add crop marks around the printed lifehash
+    #[arg(long = "crop-marks")]
+    pub crop_marks: bool,
+    /// Separate the colors of the printed lifehash into CMYK inks
+    #[arg(long = "cmyk")]
+    pub cmyk: bool,
+    /// Print how much each color of the lifehash changes when printed in CMYK
+    #[arg(long = "gamut-report")]
+    pub gamut_report: bool,
+    /// Largest delta E of a color in the gamut report that counts as in gamut, implies --gamut-report
+    #[arg(long = "max-delta-e", value_name = "DE")]
+    pub max_delta_e: Option<f64>,
+    /// Write the numbers of a grid instead of an image
+    #[arg(long = "export", value_enum, value_name = "GRID")]
+    pub export: Option<GridData>,
+    /// Format of the exported grid, guessed from a .csv, .json or .npy output file if not set
+    #[arg(long = "data-format", value_enum)]
+    pub data_format: Option<DataFormat>,
+    /// Write the Life cells of a generation, guessed from a .rle, .lif or .life output file if not set
+    #[arg(long = "life", value_enum)]
+    pub life: Option<LifeFormat>,
+    /// The generation of the Life export, 0 for the seed
+    #[arg(long = "generation")]
+    pub generation: Option<usize>,
+    /// Write a 3D printable relief mesh, guessed from a .stl, .obj or .ply output file if not set
+    #[arg(long = "mesh", value_enum)]
+    pub mesh: Option<MeshFormat>,
+    /// Width of a relief module in millimeters
+    #[arg(long = "relief-module", value_name = "MM")]
+    pub relief_module: Option<f64>,
+    /// Thickness of the solid base of the relief in millimeters
+    #[arg(long = "relief-base", value_name = "MM")]
+    pub relief_base: Option<f64>,
+    /// Height of the relief above the base for a frac value of 1 in millimeters
+    #[arg(long = "relief-height", value_name = "MM")]
+    pub relief_height: Option<f64>,
+    /// Color the relief vertices from the lifehash, for OBJ and PLY meshes
+    #[arg(long = "vertex-colors")]
+    pub vertex_colors: bool,
+    /// Dither the modules to black and white, for e-ink screens and thermal printers
+    #[arg(long = "dither", value_enum, value_name = "METHOD")]
+    pub dither: Option<Dither>,
+    /// Write raw pixels in this layout for a display controller, with no header
+    #[arg(long = "pixel-format", value_enum)]
+    pub pixel_format: Option<PixelFormat>,
+    /// Bytes from one row of the raw pixels to the next, rows are padded with zeros
+    #[arg(long = "stride", value_name = "BYTES")]
+    pub stride: Option<usize>,
 }
 
 pub fn parse_cli_from<I, T>(itr: I) -> Result<RuntimeArgs, Error>
@@ -115,22 +445,203 @@ where
     T: Into<std::ffi::OsString> + Clone,
 {
     let cli = Cli::parse_from(itr);
+    let bit_depth = cli.bit_depth.unwrap_or(8);
+    if bit_depth != 8 && bit_depth != 16 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("unsupported bit depth {bit_depth}, expected 8 or 16"),
+        ));
+    }
+    if cli.print_size.is_none() && (cli.caption.is_some() || cli.crop_marks || cli.cmyk) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "--caption, --crop-marks and --cmyk apply to --print-size, TIFF output is always CMYK",
+        ));
+    }
+    if cli.pixel_format.is_none() && cli.stride.is_some() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "--stride applies to --pixel-format",
+        ));
+    }
+    for (value, name) in [
+        (cli.mask_radius, "--mask-radius"),
+        (cli.min_opacity, "--min-opacity"),
+        (cli.max_opacity, "--max-opacity"),
+    ] {
+        // NaN is outside of the range too.
+        if value.is_some_and(|value| !(0.0..=1.0).contains(&value)) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("{name} must be from 0 to 1"),
+            ));
+        }
+    }
+    if cli.columns == Some(0) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "--columns must be at least 1",
+        ));
+    }
+    if cli.pixel_format.is_some() && (cli.dither.is_some() || cli.data_uri) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "--pixel-format writes raw color pixels, not dithered images or data URIs",
+        ));
+    }
+    let output_file = cli
+        .output_file
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("./lifehash.png"));
     Ok(RuntimeArgs {
         module_size: cli.module_size.unwrap_or(1),
+        size: cli.size,
+        fit: cli.fit.unwrap_or_default(),
+        smooth: cli.smooth,
+        tile: cli.tile,
+        tile_offset: cli.tile_offset.unwrap_or_default(),
+        contour: cli.contour,
+        contour_levels: cli.contour_levels,
+        mask: Mask {
+            shape: cli.mask,
+            radius: cli.mask_radius.unwrap_or(Mask::default().radius),
+            margin: cli.margin.unwrap_or(0),
+        },
+        frame: Frame {
+            quiet_zone: cli.quiet_zone.unwrap_or(0),
+            border: cli.border.unwrap_or(0),
+        },
+        card: (cli.card || cli.label.is_some()).then(|| Card {
+            label: cli.label.clone(),
+        }),
+        overlay: cli.overlay.then(|| {
+            let default = Overlay::default();
+            Overlay {
+                curve: cli.overlay_curve.unwrap_or(default.curve),
+                min_opacity: cli.min_opacity.unwrap_or(default.min_opacity),
+                max_opacity: cli.max_opacity.unwrap_or(default.max_opacity),
+            }
+        }),
+        compare: parse_compare(&cli)?,
+        sheet: cli.sheet,
+        gallery: cli.gallery,
+        gallery_images: cli.gallery_images.unwrap_or_default(),
+        sheet_options: Sheet {
+            columns: cli.columns,
+            spacing: cli.spacing.unwrap_or(Sheet::default().spacing),
+            background: cli.background.unwrap_or(Sheet::default().background),
+        },
+        stamp: cli.stamp.clone(),
+        stamp_options: StampOptions {
+            anchor: cli.anchor.unwrap_or_default(),
+            margin: cli.stamp_margin.unwrap_or(16),
+            border: cli.stamp_border.unwrap_or(0),
+            shadow: cli.shadow.unwrap_or(0),
+        },
         use_alpha: cli.use_alpha.unwrap_or(false),
         version: cli.lh_version.map_or(Version::Version2, Version::from),
         input: parse_input(&cli)?,
-        output_file: cli
-            .output_file
-            .unwrap_or_else(|| PathBuf::from("./lifehash.png")),
+        format: cli
+            .format
+            .or_else(|| ImageFormat::from_path(&output_file))
+            .unwrap_or_default(),
+        print: cli.print_size.map(|size| PrintOptions {
+            size,
+            caption: cli.caption.clone(),
+            crop_marks: cli.crop_marks,
+            cmyk: cli.cmyk.then(CmykProfile::default),
+        }),
+        gamut_report: (cli.gamut_report || cli.max_delta_e.is_some())
+            .then(|| cli.max_delta_e.unwrap_or(5.0)),
+        export: cli.export,
+        data_format: cli
+            .data_format
+            .or_else(|| DataFormat::from_path(&output_file))
+            .unwrap_or_default(),
+        life: cli.life.or_else(|| LifeFormat::from_path(&output_file)),
+        generation: cli.generation.unwrap_or(0),
+        mesh: cli.mesh.or_else(|| MeshFormat::from_path(&output_file)),
+        output_file,
+        force: cli.force,
+        data_uri: cli.data_uri,
+        icon_sizes: cli.icon_sizes.unwrap_or_else(|| ICO_SIZES.to_vec()),
+        icon_set: cli.icon_set,
+        icon_base_size: cli.icon_base_size.unwrap_or(64),
+        relief: ReliefOptions {
+            module_size: cli
+                .relief_module
+                .unwrap_or(ReliefOptions::default().module_size),
+            base: cli.relief_base.unwrap_or(ReliefOptions::default().base),
+            height: cli.relief_height.unwrap_or(ReliefOptions::default().height),
+            colors: cli.vertex_colors,
+        },
+        dither: cli.dither,
+        framebuffer: cli.pixel_format.map(|format| FramebufferOptions {
+            format,
+            stride: cli.stride,
+        }),
+        bit_depth,
+        png_options: PngOptions {
+            palette: cli.palette,
+            compression: cli.compression.unwrap_or_default(),
+            filter: cli.filter.unwrap_or_default(),
+            ..PngOptions::default()
+        },
+    })
+}
+
+fn parse_print_size(value: &str) -> Result<f64, String> {
+    parse_length(value).ok_or_else(|| format!("expected a size like 40mm or 1.5in, got {value}"))
+}
+
+fn parse_dimensions(value: &str) -> Result<Dimensions, String> {
+    let (width, height) = value
+        .split_once(['x', 'X'])
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT, got {value}"))?;
+    Ok(Dimensions {
+        width: width.parse().map_err(|e| format!("invalid width: {e}"))?,
+        height: height.parse().map_err(|e| format!("invalid height: {e}"))?,
     })
 }
 
+fn parse_offset(value: &str) -> Result<(usize, usize), String> {
+    let (x, y) = value
+        .split_once(',')
+        .ok_or_else(|| format!("expected X,Y, got {value}"))?;
+    Ok((
+        x.parse().map_err(|e| format!("invalid x: {e}"))?,
+        y.parse().map_err(|e| format!("invalid y: {e}"))?,
+    ))
+}
+
+fn parse_color(value: &str) -> Result<Color, String> {
+    Color::from_hex(value).ok_or_else(|| format!("expected #rrggbb, got {value}"))
+}
+
 #[cfg(not(tarpaulin_include))]
 pub fn parse_cli() -> Result<RuntimeArgs, Error> {
     parse_cli_from(std::env::args())
 }
 
+fn parse_compare(cli: &Cli) -> Result<Option<CompareWith>, Error> {
+    if cli.compare_hex.is_none() && cli.compare_version.is_none() {
+        return Ok(None);
+    }
+    let digest = cli
+        .compare_hex
+        .as_ref()
+        .map(|hex| {
+            hex::decode(hex).map_err(|e| {
+                Error::new(ErrorKind::InvalidData, format!("failed to decode hex: {e}"))
+            })
+        })
+        .transpose()?;
+    Ok(Some(CompareWith {
+        digest,
+        version: cli.compare_version.map(Version::from),
+    }))
+}
+
 fn parse_input(cli: &Cli) -> Result<Either<String, Vec<u8>>, Error> {
     if let Some(input) = &cli.input_file {
         let mut buf = String::new();
@@ -149,19 +660,511 @@ fn parse_input(cli: &Cli) -> Result<Either<String, Vec<u8>>, Error> {
 }
 
 pub fn run(args: RuntimeArgs) -> Result<(), Error> {
-    let (image, _) = match args.input {
-        Either::Left(input) => from_data(
-            input.as_bytes(),
-            args.version,
-            args.module_size as usize,
-            args.use_alpha,
-        )?,
-        Either::Right(input) => from_digest(
-            &input,
-            args.version,
-            args.module_size as usize,
+    if let Some(input) = args.sheet {
+        return run_sheet(&args, input);
+    }
+    if let Some(input) = args.gallery {
+        return run_gallery(&args, input);
+    }
+    let lifehash = match args.input {
+        Either::Left(ref input) => LifeHash::from_data(input.as_bytes(), args.version)?,
+        Either::Right(ref input) => LifeHash::from_digest(input, args.version)?,
+    };
+    if let Some(max_delta_e) = args.gamut_report {
+        let report = gamut_report(&lifehash, &CmykProfile::default(), max_delta_e);
+        // The report goes to stderr when stdout has the output.
+        if is_stdout(&args.output_file) {
+            eprintln!("{}", report.summary());
+        } else {
+            println!("{}", report.summary());
+        }
+    }
+    if let Some(with) = &args.compare {
+        return run_compare(&lifehash, with, &args);
+    }
+    if let Some(directory) = &args.icon_set {
+        check_image_options(&args, "an icon set", false)?;
+        return export_icon_set(
+            &lifehash,
+            directory,
+            "lifehash",
+            args.icon_base_size,
             args.use_alpha,
+        );
+    }
+    if let Some(print) = &args.print {
+        check_image_options(&args, "printing", false)?;
+        if args.format != ImageFormat::Pdf {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "printing at a physical size needs PDF output, use --format pdf or a .pdf file",
+            ));
+        }
+        let mut writer = open_output(&args.output_file, args.force)?;
+        encode_print_pdf(&lifehash, print, &mut writer)?;
+        return writer.flush();
+    }
+    if let Some(data) = args.export {
+        check_image_options(&args, "exporting grids", false)?;
+        let mut writer = open_output(
+            &args.output_file,
+            args.force || args.data_format != DataFormat::Npy,
+        )?;
+        args.data_format
+            .encode(&grid_array(&lifehash, data), &mut writer)?;
+        return writer.flush();
+    }
+    if let Some(format) = args.life {
+        check_image_options(&args, "exporting generations", false)?;
+        return run_life(&lifehash, format, &args);
+    }
+    if let Some(format) = args.mesh {
+        check_image_options(&args, "a relief mesh", false)?;
+        return run_mesh(&lifehash, format, &args);
+    }
+    if let Some(options) = &args.framebuffer {
+        check_image_options(&args, "a framebuffer", true)?;
+        let image = render_image(&lifehash, &args)?;
+        let mut writer = open_output(&args.output_file, args.force)?;
+        encode_framebuffer(&image, options, &mut writer)?;
+        return writer.flush();
+    }
+    if let Some(method) = args.dither {
+        check_image_options(&args, "dithering", false)?;
+        let image = dither_image(&lifehash, args.module_size, method)?;
+        return write_output(&args, |writer| {
+            if args.format == ImageFormat::Png {
+                return encode_mono_png(&image, writer);
+            }
+            args.format
+                .encoder(args.png_options)
+                .encode(&image.to_image(), writer)
+        });
+    }
+    check_contour(&args)?;
+    if args.stamp.is_some() && args.format != ImageFormat::Png {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "stamped images keep the PNG format of the base image",
+        ));
+    }
+    // The base is read first so it can be overwritten in place.
+    let base = args.stamp.as_ref().map(std::fs::read).transpose()?;
+    write_output(&args, |writer| {
+        if args.contour {
+            let image = render_vector(&lifehash, &args)?;
+            return encode_vector(&image, vector_scale(&lifehash, &args), &args, writer);
+        }
+        if args.format == ImageFormat::Ico {
+            let images = render_icon_sizes(&lifehash, &args.icon_sizes, args.use_alpha)?;
+            return encode_ico(&images, writer);
+        }
+        if let Some(base) = base {
+            let stamp = render_image(&lifehash, &args)?;
+            let border_color = lifehash.gradient.contrast_color();
+            return stamp_png(
+                base.as_slice(),
+                &stamp,
+                &args.stamp_options,
+                border_color,
+                writer,
+            );
+        }
+        let encoder = args.format.encoder(args.png_options);
+        if args.bit_depth == 16 {
+            encoder.encode16(&render_image16(&lifehash, &args)?, writer)
+        } else {
+            encoder.encode(&render_image(&lifehash, &args)?, writer)
+        }
+    })
+}
+
+/// Writes the cells of a generation as a Life pattern.
+fn run_life(lifehash: &LifeHash, format: LifeFormat, args: &RuntimeArgs) -> Result<(), Error> {
+    let grid = lifehash.generation(args.generation).ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "generation {} is past the last one, {}",
+                args.generation,
+                lifehash.generations() - 1
+            ),
+        )
+    })?;
+    let comments = [
+        format!(
+            "LifeHash {:?} of digest {}",
+            lifehash.version,
+            hex::encode(&lifehash.digest)
+        ),
+        format!(
+            "Generation {} of the {} that were run",
+            args.generation,
+            lifehash.generations()
+        ),
+    ];
+    let mut writer = open_output(&args.output_file, true)?;
+    format.encode(&grid, &comments, &mut writer)?;
+    writer.flush()
+}
+
+/// Writes the lifehash as a 3D relief.
+fn run_mesh(lifehash: &LifeHash, format: MeshFormat, args: &RuntimeArgs) -> Result<(), Error> {
+    if format == MeshFormat::Stl && args.relief.colors {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "STL meshes have no vertex colors, use OBJ or PLY",
+        ));
+    }
+    let mesh = relief_mesh(lifehash, &args.relief)?;
+    let mut writer = open_output(&args.output_file, args.force)?;
+    format.encode(&mesh, &mut writer)?;
+    writer.flush()
+}
+
+/// Passes `encode` a writer for the output, or with `--data-uri` a buffer that is then written
+/// to the output as a data URI. Data URIs are text, so they may go to a terminal.
+fn write_output(
+    args: &RuntimeArgs,
+    encode: impl FnOnce(&mut dyn Write) -> Result<(), Error>,
+) -> Result<(), Error> {
+    if args.data_uri {
+        let mut data = vec![];
+        encode(&mut data)?;
+        let mut writer = open_output(&args.output_file, true)?;
+        writeln!(writer, "{}", data_uri(args.format.mime_type(), &data))?;
+        return writer.flush();
+    }
+    let mut writer = open_output(&args.output_file, args.force)?;
+    encode(&mut writer)?;
+    writer.flush()
+}
+
+/// One lifehash per non-empty line of the input, with the line it was made from.
+fn read_lines(args: &RuntimeArgs, input: SheetInput) -> Result<Vec<(String, LifeHash)>, Error> {
+    let Either::Left(text) = &args.input else {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "batches read one input per line from a file or stdin",
+        ));
+    };
+    let lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let lifehash = match input {
+                SheetInput::Text => LifeHash::from_data(line.as_bytes(), args.version)?,
+                SheetInput::Hex => {
+                    let digest = hex::decode(line).map_err(|e| {
+                        Error::new(ErrorKind::InvalidData, format!("failed to decode hex: {e}"))
+                    })?;
+                    LifeHash::from_digest(&digest, args.version)?
+                }
+            };
+            Ok((line.to_string(), lifehash))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    if lines.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "no lines to render in the batch",
+        ));
+    }
+    Ok(lines)
+}
+
+/// Renders one lifehash per line of the input and writes an HTML gallery of them, with the
+/// images embedded or written next to it.
+fn run_gallery(args: &RuntimeArgs, input: SheetInput) -> Result<(), Error> {
+    let lines = read_lines(args, input)?;
+    let linked = args.gallery_images == GalleryImages::Link;
+    if linked && is_stdout(&args.output_file) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "linked gallery images are written next to the HTML file, which needs a name",
+        ));
+    }
+    let stem = args
+        .output_file
+        .file_stem()
+        .map_or("gallery".into(), |stem| stem.to_string_lossy());
+    let mut entries = Vec::with_capacity(lines.len());
+    for (i, (name, lifehash)) in lines.iter().enumerate() {
+        let mut png = vec![];
+        encode_png_with(&render_image(lifehash, args)?, &mut png, &args.png_options)?;
+        let src = if linked {
+            let file_name = format!("{stem}-{:04}.png", i + 1);
+            std::fs::write(args.output_file.with_file_name(&file_name), &png)?;
+            file_name
+        } else {
+            data_uri("image/png", &png)
+        };
+        entries.push(GalleryEntry {
+            name: name.clone(),
+            lifehash,
+            src,
+        });
+    }
+    let mut writer = open_output(&args.output_file, args.force)?;
+    write_gallery("LifeHash gallery", &entries, &mut writer)?;
+    writer.flush()
+}
+
+/// Renders one lifehash per non-empty line of the input into a contact sheet.
+fn run_sheet(args: &RuntimeArgs, input: SheetInput) -> Result<(), Error> {
+    let lifehashes: Vec<LifeHash> = read_lines(args, input)?
+        .into_iter()
+        .map(|(_, lifehash)| lifehash)
+        .collect();
+    let first = &lifehashes[0];
+    check_contour(args)?;
+    write_output(args, |writer| {
+        if args.contour {
+            let images = lifehashes
+                .iter()
+                .map(|lifehash| render_vector(lifehash, args))
+                .collect::<Result<Vec<_>, Error>>()?;
+            let scale = vector_scale(first, args);
+            let sheet = sheet_vector(&images, &args.sheet_options, scale);
+            return encode_vector(&sheet, scale, args, writer);
+        }
+        let encoder = args.format.encoder(args.png_options);
+        if args.bit_depth == 16 {
+            let images = lifehashes
+                .iter()
+                .map(|lifehash| render_image16(lifehash, args))
+                .collect::<Result<Vec<_>, Error>>()?;
+            encoder.encode16(&sheet_image16(&images, &args.sheet_options)?, writer)
+        } else {
+            let images = lifehashes
+                .iter()
+                .map(|lifehash| render_image(lifehash, args))
+                .collect::<Result<Vec<_>, Error>>()?;
+            encoder.encode(&sheet_image(&images, &args.sheet_options)?, writer)
+        }
+    })
+}
+
+/// Renders the lifehash next to the one it is compared with and a panel of their differences,
+/// and prints a summary of the differing modules.
+fn run_compare(lifehash: &LifeHash, with: &CompareWith, args: &RuntimeArgs) -> Result<(), Error> {
+    let version = with.version.unwrap_or(args.version);
+    let other = match (&with.digest, &args.input) {
+        (Some(digest), _) => LifeHash::from_digest(digest, version)?,
+        (None, Either::Left(input)) => LifeHash::from_data(input.as_bytes(), version)?,
+        (None, Either::Right(digest)) => LifeHash::from_digest(digest, version)?,
+    };
+    let difference = difference(lifehash, &other);
+    let size = match args.size {
+        Some(size) => size,
+        None => scaled_size(difference.dimensions.width, args.module_size)?,
+    };
+    let mut panels = vec![
+        lifehash.image_fit(size, args.fit, false)?,
+        other.image_fit(size, args.fit, false)?,
+        difference_image(lifehash, &difference, size, args.fit)?,
+    ];
+    if let Some(card) = &args.card {
+        let count = Card {
+            label: Some(format!("{}/{}", difference.count(), difference.modules())),
+        };
+        panels = panels
+            .iter()
+            .zip([(lifehash, card), (&other, card), (lifehash, &count)])
+            .map(|(image, (lifehash, card))| card_image(image, lifehash, card))
+            .collect::<Result<_, Error>>()?;
+    }
+    let sheet = Sheet {
+        columns: Some(panels.len()),
+        ..args.sheet_options
+    };
+    let image = sheet_image(&panels, &sheet)?;
+    write_output(args, |writer| {
+        args.format.encoder(args.png_options).encode(&image, writer)
+    })?;
+    // The summary goes to stderr when stdout has the image.
+    if is_stdout(&args.output_file) {
+        eprintln!("{}", difference.summary());
+    } else {
+        println!("{}", difference.summary());
+    }
+    Ok(())
+}
+
+fn check_contour(args: &RuntimeArgs) -> Result<(), Error> {
+    if args.contour && !matches!(args.format, ImageFormat::Svg | ImageFormat::Pdf) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "contour rendering needs SVG or PDF output",
+        ));
+    }
+    if args.contour && (!args.mask.is_empty() || args.overlay.is_some()) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "masks and overlays apply to raster images, not contours",
+        ));
+    }
+    Ok(())
+}
+
+/// Rejects the options of the color image that a mode would otherwise silently drop. Modes
+/// that `render` the image with [`render_image`] take the options that size and decorate it,
+/// but none of them stamp, trace contours or keep 16 bits.
+fn check_image_options(args: &RuntimeArgs, mode: &str, render: bool) -> Result<(), Error> {
+    let unsupported: Vec<&str> = [
+        (!render && args.size.is_some(), "--size"),
+        (!render && args.smooth.is_some(), "--smooth"),
+        (!render && args.tile.is_some(), "--tile"),
+        (!render && !args.mask.is_empty(), "--mask"),
+        (
+            !render && !args.frame.is_empty(),
+            "--quiet-zone and --border",
+        ),
+        (!render && args.card.is_some(), "--card"),
+        (!render && args.overlay.is_some(), "--overlay"),
+        (args.contour, "--contour"),
+        (args.stamp.is_some(), "--stamp"),
+        (args.bit_depth == 16, "--bit-depth 16"),
+    ]
+    .into_iter()
+    .filter_map(|(set, name)| set.then_some(name))
+    .collect();
+    if !unsupported.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("{mode} doesn't support {}", unsupported.join(", ")),
+        ));
+    }
+    Ok(())
+}
+
+/// Traces the contours with any frame and card, in color grid units.
+fn render_vector(lifehash: &LifeHash, args: &RuntimeArgs) -> Result<VectorImage, Error> {
+    let mut image = contour_image(lifehash, args.contour_levels)?;
+    if !args.frame.is_empty() {
+        image = frame_vector(&image, lifehash, &args.frame);
+    }
+    if let Some(card) = &args.card {
+        image = card_vector(&image, lifehash, card);
+    }
+    Ok(image)
+}
+
+/// Pixels per color grid unit of vector output.
+fn vector_scale(lifehash: &LifeHash, args: &RuntimeArgs) -> f64 {
+    args.size.map_or(args.module_size as f64, |size| {
+        size as f64 / lifehash.color_grid.dimensions.width as f64
+    })
+}
+
+fn encode_vector<W: Write>(
+    image: &VectorImage,
+    scale: f64,
+    args: &RuntimeArgs,
+    writer: W,
+) -> Result<(), Error> {
+    if args.format == ImageFormat::Svg {
+        encode_svg(image, scale, writer)
+    } else {
+        encode_pdf(image, scale, writer)
+    }
+}
+
+/// The size of `cells` modules of `module_size` pixels, an error if it doesn't fit in an image.
+fn scaled_size(cells: usize, module_size: usize) -> Result<usize, Error> {
+    cells
+        .checked_mul(module_size)
+        .filter(|size| *size <= MAX_IMAGE_SIZE)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Invalid Module Size"))
+}
+
+/// The size of the image in pixels, none when it is rendered by module size. Smooth images have
+/// no modules, so they are as large as the modules would be.
+fn rendered_size(lifehash: &LifeHash, args: &RuntimeArgs) -> Result<Option<usize>, Error> {
+    match (args.smooth, args.size) {
+        (Some(_), None) => Ok(Some(scaled_size(
+            lifehash.color_grid.dimensions.width,
+            args.module_size,
+        )?)),
+        (_, size) => Ok(size),
+    }
+}
+
+/// Renders the 8-bit image in the mode selected by the arguments, with any overlay, frame, tiling
+/// and mask applied.
+fn render_image(lifehash: &LifeHash, args: &RuntimeArgs) -> Result<Image, Error> {
+    let size = rendered_size(lifehash, args)?;
+    let mut image = match (args.smooth, size) {
+        (Some(interpolation), Some(size)) => {
+            smooth_image(lifehash, size, interpolation, args.use_alpha)?
+        }
+        (_, Some(size)) => lifehash.image_fit(size, args.fit, args.use_alpha)?,
+        (_, None) => lifehash.image(args.module_size, args.use_alpha)?,
+    };
+    if let Some(overlay) = &args.overlay {
+        let map = opacity_map(lifehash, overlay);
+        let opacity = match (args.smooth, size) {
+            (Some(interpolation), Some(size)) => map.smooth_image(size, interpolation)?,
+            (_, Some(size)) => map.image_fit(size, args.fit)?,
+            (_, None) => map.image(args.module_size)?,
+        };
+        image = apply_opacity(&image, &opacity)?;
+    }
+    post_process(image, lifehash, args, quantize8, u8::MAX)
+}
+
+/// The 16-bit per channel version of [`render_image`].
+fn render_image16(lifehash: &LifeHash, args: &RuntimeArgs) -> Result<Image<u16>, Error> {
+    let size = rendered_size(lifehash, args)?;
+    let mut image = match (args.smooth, size) {
+        (Some(interpolation), Some(size)) => {
+            smooth_image16(lifehash, size, interpolation, args.use_alpha)?
+        }
+        (_, Some(size)) => lifehash.image16_fit(size, args.fit, args.use_alpha)?,
+        (_, None) => lifehash.image16(args.module_size, args.use_alpha)?,
+    };
+    if let Some(overlay) = &args.overlay {
+        let map = opacity_map(lifehash, overlay);
+        let opacity = match (args.smooth, size) {
+            (Some(interpolation), Some(size)) => map.smooth_image16(size, interpolation)?,
+            (_, Some(size)) => map.image16_fit(size, args.fit)?,
+            (_, None) => map.image16(args.module_size)?,
+        };
+        image = apply_opacity16(&image, &opacity)?;
+    }
+    post_process(image, lifehash, args, quantize16, u16::MAX)
+}
+
+fn post_process<T: Copy + Into<f64>>(
+    image: Image<T>,
+    lifehash: &LifeHash,
+    args: &RuntimeArgs,
+    quantize: fn(f64) -> T,
+    opaque: T,
+) -> Result<Image<T>, Error> {
+    let image = if args.frame.is_empty() {
+        image
+    } else {
+        apply_frame(&image, lifehash, &args.frame, quantize, opaque)?
+    };
+    let image = match args.tile {
+        Some(size) => tile(
+            &image,
+            size.width,
+            size.height,
+            args.tile_offset.0,
+            args.tile_offset.1,
         )?,
+        None => image,
+    };
+    let image = if args.mask.is_empty() {
+        image
+    } else {
+        apply_mask(&image, &args.mask, quantize, opaque)?
     };
-    save_image(&image, &args.output_file)
+    match &args.card {
+        Some(card) => apply_card(&image, lifehash, card, quantize, opaque),
+        None => Ok(image),
+    }
 }
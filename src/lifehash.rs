@@ -1,55 +1,295 @@
-use crate::colors::gradient::select_gradient;
+use crate::colors::functions::ColorFunction;
+use crate::colors::gradient::{select_gradient, select_gradient_family, GradientFamily};
 use crate::grids::cell_grid::Cellgrid;
 use crate::grids::change_grid::ChangeGrid;
 use crate::grids::color_grid::ColorGrid;
 use crate::grids::frac_grid::FracGrid;
 use crate::utils::bits::Enumerator;
 use crate::utils::{lerp_from, select_pattern};
-use crate::{Dimensions, Image, Version};
+use crate::{Dimensions, Fit, Image, Pattern, Version};
 use sha2::{Digest, Sha256};
 use std::collections::HashSet;
 use std::io::{Error, ErrorKind};
 
-fn make_image(
+/// The largest width or height of a rendered image, in pixels.
+pub const MAX_IMAGE_SIZE: usize = 16384;
+
+pub(crate) fn quantize8(c: f64) -> u8 {
+    (c.clamp(0.0, 1.0) * 255.0) as u8
+}
+
+pub(crate) fn quantize16(c: f64) -> u16 {
+    (c.clamp(0.0, 1.0) * 65535.0).round() as u16
+}
+
+pub(crate) fn scale_image<T: Copy + Default>(
     width: usize,
     height: usize,
     float_colors: &[f64],
     module_size: usize,
     has_alpha: bool,
-) -> Result<Image, Error> {
-    if module_size == 0 {
+    quantize: fn(f64) -> T,
+    opaque: T,
+) -> Result<Image<T>, Error> {
+    if module_size == 0
+        || width
+            .max(height)
+            .checked_mul(module_size)
+            .is_none_or(|size| size > MAX_IMAGE_SIZE)
+    {
         return Err(Error::new(ErrorKind::InvalidData, "Invalid Module Size"));
     }
     let scaled_width = width * module_size;
     let scaled_height = height * module_size;
     let result_components = if has_alpha { 4 } else { 3 };
     let scaled_capacity = scaled_width * scaled_height * result_components;
-    let mut result_colors = vec![0u8; scaled_capacity];
-    for target_y in 0..scaled_width {
-        for target_x in 0..scaled_height {
+    let mut result_colors = vec![T::default(); scaled_capacity];
+    for target_y in 0..scaled_height {
+        for target_x in 0..scaled_width {
             let source_x = target_x / module_size;
             let source_y = target_y / module_size;
             let source_offset = (source_y * width + source_x) * 3;
             let target_offset = (target_y * scaled_width + target_x) * result_components;
-            result_colors[target_offset] =
-                (float_colors[source_offset].clamp(0.0, 1.0) * 255.0) as u8;
-            result_colors[target_offset + 1] =
-                (float_colors[source_offset + 1].clamp(0.0, 1.0) * 255.0) as u8;
-            result_colors[target_offset + 2] =
-                (float_colors[source_offset + 2].clamp(0.0, 1.0) * 255.0) as u8;
+            result_colors[target_offset] = quantize(float_colors[source_offset]);
+            result_colors[target_offset + 1] = quantize(float_colors[source_offset + 1]);
+            result_colors[target_offset + 2] = quantize(float_colors[source_offset + 2]);
             if has_alpha {
-                result_colors[target_offset + 3] = 255;
+                result_colors[target_offset + 3] = opaque;
             }
         }
     }
     Ok(Image {
         width: scaled_width,
         height: scaled_height,
-        channels: if has_alpha { 4 } else { 3 },
+        channels: result_components,
+        pixels: result_colors,
+    })
+}
+
+fn make_image(
+    width: usize,
+    height: usize,
+    float_colors: &[f64],
+    module_size: usize,
+    has_alpha: bool,
+) -> Result<Image, Error> {
+    scale_image(
+        width,
+        height,
+        float_colors,
+        module_size,
+        has_alpha,
+        quantize8,
+        u8::MAX,
+    )
+}
+
+fn make_image16(
+    width: usize,
+    height: usize,
+    float_colors: &[f64],
+    module_size: usize,
+    has_alpha: bool,
+) -> Result<Image<u16>, Error> {
+    scale_image(
+        width,
+        height,
+        float_colors,
+        module_size,
+        has_alpha,
+        quantize16,
+        u16::MAX,
+    )
+}
+
+/// The cell a target pixel samples, nearest-neighbor so cell edges stay crisp. Enlarging
+/// spreads the remainder pixels evenly over the cells, shrinking skips cells evenly.
+fn nearest_source(target: usize, cells: usize, size: usize) -> usize {
+    target * cells / size
+}
+
+/// Renders any color grid at exactly `size` pixels square, the same way as
+/// [`LifeHash::image_fit`].
+pub(crate) fn color_grid_image(grid: &ColorGrid, size: usize, fit: Fit) -> Result<Image, Error> {
+    resample_image(
+        grid.dimensions.width,
+        grid.dimensions.height,
+        &grid.colors(),
+        size,
+        fit,
+        false,
+        quantize8,
+        u8::MAX,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn resample_image<T: Copy + Default>(
+    width: usize,
+    height: usize,
+    float_colors: &[f64],
+    size: usize,
+    fit: Fit,
+    has_alpha: bool,
+    quantize: fn(f64) -> T,
+    opaque: T,
+) -> Result<Image<T>, Error> {
+    if size == 0 || size > MAX_IMAGE_SIZE {
+        return Err(Error::new(ErrorKind::InvalidData, "Invalid Image Size"));
+    }
+    let module_size = size / width.max(height);
+    if fit == Fit::Letterbox && module_size == 0 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "Image Size is smaller than the grid, it can't be letterboxed",
+        ));
+    }
+    let result_components = if has_alpha { 4 } else { 3 };
+    let mut result_colors = vec![T::default(); size * size * result_components];
+    // Letterboxed images are padded with transparency, or white without alpha.
+    let background = if has_alpha {
+        T::default()
+    } else {
+        quantize(1.0)
+    };
+    let offset_x = (size - width * module_size) / 2;
+    let offset_y = (size - height * module_size) / 2;
+    for target_y in 0..size {
+        // The row of the source that the target row samples, none in the letterbox padding.
+        let source_y = match fit {
+            Fit::Distribute => Some(nearest_source(target_y, height, size)),
+            Fit::Letterbox => letterbox_source(target_y, offset_y, height, module_size),
+        };
+        for target_x in 0..size {
+            let source_x = match fit {
+                Fit::Distribute => Some(nearest_source(target_x, width, size)),
+                Fit::Letterbox => letterbox_source(target_x, offset_x, width, module_size),
+            };
+            let target_offset = (target_y * size + target_x) * result_components;
+            let (Some(source_x), Some(source_y)) = (source_x, source_y) else {
+                result_colors[target_offset..target_offset + result_components].fill(background);
+                continue;
+            };
+            let source_offset = (source_y * width + source_x) * 3;
+            for c in 0..3 {
+                result_colors[target_offset + c] = quantize(float_colors[source_offset + c]);
+            }
+            if has_alpha {
+                result_colors[target_offset + 3] = opaque;
+            }
+        }
+    }
+    Ok(Image {
+        width: size,
+        height: size,
+        channels: result_components,
         pixels: result_colors,
     })
 }
 
+/// The cell a letterboxed target pixel samples, or none if it is in the padding before or after
+/// the `cells` modules that start at `offset`.
+fn letterbox_source(
+    target: usize,
+    offset: usize,
+    cells: usize,
+    module_size: usize,
+) -> Option<usize> {
+    if target < offset || target >= offset + cells * module_size {
+        return None;
+    }
+    Some((target - offset) / module_size)
+}
+
+/// The result of running the LifeHash simulation for a digest, before it is rendered to pixels.
+pub struct LifeHash {
+    pub digest: Vec<u8>,
+    pub version: Version,
+    pub pattern: Pattern,
+    pub gradient: ColorFunction<'static>,
+    pub history: Vec<Vec<u8>>,
+    pub frac_grid: FracGrid,
+    pub color_grid: ColorGrid,
+}
+impl LifeHash {
+    pub fn from_data(data: &[u8], version: Version) -> Result<Self, Error> {
+        let sha256 = Sha256::digest(data);
+        Self::from_digest(sha256.as_slice(), version)
+    }
+    pub fn from_digest(digest: &[u8], version: Version) -> Result<Self, Error> {
+        simulate(digest, version)
+    }
+    /// The kind of gradient the lifehash was colored with.
+    pub fn gradient_family(&self) -> Result<GradientFamily, Error> {
+        select_gradient_family(
+            &mut color_entropy(&self.digest, self.version)?,
+            self.version,
+        )
+    }
+    /// The number of Life generations that were run before a pattern repeated or the limit was reached.
+    pub fn generations(&self) -> usize {
+        self.history.len()
+    }
+    /// The cells of a generation, starting with the seed at 0.
+    pub fn generation(&self, index: usize) -> Option<Cellgrid> {
+        let data = self.history.get(index)?;
+        let mut grid = Cellgrid::new(self.frac_grid.dimensions);
+        grid.set_data(data);
+        Some(grid)
+    }
+    /// Renders the 8-bit per channel image, each cell is scaled to `module_size` pixels.
+    pub fn image(&self, module_size: usize, has_alpha: bool) -> Result<Image, Error> {
+        make_image(
+            self.color_grid.dimensions.width,
+            self.color_grid.dimensions.height,
+            &self.color_grid.colors(),
+            module_size,
+            has_alpha,
+        )
+    }
+    /// Renders the image at exactly `size` pixels square, for icons that need a fixed size.
+    /// Each pixel takes the color of the nearest cell, so cell edges stay crisp.
+    pub fn image_sized(&self, size: usize, has_alpha: bool) -> Result<Image, Error> {
+        self.image_fit(size, Fit::Distribute, has_alpha)
+    }
+    /// Renders the image at exactly `size` pixels square, fitting the cells as selected by `fit`.
+    pub fn image_fit(&self, size: usize, fit: Fit, has_alpha: bool) -> Result<Image, Error> {
+        resample_image(
+            self.color_grid.dimensions.width,
+            self.color_grid.dimensions.height,
+            &self.color_grid.colors(),
+            size,
+            fit,
+            has_alpha,
+            quantize8,
+            u8::MAX,
+        )
+    }
+    /// The 16-bit per channel version of [`LifeHash::image_fit`].
+    pub fn image16_fit(&self, size: usize, fit: Fit, has_alpha: bool) -> Result<Image<u16>, Error> {
+        resample_image(
+            self.color_grid.dimensions.width,
+            self.color_grid.dimensions.height,
+            &self.color_grid.colors(),
+            size,
+            fit,
+            has_alpha,
+            quantize16,
+            u16::MAX,
+        )
+    }
+    /// Renders the 16-bit per channel image from the floating point colors, keeping the full gradient precision.
+    pub fn image16(&self, module_size: usize, has_alpha: bool) -> Result<Image<u16>, Error> {
+        make_image16(
+            self.color_grid.dimensions.width,
+            self.color_grid.dimensions.height,
+            &self.color_grid.colors(),
+            module_size,
+            has_alpha,
+        )
+    }
+}
+
 pub fn from_data(
     data: &[u8],
     version: Version,
@@ -66,6 +306,30 @@ pub fn from_digest(
     module_size: usize,
     has_alpha: bool,
 ) -> Result<(Image, Vec<u8>), Error> {
+    LifeHash::from_digest(digest, version)?
+        .image(module_size, has_alpha)
+        .map(|i| (i, digest.to_vec()))
+}
+
+/// The digest bits that select the gradient and the pattern.
+fn color_entropy(digest: &[u8], version: Version) -> Result<Enumerator<'_>, Error> {
+    let mut entropy = Enumerator::new(digest);
+    match version {
+        Version::Detailed => {
+            // Throw away a bit of entropy to ensure we generate different colors and patterns from Version::Version1
+            let _ = entropy.next_bit()?;
+        }
+        Version::Version2 => {
+            // Throw away two bits of entropy to ensure we generate different colors and patterns from Version::Version1  or Version::Detailed.
+            let _ = entropy.next_bit()?;
+            let _ = entropy.next_bit()?;
+        }
+        _ => {}
+    }
+    Ok(entropy)
+}
+
+fn simulate(digest: &[u8], version: Version) -> Result<LifeHash, Error> {
     let (length, max_generations) = match version {
         Version::Version1 | Version::Version2 => (16, 150),
         Version::Detailed | Version::Fiducial | Version::GrayscaleFiducial => (32, 300),
@@ -147,28 +411,17 @@ pub fn from_digest(
             frac_grid.set_value(value, point);
         }
     }
-    let mut entropy = Enumerator::new(digest);
-    match version {
-        Version::Detailed => {
-            // Throw away a bit of entropy to ensure we generate different colors and patterns from Version::Version1
-            let _ = entropy.next_bit()?;
-        }
-        Version::Version2 => {
-            // Throw away two bits of entropy to ensure we generate different colors and patterns from Version::Version1  or Version::Detailed.
-            let _ = entropy.next_bit()?;
-            let _ = entropy.next_bit()?;
-        }
-        _ => {}
-    }
+    let mut entropy = color_entropy(digest, version)?;
     let gradient = select_gradient(&mut entropy, version)?;
     let pattern = select_pattern(&mut entropy, version)?;
     let color_grid = ColorGrid::create(&frac_grid, &gradient, pattern);
-    make_image(
-        color_grid.dimensions.width,
-        color_grid.dimensions.height,
-        &color_grid.colors(),
-        module_size,
-        has_alpha,
-    )
-    .map(|i| (i, digest.to_vec()))
+    Ok(LifeHash {
+        digest: digest.to_vec(),
+        version,
+        pattern,
+        gradient,
+        history,
+        frac_grid,
+        color_grid,
+    })
 }
@@ -0,0 +1,208 @@
+use crate::colors::rgb::Color;
+use crate::lifehash::LifeHash;
+use crate::Point;
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+
+/// Sizes of a relief in millimeters.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ReliefOptions {
+    /// Width and depth of a module.
+    pub module_size: f64,
+    /// Thickness of the solid base under the modules.
+    pub base: f64,
+    /// Height of a module with a frac value of 1 above the base.
+    pub height: f64,
+    /// Color the vertices from the color grid, for formats that store vertex colors.
+    pub colors: bool,
+}
+impl Default for ReliefOptions {
+    fn default() -> Self {
+        Self {
+            module_size: 1.0,
+            base: 2.0,
+            height: 3.0,
+            colors: false,
+        }
+    }
+}
+
+/// A triangle mesh. Triangles wind counter-clockwise seen from outside of the solid.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Mesh {
+    pub vertices: Vec<[f64; 3]>,
+    /// The color of each vertex, if the mesh is colored.
+    pub colors: Option<Vec<Color>>,
+    pub triangles: Vec<[usize; 3]>,
+    /// Index of each vertex by its position and color bits, so faces share their vertices.
+    index: HashMap<[u64; 6], usize>,
+}
+impl Mesh {
+    pub fn new(colored: bool) -> Self {
+        Self {
+            colors: colored.then(Vec::new),
+            ..Self::default()
+        }
+    }
+    /// The index of the vertex, added if the mesh doesn't have it yet. The color is only kept
+    /// if the mesh is colored.
+    pub fn vertex(&mut self, position: [f64; 3], color: Color) -> usize {
+        let color = if self.colors.is_some() {
+            [color.r, color.g, color.b]
+        } else {
+            [0.0; 3]
+        };
+        let [x, y, z] = position;
+        let [r, g, b] = color;
+        let key = [x, y, z, r, g, b].map(f64::to_bits);
+        if let Some(index) = self.index.get(&key) {
+            return *index;
+        }
+        self.vertices.push(position);
+        if let Some(colors) = &mut self.colors {
+            colors.push(Color::new(r, g, b));
+        }
+        self.index.insert(key, self.vertices.len() - 1);
+        self.vertices.len() - 1
+    }
+    /// The unit normal of the triangle.
+    pub fn normal(&self, triangle: [usize; 3]) -> [f64; 3] {
+        let [a, b, c] = triangle.map(|i| self.vertices[i]);
+        let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+        let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+        let n = [
+            u[1] * v[2] - u[2] * v[1],
+            u[2] * v[0] - u[0] * v[2],
+            u[0] * v[1] - u[1] * v[0],
+        ];
+        let length = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+        if length == 0.0 {
+            return [0.0; 3];
+        }
+        n.map(|c| c / length)
+    }
+    /// Adds a vertical wall from `start` to `end` seen from outside. Each side lists the heights
+    /// of its vertices from bottom to top, both starting and ending at the same heights. The
+    /// sides are zipped together into triangles, so walls meeting along an edge at different
+    /// heights share their vertices there.
+    fn add_wall(
+        &mut self,
+        start: (f64, f64),
+        end: (f64, f64),
+        start_heights: &[f64],
+        end_heights: &[f64],
+        color: Color,
+    ) {
+        let start: Vec<usize> = start_heights
+            .iter()
+            .map(|z| self.vertex([start.0, start.1, *z], color))
+            .collect();
+        let end: Vec<usize> = end_heights
+            .iter()
+            .map(|z| self.vertex([end.0, end.1, *z], color))
+            .collect();
+        let (mut i, mut j) = (0, 0);
+        while i + 1 < start.len() || j + 1 < end.len() {
+            let advance_end = i + 1 == start.len()
+                || (j + 1 < end.len() && end_heights[j + 1] <= start_heights[i + 1]);
+            if advance_end {
+                self.triangles.push([start[i], end[j], end[j + 1]]);
+                j += 1;
+            } else {
+                self.triangles.push([start[i], end[j], start[i + 1]]);
+                i += 1;
+            }
+        }
+    }
+    /// Adds a horizontal rectangle, facing up or down.
+    fn add_rect(&mut self, x: (f64, f64), y: (f64, f64), z: f64, up: bool, color: Color) {
+        let mut corners = [(x.0, y.0), (x.1, y.0), (x.1, y.1), (x.0, y.1)];
+        if !up {
+            corners.reverse();
+        }
+        let [a, b, c, d] = corners.map(|(x, y)| self.vertex([x, y, z], color));
+        self.triangles.push([a, b, c]);
+        self.triangles.push([a, c, d]);
+    }
+}
+
+/// Builds a closed relief of the pattern-expanded frac grid, with each module a column as high
+/// as its frac value on top of a solid base. The image is seen from above with its top row at
+/// the far end of the y axis, and the mesh starts at the origin. Like in any voxel model, columns
+/// that only touch diagonally share the edge between them.
+pub fn relief_mesh(lifehash: &LifeHash, options: &ReliefOptions) -> Result<Mesh, Error> {
+    let grid = lifehash.frac_grid.expand(lifehash.pattern);
+    let (width, depth) = (grid.dimensions.width, grid.dimensions.height);
+    // Finite sizes can still add up to an infinite extent.
+    let extent = [
+        options.module_size * width.max(depth) as f64,
+        options.base + options.height,
+    ];
+    let valid = options.module_size > 0.0 && options.base > 0.0 && options.height >= 0.0;
+    if !valid || !extent.iter().all(|size| size.is_finite()) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "The relief needs a finite positive module size and base, and a finite height",
+        ));
+    }
+    let size = options.module_size;
+    // Column heights with a ring of zero around the grid.
+    let height = |x: isize, y: isize| {
+        if x < 0 || y < 0 || x >= width as isize || y >= depth as isize {
+            return 0.0;
+        }
+        let frac = *grid.get_value(Point {
+            x: x as usize,
+            y: y as usize,
+        });
+        options.base + frac * options.height
+    };
+    // The heights where walls may meet on the vertical line at grid corner x, y.
+    let corner_heights = |x: usize, y: usize, low: f64, high: f64| {
+        let (x, y) = (x as isize, y as isize);
+        let mut heights: Vec<f64> = [(x - 1, y - 1), (x, y - 1), (x - 1, y), (x, y)]
+            .iter()
+            .map(|(x, y)| height(*x, *y))
+            .chain([low, high])
+            .filter(|z| (low..=high).contains(z))
+            .collect();
+        heights.sort_by(f64::total_cmp);
+        heights.dedup();
+        heights
+    };
+    // Grid corner x, y in model coordinates, with the rows running towards the origin.
+    let position = |x: usize, y: usize| (x as f64 * size, (depth - y) as f64 * size);
+    let mut mesh = Mesh::new(options.colors);
+    for y in 0..depth {
+        for x in 0..width {
+            let color = *lifehash.color_grid.get_value(Point { x, y });
+            let top = height(x as isize, y as isize);
+            let (left, far) = position(x, y);
+            let (right, near) = position(x + 1, y + 1);
+            mesh.add_rect((left, right), (near, far), top, true, color);
+            mesh.add_rect((left, right), (near, far), 0.0, false, color);
+            // Each wall belongs to the higher of the two columns it separates, from the corners
+            // on its left to the ones on its right seen from outside.
+            let sides = [
+                ((x + 1, y + 1), (x + 1, y), (1, 0)),
+                ((x + 1, y), (x, y), (0, -1)),
+                ((x, y), (x, y + 1), (-1, 0)),
+                ((x, y + 1), (x + 1, y + 1), (0, 1)),
+            ];
+            for (start, end, (dx, dy)) in sides {
+                let neighbor = height(x as isize + dx, y as isize + dy);
+                if neighbor >= top {
+                    continue;
+                }
+                mesh.add_wall(
+                    position(start.0, start.1),
+                    position(end.0, end.1),
+                    &corner_heights(start.0, start.1, neighbor, top),
+                    &corner_heights(end.0, end.1, neighbor, top),
+                    color,
+                );
+            }
+        }
+    }
+    Ok(mesh)
+}
@@ -0,0 +1,202 @@
+use crate::colors::functions::ColorFunction;
+use crate::colors::rgb::Color;
+use crate::colors::{BLACK, WHITE};
+use crate::lifehash::{quantize16, quantize8, LifeHash, MAX_IMAGE_SIZE};
+use crate::render::font::{text_bitmap, text_width, GLYPH_HEIGHT};
+use crate::render::frame::solid_pixel;
+use crate::vector::{Shape, VectorImage};
+use crate::Image;
+use std::io::{Error, ErrorKind};
+
+/// Space around the caption text in dots.
+const PADDING: usize = 2;
+/// The caption text is at most this many times shorter than the image.
+const HEIGHT_RATIO: usize = 6;
+/// Minimum luminance difference between the caption and its background, as a fraction of the
+/// difference between black and white.
+const MIN_CONTRAST: f64 = 0.45;
+
+/// The image above a caption strip, rendered with the built-in bitmap font.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Card {
+    /// Caption text, the digest [`fingerprint`] if none.
+    pub label: Option<String>,
+}
+impl Card {
+    pub fn caption(&self, lifehash: &LifeHash) -> String {
+        self.label
+            .clone()
+            .unwrap_or_else(|| fingerprint(&lifehash.digest))
+    }
+}
+
+/// The first and last four bytes of the digest in hex, enough to tell lifehashes apart when
+/// comparing them side by side.
+pub fn fingerprint(digest: &[u8]) -> String {
+    if digest.len() <= 8 {
+        hex::encode(digest)
+    } else {
+        format!(
+            "{}..{}",
+            hex::encode(&digest[..4]),
+            hex::encode(&digest[digest.len() - 4..])
+        )
+    }
+}
+
+/// The strip background and the text color of each column of dots. The key colors of the
+/// gradient are spread over the text from left to right in equal bands, on the gradient's
+/// contrast color, each pushed towards the opposite color wherever it would be hard to read.
+pub fn caption_colors(gradient: &ColorFunction, columns: usize) -> (Color, Vec<Color>) {
+    let background = gradient.contrast_color();
+    let opposite = if background == WHITE { BLACK } else { WHITE };
+    let min_difference = MIN_CONTRAST * WHITE.luminance();
+    let keys = gradient.key_colors();
+    let colors = (0..columns)
+        .map(|column| {
+            let color = keys
+                .get(column * keys.len() / columns)
+                .copied()
+                .unwrap_or(opposite);
+            (0..=10)
+                .map(|step| color.lerp_to(&opposite, step as f64 / 10.0))
+                .find(|c| (c.luminance() - background.luminance()).abs() >= min_difference)
+                .unwrap_or(opposite)
+        })
+        .collect();
+    (background, colors)
+}
+
+/// Size of a dot of the font for an image, as large as fits the image width while keeping the
+/// text short next to the image.
+fn dot_size(width: f64, height: f64, columns: usize) -> f64 {
+    let by_width = width / (columns + 2 * PADDING) as f64;
+    let by_height = height / (GLYPH_HEIGHT * HEIGHT_RATIO) as f64;
+    by_width.min(by_height)
+}
+
+/// Puts the image on a card above the caption. The card is widened if the caption doesn't fit
+/// at one pixel per dot.
+pub fn card_image(image: &Image, lifehash: &LifeHash, card: &Card) -> Result<Image, Error> {
+    apply_card(image, lifehash, card, quantize8, u8::MAX)
+}
+
+/// The 16-bit per channel version of [`card_image`].
+pub fn card_image16(
+    image: &Image<u16>,
+    lifehash: &LifeHash,
+    card: &Card,
+) -> Result<Image<u16>, Error> {
+    apply_card(image, lifehash, card, quantize16, u16::MAX)
+}
+
+pub(crate) fn apply_card<T: Copy>(
+    image: &Image<T>,
+    lifehash: &LifeHash,
+    card: &Card,
+    quantize: fn(f64) -> T,
+    opaque: T,
+) -> Result<Image<T>, Error> {
+    let caption = card.caption(lifehash);
+    let columns = text_width(&caption);
+    let bitmap = text_bitmap(&caption);
+    let dot = (dot_size(image.width as f64, image.height as f64, columns) as usize).max(1);
+    let width = image.width.max((columns + 2 * PADDING) * dot);
+    let height = image.height + (GLYPH_HEIGHT + 2 * PADDING) * dot;
+    if width > MAX_IMAGE_SIZE || height > MAX_IMAGE_SIZE {
+        return Err(Error::new(ErrorKind::InvalidData, "Invalid Image Size"));
+    }
+    let (background, colors) = caption_colors(&lifehash.gradient, columns);
+    let background_pixel = solid_pixel(background, image.channels, quantize, opaque);
+    let text_pixels: Vec<Vec<T>> = colors
+        .into_iter()
+        .map(|color| solid_pixel(color, image.channels, quantize, opaque))
+        .collect();
+    let image_x = (width - image.width) / 2;
+    let text_x = (width - columns * dot) / 2;
+    let text_y = image.height + PADDING * dot;
+    let mut pixels = Vec::with_capacity(width * height * image.channels);
+    for y in 0..height {
+        for x in 0..width {
+            if y < image.height && (image_x..image_x + image.width).contains(&x) {
+                let offset = (y * image.width + x - image_x) * image.channels;
+                pixels.extend_from_slice(&image.pixels[offset..][..image.channels]);
+                continue;
+            }
+            let column = x.wrapping_sub(text_x) / dot;
+            let row = y.wrapping_sub(text_y) / dot;
+            if row < GLYPH_HEIGHT && column < columns && bitmap[row][column] {
+                pixels.extend_from_slice(&text_pixels[column]);
+            } else {
+                pixels.extend_from_slice(&background_pixel);
+            }
+        }
+    }
+    Ok(Image {
+        width,
+        height,
+        channels: image.channels,
+        pixels,
+    })
+}
+
+/// Puts a vector image in color grid units on a card above the caption, with the text drawn as
+/// rectangles so it doesn't depend on any fonts.
+pub fn card_vector(image: &VectorImage, lifehash: &LifeHash, card: &Card) -> VectorImage {
+    let caption = card.caption(lifehash);
+    let columns = text_width(&caption);
+    let bitmap = text_bitmap(&caption);
+    let dot = dot_size(image.width, image.height, columns);
+    let width = image.width.max((columns + 2 * PADDING) as f64 * dot);
+    let height = image.height + (GLYPH_HEIGHT + 2 * PADDING) as f64 * dot;
+    let image_x = (width - image.width) / 2.0;
+    let text_x = (width - columns as f64 * dot) / 2.0;
+    let text_y = image.height + PADDING as f64 * dot;
+    let (background, colors) = caption_colors(&lifehash.gradient, columns);
+    let mut shapes: Vec<Shape> = image
+        .shapes
+        .iter()
+        .map(|shape| shape.translated(image_x, 0.0))
+        .collect();
+    // The background has a hole for the image and is drawn on top, so it also covers anything
+    // drawn past the edges of the image.
+    let mut backdrop = Shape::new(background);
+    backdrop.add_rect(0.0, 0.0, width, height);
+    backdrop.add_rect(image_x, 0.0, image.width, image.height);
+    shapes.push(backdrop);
+    let mut text: Vec<Shape> = vec![];
+    for (column, color) in colors.into_iter().enumerate() {
+        let index = match text.iter().position(|shape| shape.color == color) {
+            Some(index) => index,
+            None => {
+                text.push(Shape::new(color));
+                text.len() - 1
+            }
+        };
+        // Vertical runs of set dots in the column.
+        let mut row = 0;
+        while row < GLYPH_HEIGHT {
+            if !bitmap[row][column] {
+                row += 1;
+                continue;
+            }
+            let start = row;
+            while row < GLYPH_HEIGHT && bitmap[row][column] {
+                row += 1;
+            }
+            text[index].add_rect(
+                text_x + column as f64 * dot,
+                text_y + start as f64 * dot,
+                dot,
+                (row - start) as f64 * dot,
+            );
+        }
+    }
+    shapes.extend(text.into_iter().filter(|shape| !shape.paths.is_empty()));
+    VectorImage {
+        width,
+        height,
+        crisp_edges: image.crisp_edges,
+        shapes,
+    }
+}
@@ -0,0 +1,134 @@
+use crate::colors::rgb::Color;
+use crate::colors::{BLUE, RED, WHITE};
+use crate::grids::color_grid::ColorGrid;
+use crate::lifehash::{color_grid_image, quantize8, LifeHash};
+use crate::{Dimensions, Fit, Image, Point, Version};
+use std::io::Error;
+
+/// Frac values closer than this are the same.
+const FRAC_TOLERANCE: f64 = 1e-9;
+/// How far unchanged modules are faded to white in the difference panel.
+const FADE: f64 = 0.6;
+
+/// What a lifehash is compared against, the same digest or version where not given.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct CompareWith {
+    pub digest: Option<Vec<u8>>,
+    pub version: Option<Version>,
+}
+
+/// The modules where two lifehashes differ, at the resolution of the larger color grid. The
+/// smaller grid is sampled nearest-neighbor, so versions of different detail can be compared.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difference {
+    pub dimensions: Dimensions,
+    /// True for each module, row by row, where the frac values differ.
+    pub frac: Vec<bool>,
+    /// True for each module where the colors differ once quantized to 8 bits.
+    pub color: Vec<bool>,
+}
+impl Difference {
+    pub fn modules(&self) -> usize {
+        self.dimensions.width * self.dimensions.height
+    }
+    pub fn frac_count(&self) -> usize {
+        self.frac.iter().filter(|differs| **differs).count()
+    }
+    pub fn color_count(&self) -> usize {
+        self.color.iter().filter(|differs| **differs).count()
+    }
+    /// The number of modules where the frac value or the color differs.
+    pub fn count(&self) -> usize {
+        self.frac
+            .iter()
+            .zip(&self.color)
+            .filter(|(frac, color)| **frac || **color)
+            .count()
+    }
+    pub fn summary(&self) -> String {
+        let modules = self.modules();
+        let percent = |count: usize| 100.0 * count as f64 / modules.max(1) as f64;
+        format!(
+            "{} of {} modules differ ({:.1}%): frac values in {} ({:.1}%), colors in {} ({:.1}%)",
+            self.count(),
+            modules,
+            percent(self.count()),
+            self.frac_count(),
+            percent(self.frac_count()),
+            self.color_count(),
+            percent(self.color_count())
+        )
+    }
+}
+
+/// Compares the frac and color grids of two lifehashes module by module.
+pub fn difference(a: &LifeHash, b: &LifeHash) -> Difference {
+    let dimensions = Dimensions {
+        width: a
+            .color_grid
+            .dimensions
+            .width
+            .max(b.color_grid.dimensions.width),
+        height: a
+            .color_grid
+            .dimensions
+            .height
+            .max(b.color_grid.dimensions.height),
+    };
+    let fracs = [a, b].map(|lifehash| lifehash.frac_grid.expand(lifehash.pattern));
+    let sample = |grid: Dimensions, x: usize, y: usize| Point {
+        x: x * grid.width / dimensions.width,
+        y: y * grid.height / dimensions.height,
+    };
+    let mut frac = Vec::with_capacity(dimensions.width * dimensions.height);
+    let mut color = Vec::with_capacity(dimensions.width * dimensions.height);
+    for y in 0..dimensions.height {
+        for x in 0..dimensions.width {
+            let [frac_a, frac_b] =
+                [0, 1].map(|i| *fracs[i].get_value(sample(fracs[i].dimensions, x, y)));
+            frac.push((frac_a - frac_b).abs() > FRAC_TOLERANCE);
+            let [color_a, color_b] = [a, b].map(|lifehash| {
+                let grid = &lifehash.color_grid;
+                let c = grid.get_value(sample(grid.dimensions, x, y));
+                [c.r, c.g, c.b].map(quantize8)
+            });
+            color.push(color_a != color_b);
+        }
+    }
+    Difference {
+        dimensions,
+        frac,
+        color,
+    }
+}
+
+/// Renders the difference at `size` pixels square over a faded gray copy of `base`. Modules with
+/// different frac values are red and modules where only the color differs are blue.
+pub fn difference_image(
+    base: &LifeHash,
+    difference: &Difference,
+    size: usize,
+    fit: Fit,
+) -> Result<Image, Error> {
+    let mut grid = ColorGrid::new(difference.dimensions);
+    let source = &base.color_grid;
+    for y in 0..difference.dimensions.height {
+        for x in 0..difference.dimensions.width {
+            let index = y * difference.dimensions.width + x;
+            let color = if difference.frac[index] {
+                RED
+            } else if difference.color[index] {
+                BLUE
+            } else {
+                let c = source.get_value(Point {
+                    x: x * source.dimensions.width / difference.dimensions.width,
+                    y: y * source.dimensions.height / difference.dimensions.height,
+                });
+                let gray = (c.r + c.g + c.b) / 3.0;
+                Color::new(gray, gray, gray).lerp_to(&WHITE, FADE)
+            };
+            grid.set_value(color, Point { x, y });
+        }
+    }
+    color_grid_image(&grid, size, fit)
+}
@@ -0,0 +1,176 @@
+use crate::lifehash::LifeHash;
+use crate::vector::{Shape, VectorImage};
+use crate::Point;
+use std::collections::BTreeMap;
+use std::io::{Error, ErrorKind};
+
+/// Lattice value outside of the image, below every level so all contours are closed.
+const OUTSIDE: f64 = -1.0;
+
+/// Traces the lifehash as smooth filled contours of the pattern-expanded frac grid. Without
+/// `levels` there is one level for each distinct frac value, otherwise the values are split into
+/// that many evenly spaced levels. Each level is a shape colored from the gradient and stacked on
+/// the levels below it, with coordinates in color grid cells.
+pub fn contour_image(lifehash: &LifeHash, levels: Option<usize>) -> Result<VectorImage, Error> {
+    let grid = lifehash.frac_grid.expand(lifehash.pattern);
+    let size = grid.dimensions.width;
+    // Each level is the threshold it starts at and the frac value its color comes from.
+    let (base, thresholds): (f64, Vec<(f64, f64)>) = match levels {
+        None => {
+            let mut values = grid.storage.clone();
+            values.sort_by(f64::total_cmp);
+            values.dedup();
+            let thresholds = values
+                .windows(2)
+                .map(|pair| ((pair[0] + pair[1]) / 2.0, pair[1]))
+                .collect();
+            (values[0], thresholds)
+        }
+        Some(levels) if levels >= 2 => {
+            let step = 1.0 / (levels - 1) as f64;
+            let thresholds = (1..levels)
+                .map(|level| ((level as f64 - 0.5) * step, level as f64 * step))
+                .collect();
+            (0.0, thresholds)
+        }
+        Some(_) => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "At least 2 contour levels are required",
+            ))
+        }
+    };
+
+    // Samples sit at the cell centers, with one wrapped row around the grid so the contours run
+    // up to the edges, and an outside row that closes them beyond the clip.
+    let lattice_size = size + 4;
+    let mut lattice = vec![OUTSIDE; lattice_size * lattice_size];
+    for y in 1..lattice_size - 1 {
+        for x in 1..lattice_size - 1 {
+            let point = Point {
+                x: (x + 2 * size - 2) % size,
+                y: (y + 2 * size - 2) % size,
+            };
+            lattice[y * lattice_size + x] = *grid.get_value(point);
+        }
+    }
+
+    let mut background = Shape::new(lifehash.gradient.apply(base));
+    background.add_rect(0.0, 0.0, size as f64, size as f64);
+    let mut shapes = vec![background];
+    for (threshold, value) in thresholds {
+        let paths = trace(&lattice, lattice_size, threshold);
+        if !paths.is_empty() {
+            shapes.push(Shape {
+                color: lifehash.gradient.apply(value),
+                opacity: 1.0,
+                paths,
+            });
+        }
+    }
+    Ok(VectorImage {
+        width: size as f64,
+        height: size as f64,
+        crisp_edges: false,
+        shapes,
+    })
+}
+
+/// Marching squares over the lattice, returns the closed outlines of the area at or above the
+/// threshold. Outlines run clockwise around the area and counterclockwise around its holes.
+fn trace(lattice: &[f64], size: usize, threshold: f64) -> Vec<Vec<(f64, f64)>> {
+    let value = |x: usize, y: usize| lattice[y * size + x];
+    // Edges are numbered by their first corner, even for horizontal and odd for vertical.
+    let horizontal = |x: usize, y: usize| (y * size + x) * 2;
+    let vertical = |x: usize, y: usize| (y * size + x) * 2 + 1;
+    // A contour crosses each edge at most once, so segments are keyed by the edge they start on.
+    let mut segments = BTreeMap::new();
+    for y in 0..size - 1 {
+        for x in 0..size - 1 {
+            // Corners and edges clockwise from the top left, edge i runs from corner i to i + 1.
+            let corners = [
+                value(x, y),
+                value(x + 1, y),
+                value(x + 1, y + 1),
+                value(x, y + 1),
+            ];
+            let edges = [
+                horizontal(x, y),
+                vertical(x + 1, y),
+                horizontal(x, y + 1),
+                vertical(x, y),
+            ];
+            let inside = corners.map(|c| c >= threshold);
+            let exits: Vec<usize> = (0..4)
+                .filter(|&i| inside[i] && !inside[(i + 1) % 4])
+                .collect();
+            match exits.as_slice() {
+                [] => {}
+                [exit] => {
+                    let entry = (0..4)
+                        .find(|&i| !inside[i] && inside[(i + 1) % 4])
+                        .expect("a contour leaving a cell also enters it");
+                    segments.insert(edges[*exit], edges[entry]);
+                }
+                _ => {
+                    // A saddle, the center decides whether the inside corners are connected.
+                    let connected = corners.iter().sum::<f64>() / 4.0 >= threshold;
+                    for exit in exits {
+                        let entry = (exit + if connected { 1 } else { 3 }) % 4;
+                        segments.insert(edges[exit], edges[entry]);
+                    }
+                }
+            }
+        }
+    }
+
+    let crossing = |edge: usize| {
+        let x = edge / 2 % size;
+        let y = edge / 2 / size;
+        let (x1, y1) = if edge % 2 == 0 {
+            (x + 1, y)
+        } else {
+            (x, y + 1)
+        };
+        let (a, b) = (value(x, y), value(x1, y1));
+        // Crossings into the outside row are clipped anyway, keeping them on the lattice point
+        // lets them simplify away.
+        let t = match (a == OUTSIDE, b == OUTSIDE) {
+            (true, _) => 0.0,
+            (_, true) => 1.0,
+            _ => (threshold - a) / (b - a),
+        };
+        // Lattice points are a cell and a half outside of the grid.
+        (
+            x as f64 + (x1 - x) as f64 * t - 1.5,
+            y as f64 + (y1 - y) as f64 * t - 1.5,
+        )
+    };
+    let mut paths = vec![];
+    while let Some((start, mut next)) = segments.pop_first() {
+        let mut path = vec![crossing(start)];
+        while next != start {
+            path.push(crossing(next));
+            match segments.remove(&next) {
+                Some(edge) => next = edge,
+                None => break,
+            }
+        }
+        paths.push(simplify(path));
+    }
+    paths
+}
+
+/// Drops points that lie on a straight line between their neighbors.
+fn simplify(path: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    let count = path.len();
+    (0..count)
+        .filter(|&i| {
+            let (ax, ay) = path[(i + count - 1) % count];
+            let (bx, by) = path[i];
+            let (cx, cy) = path[(i + 1) % count];
+            ((bx - ax) * (cy - by) - (by - ay) * (cx - bx)).abs() > 1e-9
+        })
+        .map(|i| path[i])
+        .collect()
+}
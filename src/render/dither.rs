@@ -0,0 +1,161 @@
+use crate::colors::rgb::Color;
+use crate::colors::WHITE;
+use crate::encoders::rgba;
+use crate::grids::color_grid::ColorGrid;
+use crate::lifehash::{LifeHash, MAX_IMAGE_SIZE};
+use crate::Image;
+use clap::ValueEnum;
+use std::io::{Error, ErrorKind};
+
+/// How the shades of a lifehash become black and white pixels.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum Dither {
+    /// Error diffusion to four neighbors, the closest to the shades.
+    FloydSteinberg,
+    /// A 4x4 Bayer threshold matrix, a regular pattern without streaks.
+    Bayer,
+    /// Error diffusion that drops a quarter of the error, for more contrast.
+    Atkinson,
+}
+
+/// Thresholds of the ordered dither, in sixteenths.
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+/// Neighbors that share the error of a pixel and their shares, as x and y offsets.
+const FLOYD_STEINBERG: [(isize, usize, f64); 4] = [
+    (1, 0, 7.0 / 16.0),
+    (-1, 1, 3.0 / 16.0),
+    (0, 1, 5.0 / 16.0),
+    (1, 1, 1.0 / 16.0),
+];
+const ATKINSON: [(isize, usize, f64); 6] = [
+    (1, 0, 1.0 / 8.0),
+    (2, 0, 1.0 / 8.0),
+    (-1, 1, 1.0 / 8.0),
+    (0, 1, 1.0 / 8.0),
+    (1, 1, 1.0 / 8.0),
+    (0, 2, 1.0 / 8.0),
+];
+
+/// A black and white image, true where a pixel is black.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonoImage {
+    pub width: usize,
+    pub height: usize,
+    pub black: Vec<bool>,
+}
+impl MonoImage {
+    /// Black where the luminance of the image is below half, with alpha blended over white.
+    pub fn threshold(image: &Image) -> Self {
+        let black = image
+            .pixels
+            .chunks_exact(image.channels)
+            .take(image.width * image.height)
+            .map(|pixel| {
+                let [r, g, b, a] = rgba(pixel, u8::MAX).map(|c| c as f64 / 255.0);
+                let color = Color::new(r, g, b).lerp_to(&WHITE, 1.0 - a);
+                color.relative_luminance() < 0.5
+            })
+            .collect();
+        Self {
+            width: image.width,
+            height: image.height,
+            black,
+        }
+    }
+    /// The pixels eight to a byte, the leftmost in the most significant bit and 1 for black. Each
+    /// row starts on a byte boundary, as in PBM files and most display drivers.
+    pub fn packed_rows(&self) -> Vec<u8> {
+        let row_bytes = self.width.div_ceil(8);
+        let mut packed = vec![0u8; row_bytes * self.height];
+        for (i, black) in self.black.iter().enumerate() {
+            if *black {
+                let (x, y) = (i % self.width, i / self.width);
+                packed[y * row_bytes + x / 8] |= 0x80 >> (x % 8);
+            }
+        }
+        packed
+    }
+    /// Each pixel as a square of `module_size` pixels.
+    pub fn scaled(&self, module_size: usize) -> Result<Self, Error> {
+        if module_size == 0
+            || self
+                .width
+                .max(self.height)
+                .checked_mul(module_size)
+                .is_none_or(|size| size > MAX_IMAGE_SIZE)
+        {
+            return Err(Error::new(ErrorKind::InvalidData, "Invalid Module Size"));
+        }
+        let width = self.width * module_size;
+        let height = self.height * module_size;
+        let black = (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width / module_size, i / width / module_size);
+                self.black[y * self.width + x]
+            })
+            .collect();
+        Ok(Self {
+            width,
+            height,
+            black,
+        })
+    }
+    /// The image in one gray channel, 0 for black and 255 for white.
+    pub fn to_image(&self) -> Image {
+        Image {
+            width: self.width,
+            height: self.height,
+            channels: 1,
+            pixels: self
+                .black
+                .iter()
+                .map(|black| if *black { 0 } else { u8::MAX })
+                .collect(),
+        }
+    }
+}
+
+/// Dithers the luminance of the color grid to one pixel per module. The luminance is in linear
+/// light, so the share of white pixels matches the brightness of the colors.
+pub fn dither_grid(grid: &ColorGrid, method: Dither) -> MonoImage {
+    let (width, height) = (grid.dimensions.width, grid.dimensions.height);
+    let mut levels: Vec<f64> = grid.storage.iter().map(Color::relative_luminance).collect();
+    let mut black = vec![false; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            let level = levels[y * width + x];
+            let white = match method {
+                Dither::Bayer => level > (BAYER[y % 4][x % 4] as f64 + 0.5) / 16.0,
+                _ => level >= 0.5,
+            };
+            black[y * width + x] = !white;
+            let neighbors: &[(isize, usize, f64)] = match method {
+                Dither::FloydSteinberg => &FLOYD_STEINBERG,
+                Dither::Atkinson => &ATKINSON,
+                Dither::Bayer => &[],
+            };
+            let error = level - if white { 1.0 } else { 0.0 };
+            for (dx, dy, share) in neighbors {
+                let (nx, ny) = (x as isize + dx, y + dy);
+                if nx >= 0 && (nx as usize) < width && ny < height {
+                    levels[ny * width + nx as usize] += error * share;
+                }
+            }
+        }
+    }
+    MonoImage {
+        width,
+        height,
+        black,
+    }
+}
+
+/// Dithers the color grid of the lifehash at its native resolution, then scales each module to
+/// a square of `module_size` pixels so the structure stays readable.
+pub fn dither_image(
+    lifehash: &LifeHash,
+    module_size: usize,
+    method: Dither,
+) -> Result<MonoImage, Error> {
+    dither_grid(&lifehash.color_grid, method).scaled(module_size)
+}
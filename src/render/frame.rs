@@ -0,0 +1,157 @@
+use crate::colors::functions::ColorFunction;
+use crate::colors::rgb::Color;
+use crate::colors::{BLACK, WHITE};
+use crate::lifehash::{quantize16, quantize8, LifeHash, MAX_IMAGE_SIZE};
+use crate::vector::{Shape, VectorImage};
+use crate::Image;
+use std::io::{Error, ErrorKind};
+
+/// Padding around the lifehash that helps detectors find its boundary, in modules.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct Frame {
+    /// Plain padding outside of the border.
+    pub quiet_zone: usize,
+    /// Width of the contrasting ring around the image, none if 0.
+    pub border: usize,
+}
+impl Frame {
+    pub fn is_empty(&self) -> bool {
+        self.quiet_zone == 0 && self.border == 0
+    }
+    /// The border and quiet zone colors. The border takes the gradient's contrast color and the
+    /// quiet zone the opposite one, or the contrast color itself if there is no border.
+    pub fn colors(&self, gradient: &ColorFunction) -> (Color, Color) {
+        let contrast = gradient.contrast_color();
+        let opposite = if contrast == WHITE { BLACK } else { WHITE };
+        if self.border == 0 {
+            (contrast, contrast)
+        } else {
+            (contrast, opposite)
+        }
+    }
+}
+
+/// Surrounds the image with the frame. Modules are measured from the color grid, so the frame
+/// scales with images rendered at any size.
+pub fn frame_image(image: &Image, lifehash: &LifeHash, frame: &Frame) -> Result<Image, Error> {
+    apply_frame(image, lifehash, frame, quantize8, u8::MAX)
+}
+
+/// The 16-bit per channel version of [`frame_image`].
+pub fn frame_image16(
+    image: &Image<u16>,
+    lifehash: &LifeHash,
+    frame: &Frame,
+) -> Result<Image<u16>, Error> {
+    apply_frame(image, lifehash, frame, quantize16, u16::MAX)
+}
+
+pub(crate) fn apply_frame<T: Copy>(
+    image: &Image<T>,
+    lifehash: &LifeHash,
+    frame: &Frame,
+    quantize: fn(f64) -> T,
+    opaque: T,
+) -> Result<Image<T>, Error> {
+    // Modules to pixels, rounded to the nearest.
+    let cells = lifehash.color_grid.dimensions.width;
+    let pixels = |modules: usize| {
+        modules
+            .checked_mul(image.width)?
+            .checked_add(cells / 2)
+            .map(|size| size / cells)
+    };
+    let padded = |size: usize, padding: usize| {
+        padding
+            .checked_mul(2)?
+            .checked_add(size)
+            .filter(|size| *size <= MAX_IMAGE_SIZE)
+    };
+    let border = pixels(frame.border);
+    let padding = frame.quiet_zone.checked_add(frame.border).and_then(pixels);
+    let (Some(border), Some(padding)) = (border, padding) else {
+        return Err(Error::new(ErrorKind::InvalidData, "Invalid Image Size"));
+    };
+    let (Some(width), Some(height)) = (padded(image.width, padding), padded(image.height, padding))
+    else {
+        return Err(Error::new(ErrorKind::InvalidData, "Invalid Image Size"));
+    };
+    let (border_color, quiet_color) = frame.colors(&lifehash.gradient);
+    let border_pixel = solid_pixel(border_color, image.channels, quantize, opaque);
+    let quiet_pixel = solid_pixel(quiet_color, image.channels, quantize, opaque);
+    let mut pixels = Vec::with_capacity(width * height * image.channels);
+    for y in 0..height {
+        for x in 0..width {
+            // Distance in pixels from the edge of the image, 0 inside of it.
+            let outside = padding
+                .saturating_sub(x)
+                .max(padding.saturating_sub(y))
+                .max((x + 1).saturating_sub(padding + image.width))
+                .max((y + 1).saturating_sub(padding + image.height));
+            if outside == 0 {
+                let offset = ((y - padding) * image.width + x - padding) * image.channels;
+                pixels.extend_from_slice(&image.pixels[offset..][..image.channels]);
+            } else if outside <= border {
+                pixels.extend_from_slice(&border_pixel);
+            } else {
+                pixels.extend_from_slice(&quiet_pixel);
+            }
+        }
+    }
+    Ok(Image {
+        width,
+        height,
+        channels: image.channels,
+        pixels,
+    })
+}
+
+/// An opaque pixel of the color with the given number of channels, gray if there are one or two.
+pub(crate) fn solid_pixel<T: Copy>(
+    color: Color,
+    channels: usize,
+    quantize: fn(f64) -> T,
+    opaque: T,
+) -> Vec<T> {
+    let mut pixel = match channels {
+        1 | 2 => vec![quantize((color.r + color.g + color.b) / 3.0)],
+        _ => vec![quantize(color.r), quantize(color.g), quantize(color.b)],
+    };
+    if channels % 2 == 0 {
+        pixel.push(opaque);
+    }
+    pixel
+}
+
+/// Surrounds a vector image in color grid units with the frame. The frame is drawn on top, so it
+/// also covers anything drawn past the edges of the image.
+pub fn frame_vector(image: &VectorImage, lifehash: &LifeHash, frame: &Frame) -> VectorImage {
+    let padding = (frame.quiet_zone + frame.border) as f64;
+    let width = image.width + 2.0 * padding;
+    let height = image.height + 2.0 * padding;
+    let mut shapes: Vec<Shape> = image
+        .shapes
+        .iter()
+        .map(|shape| shape.translated(padding, padding))
+        .collect();
+    let (border_color, quiet_color) = frame.colors(&lifehash.gradient);
+    // Each ring is the area between two insets from the canvas edge, with the inner one as a hole.
+    let mut ring = |color: Color, outer: f64, inner: f64| {
+        let mut shape = Shape::new(color);
+        shape.add_rect(outer, outer, width - 2.0 * outer, height - 2.0 * outer);
+        shape.add_rect(inner, inner, width - 2.0 * inner, height - 2.0 * inner);
+        shapes.push(shape);
+    };
+    if frame.quiet_zone > 0 {
+        ring(quiet_color, 0.0, frame.quiet_zone as f64);
+    }
+    if frame.border > 0 {
+        ring(border_color, frame.quiet_zone as f64, padding);
+    }
+    VectorImage {
+        width,
+        height,
+        crisp_edges: image.crisp_edges,
+        shapes,
+    }
+}
@@ -0,0 +1,129 @@
+use crate::lifehash::{quantize16, quantize8, MAX_IMAGE_SIZE};
+use crate::Image;
+use clap::ValueEnum;
+use std::io::{Error, ErrorKind};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum MaskShape {
+    Circle,
+    /// A rectangle with rounded corners, see [`Mask::radius`].
+    Rounded,
+    /// A superellipse, between a circle and a rounded rectangle.
+    Squircle,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Mask {
+    pub shape: Option<MaskShape>,
+    /// Corner radius of [`MaskShape::Rounded`] as a fraction of the image size, 0.5 is a circle.
+    pub radius: f64,
+    /// Transparent pixels added around the image.
+    pub margin: usize,
+}
+impl Default for Mask {
+    fn default() -> Self {
+        Self {
+            shape: None,
+            radius: 0.2,
+            margin: 0,
+        }
+    }
+}
+impl Mask {
+    pub fn is_empty(&self) -> bool {
+        self.shape.is_none() && self.margin == 0
+    }
+    /// Signed distance in pixels from the point to the edge of the shape, negative inside. The
+    /// point is relative to the center of a `width` by `height` shape.
+    fn distance(&self, x: f64, y: f64, width: f64, height: f64) -> f64 {
+        let (half_width, half_height) = (width / 2.0, height / 2.0);
+        let half_size = half_width.min(half_height);
+        match self.shape {
+            None => (x.abs() - half_width).max(y.abs() - half_height),
+            Some(MaskShape::Circle) => x.hypot(y) - half_size,
+            Some(MaskShape::Rounded) => {
+                let radius = (self.radius * 2.0 * half_size).clamp(0.0, half_size);
+                let qx = x.abs() - (half_width - radius);
+                let qy = y.abs() - (half_height - radius);
+                qx.max(0.0).hypot(qy.max(0.0)) + qx.max(qy).min(0.0) - radius
+            }
+            Some(MaskShape::Squircle) => {
+                // |x|^4 + |y|^4 = 1 in unit coordinates, divided by the gradient of the norm
+                // to get close to a distance in pixels.
+                let ux = x / half_width;
+                let uy = y / half_height;
+                let norm = (ux.powi(4) + uy.powi(4)).powf(0.25);
+                if norm == 0.0 {
+                    return -half_size;
+                }
+                let gradient = (ux.powi(6) + uy.powi(6)).sqrt() / norm.powi(3);
+                (norm - 1.0) * half_size / gradient
+            }
+        }
+    }
+}
+
+/// Cuts the image to the mask shape with anti-aliased edges in the alpha channel, and surrounds
+/// it with the transparent margin. An alpha channel is added if the image has none.
+pub fn mask_image(image: &Image, mask: &Mask) -> Result<Image, Error> {
+    apply_mask(image, mask, quantize8, u8::MAX)
+}
+
+/// The 16-bit per channel version of [`mask_image`].
+pub fn mask_image16(image: &Image<u16>, mask: &Mask) -> Result<Image<u16>, Error> {
+    apply_mask(image, mask, quantize16, u16::MAX)
+}
+
+pub(crate) fn apply_mask<T: Copy + Into<f64>>(
+    image: &Image<T>,
+    mask: &Mask,
+    quantize: fn(f64) -> T,
+    opaque: T,
+) -> Result<Image<T>, Error> {
+    let padded = |size: usize| {
+        mask.margin
+            .checked_mul(2)?
+            .checked_add(size)
+            .filter(|size| *size <= MAX_IMAGE_SIZE)
+    };
+    let (Some(width), Some(height)) = (padded(image.width), padded(image.height)) else {
+        return Err(Error::new(ErrorKind::InvalidData, "Invalid Image Size"));
+    };
+    if image.width == 0 || image.height == 0 {
+        return Err(Error::new(ErrorKind::InvalidData, "Invalid Image Size"));
+    }
+    let has_alpha = image.channels % 2 == 0;
+    let color_components = if has_alpha {
+        image.channels - 1
+    } else {
+        image.channels
+    };
+    let channels = color_components + 1;
+    let mut pixels = Vec::with_capacity(width * height * channels);
+    for target_y in 0..height {
+        // The margin repeats the edge colors so scaling the image later doesn't pull in dark fringes.
+        let source_y = target_y.saturating_sub(mask.margin).min(image.height - 1);
+        let y = target_y as f64 + 0.5 - mask.margin as f64 - image.height as f64 / 2.0;
+        for target_x in 0..width {
+            let source_x = target_x.saturating_sub(mask.margin).min(image.width - 1);
+            let x = target_x as f64 + 0.5 - mask.margin as f64 - image.width as f64 / 2.0;
+            let pixel = &image.pixels[(source_y * image.width + source_x) * image.channels..]
+                [..image.channels];
+            let alpha = if has_alpha {
+                pixel[color_components]
+            } else {
+                opaque
+            };
+            let distance = mask.distance(x, y, image.width as f64, image.height as f64);
+            let coverage = (0.5 - distance).clamp(0.0, 1.0);
+            pixels.extend_from_slice(&pixel[..color_components]);
+            pixels.push(quantize(alpha.into() / opaque.into() * coverage));
+        }
+    }
+    Ok(Image {
+        width,
+        height,
+        channels,
+        pixels,
+    })
+}
@@ -0,0 +1,11 @@
+pub mod card;
+pub mod compare;
+pub mod contour;
+pub mod dither;
+pub mod font;
+pub mod frame;
+pub mod mask;
+pub mod overlay;
+pub mod sheet;
+pub mod smooth;
+pub mod tile;
@@ -0,0 +1,214 @@
+use crate::colors::rgb::Color;
+use crate::grids::frac_grid::{FracGrid, Interpolation};
+use crate::lifehash::{quantize16, quantize8, resample_image, scale_image, LifeHash};
+use crate::render::smooth::smooth_values;
+use crate::{Fit, Image};
+use clap::ValueEnum;
+use std::io::{Error, ErrorKind};
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, ValueEnum)]
+pub enum OpacityCurve {
+    #[default]
+    Linear,
+    /// Eases in and out, flattening both ends.
+    Smoothstep,
+    /// Keeps low values faint.
+    Square,
+    /// Lifts low values.
+    Sqrt,
+}
+
+/// Maps the normalized frac values to opacity, so the structure of the lifehash shows as
+/// translucency. A minimum above the maximum inverts the mapping.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Overlay {
+    pub curve: OpacityCurve,
+    pub min_opacity: f64,
+    pub max_opacity: f64,
+}
+impl Default for Overlay {
+    fn default() -> Self {
+        Self {
+            curve: OpacityCurve::default(),
+            min_opacity: 0.0,
+            max_opacity: 1.0,
+        }
+    }
+}
+impl Overlay {
+    pub fn opacity(&self, value: f64) -> f64 {
+        let t = value.clamp(0.0, 1.0);
+        let curved = match self.curve {
+            OpacityCurve::Linear => t,
+            OpacityCurve::Smoothstep => t * t * (3.0 - 2.0 * t),
+            OpacityCurve::Square => t * t,
+            OpacityCurve::Sqrt => t.sqrt(),
+        };
+        (self.min_opacity + (self.max_opacity - self.min_opacity) * curved).clamp(0.0, 1.0)
+    }
+}
+
+/// The opacity of a lifehash under an overlay, rendered to images the same ways as the lifehash
+/// so each pixel of the map is the opacity of the matching pixel of the image.
+pub struct OpacityMap<'a> {
+    lifehash: &'a LifeHash,
+    overlay: Overlay,
+}
+impl OpacityMap<'_> {
+    /// The opacity of every module: the frac grid expanded by the pattern like the color grid,
+    /// with the overlay applied to each value.
+    pub fn grid(&self) -> FracGrid {
+        let mut grid = self.lifehash.frac_grid.expand(self.lifehash.pattern);
+        for value in grid.storage.iter_mut() {
+            *value = self.overlay.opacity(*value);
+        }
+        grid
+    }
+    /// The opacity as a gray image, each module scaled to `module_size` pixels like
+    /// [`LifeHash::image`].
+    pub fn image(&self, module_size: usize) -> Result<Image, Error> {
+        self.scaled(module_size, quantize8, u8::MAX)
+    }
+    /// The 16-bit per channel version of [`OpacityMap::image`].
+    pub fn image16(&self, module_size: usize) -> Result<Image<u16>, Error> {
+        self.scaled(module_size, quantize16, u16::MAX)
+    }
+    /// The opacity at exactly `size` pixels square, like [`LifeHash::image_fit`].
+    pub fn image_fit(&self, size: usize, fit: Fit) -> Result<Image, Error> {
+        self.resampled(size, fit, quantize8, u8::MAX)
+    }
+    /// The 16-bit per channel version of [`OpacityMap::image_fit`].
+    pub fn image16_fit(&self, size: usize, fit: Fit) -> Result<Image<u16>, Error> {
+        self.resampled(size, fit, quantize16, u16::MAX)
+    }
+    /// The opacity of the interpolated frac values, like [`smooth_image`](crate::render::smooth::smooth_image).
+    pub fn smooth_image(&self, size: usize, interpolation: Interpolation) -> Result<Image, Error> {
+        self.smoothed(size, interpolation, quantize8, u8::MAX)
+    }
+    /// The 16-bit per channel version of [`OpacityMap::smooth_image`].
+    pub fn smooth_image16(
+        &self,
+        size: usize,
+        interpolation: Interpolation,
+    ) -> Result<Image<u16>, Error> {
+        self.smoothed(size, interpolation, quantize16, u16::MAX)
+    }
+    fn scaled<T: Copy + Default>(
+        &self,
+        module_size: usize,
+        quantize: fn(f64) -> T,
+        opaque: T,
+    ) -> Result<Image<T>, Error> {
+        let grid = self.grid();
+        let (width, height) = (grid.dimensions.width, grid.dimensions.height);
+        scale_image(
+            width,
+            height,
+            &grid.colors(),
+            module_size,
+            false,
+            quantize,
+            opaque,
+        )
+    }
+    fn resampled<T: Copy + Default>(
+        &self,
+        size: usize,
+        fit: Fit,
+        quantize: fn(f64) -> T,
+        opaque: T,
+    ) -> Result<Image<T>, Error> {
+        let grid = self.grid();
+        let (width, height) = (grid.dimensions.width, grid.dimensions.height);
+        resample_image(
+            width,
+            height,
+            &grid.colors(),
+            size,
+            fit,
+            false,
+            quantize,
+            opaque,
+        )
+    }
+    fn smoothed<T: Copy + Default>(
+        &self,
+        size: usize,
+        interpolation: Interpolation,
+        quantize: fn(f64) -> T,
+        opaque: T,
+    ) -> Result<Image<T>, Error> {
+        smooth_values(
+            &self.lifehash.frac_grid,
+            self.lifehash.pattern,
+            size,
+            interpolation,
+            |value| {
+                let opacity = self.overlay.opacity(value);
+                Color::new(opacity, opacity, opacity)
+            },
+            false,
+            quantize,
+            opaque,
+        )
+    }
+}
+
+/// The opacity map of the lifehash under the overlay.
+pub fn opacity_map<'a>(lifehash: &'a LifeHash, overlay: &Overlay) -> OpacityMap<'a> {
+    OpacityMap {
+        lifehash,
+        overlay: *overlay,
+    }
+}
+
+/// Multiplies the alpha of the image, which gets an alpha channel if it has none, by the
+/// brightness of the opacity image rendered from an [`OpacityMap`].
+pub fn apply_opacity(image: &Image, opacity: &Image) -> Result<Image, Error> {
+    combine_opacity(image, opacity, quantize8, u8::MAX)
+}
+
+/// The 16-bit per channel version of [`apply_opacity`].
+pub fn apply_opacity16(image: &Image<u16>, opacity: &Image<u16>) -> Result<Image<u16>, Error> {
+    combine_opacity(image, opacity, quantize16, u16::MAX)
+}
+
+fn combine_opacity<T: Copy + Into<f64>>(
+    image: &Image<T>,
+    opacity: &Image<T>,
+    quantize: fn(f64) -> T,
+    opaque: T,
+) -> Result<Image<T>, Error> {
+    if (image.width, image.height) != (opacity.width, opacity.height) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Opacity image size doesn't match the image",
+        ));
+    }
+    let has_alpha = image.channels % 2 == 0;
+    let color_components = if has_alpha {
+        image.channels - 1
+    } else {
+        image.channels
+    };
+    let mut pixels = Vec::with_capacity(image.width * image.height * (color_components + 1));
+    for (pixel, opacity_pixel) in image
+        .pixels
+        .chunks_exact(image.channels)
+        .zip(opacity.pixels.chunks_exact(opacity.channels))
+    {
+        let alpha = if has_alpha {
+            pixel[color_components].into() / opaque.into()
+        } else {
+            1.0
+        };
+        pixels.extend_from_slice(&pixel[..color_components]);
+        pixels.push(quantize(alpha * opacity_pixel[0].into() / opaque.into()));
+    }
+    Ok(Image {
+        width: image.width,
+        height: image.height,
+        channels: color_components + 1,
+        pixels,
+    })
+}
@@ -0,0 +1,159 @@
+use crate::colors::rgb::Color;
+use crate::colors::WHITE;
+use crate::encoders::rgba;
+use crate::lifehash::{quantize16, quantize8, MAX_IMAGE_SIZE};
+use crate::vector::{Shape, VectorImage};
+use crate::Image;
+use clap::ValueEnum;
+use std::io::{Error, ErrorKind};
+
+/// How each line of the input of a batch, such as a contact sheet, is read.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum SheetInput {
+    /// Hashes the text of the line.
+    Text,
+    /// Decodes the line as a hex digest.
+    Hex,
+}
+
+/// A grid of lifehashes in one image, for comparing many of them at a glance.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sheet {
+    /// Lifehashes per row, by default as many as make the grid close to square. At least one
+    /// and at most as many as there are lifehashes are used.
+    pub columns: Option<usize>,
+    /// Space between the lifehashes and around the edges in pixels.
+    pub spacing: usize,
+    pub background: Color,
+}
+impl Default for Sheet {
+    fn default() -> Self {
+        Self {
+            columns: None,
+            spacing: 8,
+            background: WHITE,
+        }
+    }
+}
+impl Sheet {
+    /// The number of columns and rows for `count` lifehashes.
+    pub fn grid(&self, count: usize) -> (usize, usize) {
+        let columns = self
+            .columns
+            .unwrap_or_else(|| (1..).find(|c| c * c >= count).unwrap_or(1))
+            .clamp(1, count.max(1));
+        (columns, count.div_ceil(columns))
+    }
+}
+
+/// Lays the images out in a grid, each centered in a cell the size of the largest one. Pixels
+/// with alpha are blended over the background, so the sheet is always RGB.
+pub fn sheet_image(images: &[Image], sheet: &Sheet) -> Result<Image, Error> {
+    apply_sheet(images, sheet, quantize8, u8::MAX)
+}
+
+/// The 16-bit per channel version of [`sheet_image`].
+pub fn sheet_image16(images: &[Image<u16>], sheet: &Sheet) -> Result<Image<u16>, Error> {
+    apply_sheet(images, sheet, quantize16, u16::MAX)
+}
+
+pub(crate) fn apply_sheet<T: Copy + Into<f64>>(
+    images: &[Image<T>],
+    sheet: &Sheet,
+    quantize: fn(f64) -> T,
+    opaque: T,
+) -> Result<Image<T>, Error> {
+    if images.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidInput, "Empty contact sheet"));
+    }
+    let (columns, rows) = sheet.grid(images.len());
+    let cell_width = images.iter().map(|image| image.width).max().unwrap_or(0);
+    let cell_height = images.iter().map(|image| image.height).max().unwrap_or(0);
+    // The cells are placed within the sheet, so their offsets can't overflow once it fits.
+    let size = |cells: usize, cell_size: usize| {
+        cell_size
+            .checked_add(sheet.spacing)?
+            .checked_mul(cells)?
+            .checked_add(sheet.spacing)
+            .filter(|size| *size <= MAX_IMAGE_SIZE)
+    };
+    let (Some(width), Some(height)) = (size(columns, cell_width), size(rows, cell_height)) else {
+        return Err(Error::new(ErrorKind::InvalidData, "Invalid Image Size"));
+    };
+    let max = opaque.into();
+    let background = [sheet.background.r, sheet.background.g, sheet.background.b];
+    let mut pixels: Vec<T> = background
+        .iter()
+        .map(|c| quantize(*c))
+        .collect::<Vec<T>>()
+        .repeat(width * height);
+    for (i, image) in images.iter().enumerate() {
+        let left = sheet.spacing + (i % columns) * (cell_width + sheet.spacing);
+        let top = sheet.spacing + (i / columns) * (cell_height + sheet.spacing);
+        let left = left + (cell_width - image.width) / 2;
+        let top = top + (cell_height - image.height) / 2;
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let pixel = rgba(
+                    &image.pixels[(y * image.width + x) * image.channels..][..image.channels],
+                    opaque,
+                );
+                let alpha = pixel[3].into() / max;
+                let offset = ((top + y) * width + left + x) * 3;
+                for c in 0..3 {
+                    pixels[offset + c] =
+                        quantize(pixel[c].into() / max * alpha + background[c] * (1.0 - alpha));
+                }
+            }
+        }
+    }
+    Ok(Image {
+        width,
+        height,
+        channels: 3,
+        pixels,
+    })
+}
+
+/// Lays vector images out like [`sheet_image`], with `scale` pixels per unit to convert the
+/// spacing.
+pub fn sheet_vector(images: &[VectorImage], sheet: &Sheet, scale: f64) -> VectorImage {
+    let (columns, rows) = sheet.grid(images.len());
+    let spacing = sheet.spacing as f64 / scale;
+    let cell_width = images.iter().map(|image| image.width).fold(0.0, f64::max);
+    let cell_height = images.iter().map(|image| image.height).fold(0.0, f64::max);
+    let width = columns as f64 * (cell_width + spacing) + spacing;
+    let height = rows as f64 * (cell_height + spacing) + spacing;
+    let mut background = Shape::new(sheet.background);
+    background.add_rect(0.0, 0.0, width, height);
+    let mut shapes = vec![background];
+    for (i, image) in images.iter().enumerate() {
+        let left = spacing
+            + (i % columns) as f64 * (cell_width + spacing)
+            + (cell_width - image.width) / 2.0;
+        let top = spacing
+            + (i / columns) as f64 * (cell_height + spacing)
+            + (cell_height - image.height) / 2.0;
+        // Shapes may reach past the edges of their image, so each is cut to its cell.
+        shapes.extend(
+            image
+                .shapes
+                .iter()
+                .map(|shape| {
+                    shape.translated(left, top).clipped(
+                        left,
+                        top,
+                        left + image.width,
+                        top + image.height,
+                    )
+                })
+                .filter(|shape| !shape.paths.is_empty()),
+        );
+    }
+    VectorImage {
+        width,
+        height,
+        crisp_edges: images.iter().all(|image| image.crisp_edges),
+        shapes,
+    }
+}
@@ -0,0 +1,105 @@
+use crate::colors::rgb::Color;
+use crate::grids::color_grid::pattern_transforms;
+use crate::grids::frac_grid::{FracGrid, Interpolation};
+use crate::lifehash::{quantize16, quantize8, LifeHash, MAX_IMAGE_SIZE};
+use crate::utils::target_size;
+use crate::{Image, Pattern};
+use std::io::{Error, ErrorKind};
+
+/// Renders a soft version of the lifehash at any size. The frac grid is interpolated before the
+/// gradient is applied, and the pattern transforms are applied in continuous space.
+pub fn smooth_image(
+    lifehash: &LifeHash,
+    size: usize,
+    interpolation: Interpolation,
+    has_alpha: bool,
+) -> Result<Image, Error> {
+    render_smooth(lifehash, size, interpolation, has_alpha, quantize8, u8::MAX)
+}
+
+/// The 16-bit per channel version of [`smooth_image`], which avoids banding in large gradients.
+pub fn smooth_image16(
+    lifehash: &LifeHash,
+    size: usize,
+    interpolation: Interpolation,
+    has_alpha: bool,
+) -> Result<Image<u16>, Error> {
+    render_smooth(
+        lifehash,
+        size,
+        interpolation,
+        has_alpha,
+        quantize16,
+        u16::MAX,
+    )
+}
+
+fn render_smooth<T: Copy + Default>(
+    lifehash: &LifeHash,
+    size: usize,
+    interpolation: Interpolation,
+    has_alpha: bool,
+    quantize: fn(f64) -> T,
+    opaque: T,
+) -> Result<Image<T>, Error> {
+    smooth_values(
+        &lifehash.frac_grid,
+        lifehash.pattern,
+        size,
+        interpolation,
+        |value| lifehash.gradient.apply(value),
+        has_alpha,
+        quantize,
+        opaque,
+    )
+}
+
+/// Interpolates the frac grid expanded by the pattern to `size` pixels square, coloring each
+/// interpolated value with `color`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn smooth_values<T: Copy + Default>(
+    frac_grid: &FracGrid,
+    pattern: Pattern,
+    size: usize,
+    interpolation: Interpolation,
+    color: impl Fn(f64) -> Color,
+    has_alpha: bool,
+    quantize: fn(f64) -> T,
+    opaque: T,
+) -> Result<Image<T>, Error> {
+    if size == 0 || size > MAX_IMAGE_SIZE {
+        return Err(Error::new(ErrorKind::InvalidData, "Invalid Image Size"));
+    }
+    let frac_size = frac_grid.dimensions.width as f64;
+    let grid_size = target_size(frac_grid.dimensions, pattern).width as f64;
+    let transforms = pattern_transforms(pattern);
+    let result_components = if has_alpha { 4 } else { 3 };
+    let mut result_colors = vec![T::default(); size * size * result_components];
+    for target_y in 0..size {
+        let y = (target_y as f64 + 0.5) * grid_size / size as f64;
+        for target_x in 0..size {
+            let x = (target_x as f64 + 0.5) * grid_size / size as f64;
+            // Find the transform that drew this part of the grid and sample the frac grid where it came from.
+            let (source_x, source_y) = transforms
+                .iter()
+                .map(|t| t.invert(x, y, grid_size))
+                .find(|(sx, sy)| (0.0..frac_size).contains(sx) && (0.0..frac_size).contains(sy))
+                .unwrap_or((x, y));
+            let value = frac_grid.sample(source_x, source_y, interpolation);
+            let color = color(value);
+            let target_offset = (target_y * size + target_x) * result_components;
+            result_colors[target_offset] = quantize(color.r);
+            result_colors[target_offset + 1] = quantize(color.g);
+            result_colors[target_offset + 2] = quantize(color.b);
+            if has_alpha {
+                result_colors[target_offset + 3] = opaque;
+            }
+        }
+    }
+    Ok(Image {
+        width: size,
+        height: size,
+        channels: result_components,
+        pixels: result_colors,
+    })
+}
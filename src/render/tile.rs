@@ -0,0 +1,39 @@
+use crate::lifehash::MAX_IMAGE_SIZE;
+use crate::Image;
+use std::io::{Error, ErrorKind};
+
+/// Repeats the image to fill `width` by `height` pixels. The Life field wraps toroidally and the
+/// patterns mirror it, so a full lifehash image tiles seamlessly.
+/// The offset moves the tile origin, in pixels, so the center of symmetry isn't always in the corner.
+pub fn tile<T: Copy>(
+    image: &Image<T>,
+    width: usize,
+    height: usize,
+    offset_x: usize,
+    offset_y: usize,
+) -> Result<Image<T>, Error> {
+    if width == 0 || height == 0 || width > MAX_IMAGE_SIZE || height > MAX_IMAGE_SIZE {
+        return Err(Error::new(ErrorKind::InvalidData, "Invalid Image Size"));
+    }
+    if image.width == 0 || image.height == 0 {
+        return Err(Error::new(ErrorKind::InvalidData, "Invalid Tile Size"));
+    }
+    // Offsets past a whole tile wrap around, reduced first so adding them can't overflow.
+    let (offset_x, offset_y) = (offset_x % image.width, offset_y % image.height);
+    let mut pixels = Vec::with_capacity(width * height * image.channels);
+    for target_y in 0..height {
+        let source_y = (target_y + offset_y) % image.height;
+        let row = &image.pixels[source_y * image.width * image.channels..]
+            [..image.width * image.channels];
+        for target_x in 0..width {
+            let source_x = (target_x + offset_x) % image.width;
+            pixels.extend_from_slice(&row[source_x * image.channels..][..image.channels]);
+        }
+    }
+    Ok(Image {
+        width,
+        height,
+        channels: image.channels,
+        pixels,
+    })
+}
@@ -0,0 +1,287 @@
+use crate::colors::rgb::Color;
+use crate::encoders::png::{decode_png, encode_png, png_bit_depth};
+use crate::encoders::rgba;
+use crate::lifehash::{quantize16, quantize8, MAX_IMAGE_SIZE};
+use crate::Image;
+use clap::ValueEnum;
+use std::io::{Error, ErrorKind, Read, Write};
+
+/// Opacity of the drop shadow where the stamp is fully opaque.
+const SHADOW_OPACITY: f64 = 0.5;
+
+/// Where the stamp is placed on the base image.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, ValueEnum)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    #[default]
+    BottomRight,
+}
+impl Anchor {
+    /// The horizontal and vertical alignment, 0 for start, 1 for center and 2 for end.
+    fn alignment(self) -> (i64, i64) {
+        match self {
+            Anchor::TopLeft => (0, 0),
+            Anchor::Top => (1, 0),
+            Anchor::TopRight => (2, 0),
+            Anchor::Left => (0, 1),
+            Anchor::Center => (1, 1),
+            Anchor::Right => (2, 1),
+            Anchor::BottomLeft => (0, 2),
+            Anchor::Bottom => (1, 2),
+            Anchor::BottomRight => (2, 2),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct StampOptions {
+    pub anchor: Anchor,
+    /// Distance in pixels from the anchored edges of the base image to the stamp and its border.
+    pub margin: usize,
+    /// Width in pixels of a border that follows the outline of the stamp, none if 0.
+    pub border: usize,
+    /// Offset and blur in pixels of a drop shadow, none if 0.
+    pub shadow: usize,
+}
+
+/// Composites the stamp onto the base image with alpha blending. The result is RGB, with alpha
+/// if the base image has it.
+pub fn stamp_image(
+    base: &Image,
+    stamp: &Image,
+    options: &StampOptions,
+    border_color: Color,
+) -> Result<Image, Error> {
+    composite(base, stamp, options, border_color, quantize8, u8::MAX)
+}
+
+/// The 16-bit per channel version of [`stamp_image`].
+pub fn stamp_image16(
+    base: &Image<u16>,
+    stamp: &Image<u16>,
+    options: &StampOptions,
+    border_color: Color,
+) -> Result<Image<u16>, Error> {
+    composite(base, stamp, options, border_color, quantize16, u16::MAX)
+}
+
+/// Decodes a base PNG, stamps it and writes it back as PNG, keeping 16-bit images at 16 bits.
+pub fn stamp_png<R: Read, W: Write>(
+    mut base: R,
+    stamp: &Image,
+    options: &StampOptions,
+    border_color: Color,
+    writer: W,
+) -> Result<(), Error> {
+    let mut data = vec![];
+    base.read_to_end(&mut data)?;
+    if png_bit_depth(data.as_slice())? == 16 {
+        let base = decode_png::<u16, _>(data.as_slice())?;
+        let result = stamp_image16(&base, &stamp.to_u16(), options, border_color)?;
+        encode_png(&result, writer)
+    } else {
+        let base = decode_png::<u8, _>(data.as_slice())?;
+        encode_png(&stamp_image(&base, stamp, options, border_color)?, writer)
+    }
+}
+
+fn composite<T: Copy + Into<f64>>(
+    base: &Image<T>,
+    stamp: &Image<T>,
+    options: &StampOptions,
+    border_color: Color,
+    quantize: fn(f64) -> T,
+    opaque: T,
+) -> Result<Image<T>, Error> {
+    if stamp.width == 0 || stamp.height == 0 {
+        return Err(Error::new(ErrorKind::InvalidInput, "Empty stamp image"));
+    }
+    let max = opaque.into();
+    // The stamp is drawn on a padded canvas with room for the border and the shadow.
+    let padding = options
+        .shadow
+        .checked_mul(2)
+        .and_then(|shadow| shadow.checked_add(options.border));
+    let padded = |size: usize| {
+        padding?
+            .checked_mul(2)?
+            .checked_add(size)
+            .filter(|size| *size <= MAX_IMAGE_SIZE)
+    };
+    let (Some(padding), Some(width), Some(height)) =
+        (padding, padded(stamp.width), padded(stamp.height))
+    else {
+        return Err(Error::new(ErrorKind::InvalidData, "Invalid Image Size"));
+    };
+    let mut stamp_alpha = vec![0.0; width * height];
+    let mut canvas = vec![[0.0; 4]; width * height];
+    for y in 0..stamp.height {
+        for x in 0..stamp.width {
+            let pixel = rgba(
+                &stamp.pixels[(y * stamp.width + x) * stamp.channels..][..stamp.channels],
+                opaque,
+            );
+            let index = (y + padding) * width + x + padding;
+            stamp_alpha[index] = pixel[3].into() / max;
+            canvas[index] = pixel.map(|c| c.into() / max);
+        }
+    }
+    let silhouette = if options.border > 0 {
+        dilate(&stamp_alpha, width, height, options.border)
+    } else {
+        stamp_alpha
+    };
+    let mut layers = vec![];
+    if options.shadow > 0 {
+        let mut shadow = vec![0.0; width * height];
+        for y in options.shadow..height {
+            for x in options.shadow..width {
+                shadow[y * width + x] =
+                    silhouette[(y - options.shadow) * width + x - options.shadow];
+            }
+        }
+        let shadow = blur(&shadow, width, height, options.shadow.div_ceil(2));
+        layers.push(
+            shadow
+                .iter()
+                .map(|a| [0.0, 0.0, 0.0, a * SHADOW_OPACITY])
+                .collect(),
+        );
+    }
+    if options.border > 0 {
+        let color = [border_color.r, border_color.g, border_color.b];
+        layers.push(
+            silhouette
+                .iter()
+                .map(|a| [color[0], color[1], color[2], *a])
+                .collect(),
+        );
+    }
+    // The stamp goes on top of the layers below it.
+    let mut stack: Vec<[f64; 4]> = vec![[0.0; 4]; width * height];
+    for layer in layers.iter().chain(std::iter::once(&canvas)) {
+        for (destination, source) in stack.iter_mut().zip(layer.iter()) {
+            *destination = over(*source, *destination);
+        }
+    }
+
+    // Place the stamp and its border at the anchor, then offset for the padding around them.
+    let box_width = (stamp.width + 2 * options.border) as i64;
+    let box_height = (stamp.height + 2 * options.border) as i64;
+    let align = |alignment: i64, base_size: usize, box_size: i64| match alignment {
+        // Margins past the largest image all put the stamp off the base alike.
+        0 => options.margin.min(MAX_IMAGE_SIZE) as i64,
+        1 => (base_size as i64 - box_size) / 2,
+        _ => base_size as i64 - box_size - options.margin.min(MAX_IMAGE_SIZE) as i64,
+    };
+    let (horizontal, vertical) = options.anchor.alignment();
+    let shift = (padding - options.border) as i64;
+    let origin_x = align(horizontal, base.width, box_width) - shift;
+    let origin_y = align(vertical, base.height, box_height) - shift;
+
+    let has_alpha = base.channels % 2 == 0;
+    let channels = if has_alpha { 4 } else { 3 };
+    let mut pixels = Vec::with_capacity(base.width * base.height * channels);
+    for y in 0..base.height {
+        for x in 0..base.width {
+            let pixel = rgba(
+                &base.pixels[(y * base.width + x) * base.channels..][..base.channels],
+                opaque,
+            );
+            let mut color = pixel.map(|c| c.into() / max);
+            let canvas_x = x as i64 - origin_x;
+            let canvas_y = y as i64 - origin_y;
+            if (0..width as i64).contains(&canvas_x) && (0..height as i64).contains(&canvas_y) {
+                color = over(stack[canvas_y as usize * width + canvas_x as usize], color);
+            }
+            pixels.extend(color[..channels].iter().map(|c| quantize(*c)));
+        }
+    }
+    Ok(Image {
+        width: base.width,
+        height: base.height,
+        channels,
+        pixels,
+    })
+}
+
+/// Porter-Duff source over destination for colors that are not premultiplied.
+fn over(source: [f64; 4], destination: [f64; 4]) -> [f64; 4] {
+    let alpha = source[3] + destination[3] * (1.0 - source[3]);
+    if alpha <= 0.0 {
+        return [0.0; 4];
+    }
+    let mut result = [0.0, 0.0, 0.0, alpha];
+    for c in 0..3 {
+        result[c] =
+            (source[c] * source[3] + destination[c] * destination[3] * (1.0 - source[3])) / alpha;
+    }
+    result
+}
+
+/// Grows the alpha mask by `radius` pixels, anti-aliased at the rounded edge.
+fn dilate(alpha: &[f64], width: usize, height: usize, radius: usize) -> Vec<f64> {
+    let reach = radius as i64 + 1;
+    let mut result = vec![0.0; width * height];
+    for y in 0..height as i64 {
+        for x in 0..width as i64 {
+            let mut value: f64 = 0.0;
+            for dy in -reach..=reach {
+                for dx in -reach..=reach {
+                    let (sx, sy) = (x + dx, y + dy);
+                    if sx < 0 || sy < 0 || sx >= width as i64 || sy >= height as i64 {
+                        continue;
+                    }
+                    let weight =
+                        (radius as f64 + 1.0 - (dx as f64).hypot(dy as f64)).clamp(0.0, 1.0);
+                    value = value.max(alpha[sy as usize * width + sx as usize] * weight);
+                }
+            }
+            result[y as usize * width + x as usize] = value;
+        }
+    }
+    result
+}
+
+/// Two passes of a separable box blur, which is close to a gaussian.
+fn blur(alpha: &[f64], width: usize, height: usize, radius: usize) -> Vec<f64> {
+    let mut result = alpha.to_vec();
+    for _ in 0..2 {
+        result = box_blur(&result, width, height, radius, (1, 0));
+        result = box_blur(&result, width, height, radius, (0, 1));
+    }
+    result
+}
+
+fn box_blur(
+    alpha: &[f64],
+    width: usize,
+    height: usize,
+    radius: usize,
+    direction: (usize, usize),
+) -> Vec<f64> {
+    let radius = radius as i64;
+    let count = (2 * radius + 1) as f64;
+    let mut result = vec![0.0; width * height];
+    for y in 0..height as i64 {
+        for x in 0..width as i64 {
+            let mut sum = 0.0;
+            for offset in -radius..=radius {
+                let sx = x + offset * direction.0 as i64;
+                let sy = y + offset * direction.1 as i64;
+                if sx >= 0 && sy >= 0 && sx < width as i64 && sy < height as i64 {
+                    sum += alpha[sy as usize * width + sx as usize];
+                }
+            }
+            result[y as usize * width + x as usize] = sum / count;
+        }
+    }
+    result
+}
@@ -2,6 +2,7 @@ use crate::utils::bits::Enumerator;
 use crate::{Dimensions, Pattern, Version};
 use std::io::Error;
 
+pub mod base64;
 pub mod bits;
 pub const fn lerp_to(to_a: f64, to_b: f64, t: f64) -> f64 {
     t * (to_b - to_a) + to_a
@@ -0,0 +1,165 @@
+use crate::colors::rgb::Color;
+use crate::encoders::rgba;
+use crate::Image;
+
+/// A filled area of one color. Each path is a closed polygon and overlapping paths are filled
+/// with the even-odd rule, so holes are paths nested inside another.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shape {
+    pub color: Color,
+    pub opacity: f64,
+    pub paths: Vec<Vec<(f64, f64)>>,
+}
+impl Shape {
+    pub fn new(color: Color) -> Self {
+        Self {
+            color,
+            opacity: 1.0,
+            paths: vec![],
+        }
+    }
+    pub fn add_rect(&mut self, x: f64, y: f64, width: f64, height: f64) {
+        self.paths.push(vec![
+            (x, y),
+            (x + width, y),
+            (x + width, y + height),
+            (x, y + height),
+        ]);
+    }
+    /// A copy of the shape moved by `dx`, `dy`.
+    pub fn translated(&self, dx: f64, dy: f64) -> Shape {
+        Shape {
+            color: self.color,
+            opacity: self.opacity,
+            paths: self
+                .paths
+                .iter()
+                .map(|path| path.iter().map(|(x, y)| (x + dx, y + dy)).collect())
+                .collect(),
+        }
+    }
+    /// A copy of the shape cut to the rectangle from `left`, `top` to `right`, `bottom`. The
+    /// parts of the paths outside are moved onto its edges, which leaves what is filled inside
+    /// unchanged for the even-odd rule.
+    pub fn clipped(&self, left: f64, top: f64, right: f64, bottom: f64) -> Shape {
+        // Each edge of the rectangle as a test for the inside and the crossing of a segment.
+        type Edge = (fn(f64, f64, f64) -> bool, usize);
+        let edges: [(Edge, f64); 4] = [
+            ((|x, _, e| x >= e, 0), left),
+            ((|x, _, e| x <= e, 0), right),
+            ((|_, y, e| y >= e, 1), top),
+            ((|_, y, e| y <= e, 1), bottom),
+        ];
+        let paths = self
+            .paths
+            .iter()
+            .map(|path| {
+                edges
+                    .iter()
+                    .fold(path.clone(), |path, ((inside, axis), edge)| {
+                        let inside = |(x, y): (f64, f64)| inside(x, y, *edge);
+                        let crossing = |a: (f64, f64), b: (f64, f64)| {
+                            let (a_value, b_value) =
+                                if *axis == 0 { (a.0, b.0) } else { (a.1, b.1) };
+                            let t = (edge - a_value) / (b_value - a_value);
+                            (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+                        };
+                        let mut clipped = vec![];
+                        for (i, &point) in path.iter().enumerate() {
+                            let previous = path[(i + path.len() - 1) % path.len()];
+                            match (inside(previous), inside(point)) {
+                                (true, true) => clipped.push(point),
+                                (true, false) => clipped.push(crossing(previous, point)),
+                                (false, true) => {
+                                    clipped.push(crossing(previous, point));
+                                    clipped.push(point);
+                                }
+                                (false, false) => {}
+                            }
+                        }
+                        clipped
+                    })
+            })
+            .filter(|path| path.len() >= 3)
+            .collect();
+        Shape {
+            color: self.color,
+            opacity: self.opacity,
+            paths,
+        }
+    }
+}
+
+/// A resolution independent image, drawn shape by shape from the back. Anything outside of
+/// `width` x `height` is clipped by the encoders.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VectorImage {
+    pub width: f64,
+    pub height: f64,
+    /// Hints that the shapes are aligned to whole units and should not be anti-aliased.
+    pub crisp_edges: bool,
+    pub shapes: Vec<Shape>,
+}
+impl VectorImage {
+    /// Traces a raster image with one unit per pixel. Pixels of the same color are merged into
+    /// horizontal runs and fully transparent pixels are left out.
+    pub fn from_image(image: &Image) -> VectorImage {
+        let mut shapes: Vec<([u8; 4], Shape)> = vec![];
+        for y in 0..image.height {
+            let row =
+                &image.pixels[y * image.width * image.channels..][..image.width * image.channels];
+            let mut pixels = row.chunks_exact(image.channels).map(|p| rgba(p, u8::MAX));
+            let mut x = 0;
+            let Some(mut run_color) = pixels.next() else {
+                continue;
+            };
+            let mut run_start = 0;
+            loop {
+                let pixel = pixels.next();
+                x += 1;
+                if pixel == Some(run_color) {
+                    continue;
+                }
+                if run_color[3] != 0 {
+                    let index = match shapes.iter().position(|(color, _)| *color == run_color) {
+                        Some(index) => index,
+                        None => {
+                            let [r, g, b, a] = run_color;
+                            let mut shape = Shape::new(Color::new_u8(r, g, b));
+                            shape.opacity = a as f64 / 255.0;
+                            shapes.push((run_color, shape));
+                            shapes.len() - 1
+                        }
+                    };
+                    let width = (x - run_start) as f64;
+                    shapes[index]
+                        .1
+                        .add_rect(run_start as f64, y as f64, width, 1.0);
+                }
+                match pixel {
+                    Some(color) => {
+                        run_color = color;
+                        run_start = x;
+                    }
+                    None => break,
+                }
+            }
+        }
+        VectorImage {
+            width: image.width as f64,
+            height: image.height as f64,
+            crisp_edges: true,
+            shapes: shapes.into_iter().map(|(_, shape)| shape).collect(),
+        }
+    }
+}
+
+/// Formats a coordinate with at most three decimals and no trailing zeros.
+pub(crate) fn number(value: f64) -> String {
+    let formatted = format!("{value:.3}");
+    let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+    match trimmed {
+        "-0" | "" => "0".to_string(),
+        _ => trimmed.to_string(),
+    }
+}
@@ -1,3 +1,6 @@
+// The parsed flags are compared to their expected values, booleans included.
+#![allow(clippy::bool_assert_comparison)]
+
 use lifehash_lib::{run, Either, Version};
 use std::path::PathBuf;
 
@@ -227,3 +230,1228 @@ fn test_parse_cli_from_file() {
         }
     }
 }
+
+#[test]
+fn test_parse_cli_stdout() {
+    let args = vec!["lifehash", "--hex", "010203", "-o", "-", "--force"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert!(lifehash_lib::is_stdout(&runtime_args.output_file));
+    assert!(runtime_args.force);
+    let args = vec!["lifehash", "--hex", "010203"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert!(!lifehash_lib::is_stdout(&runtime_args.output_file));
+    assert!(!runtime_args.force);
+}
+
+#[test]
+fn test_parse_cli_bit_depth() {
+    let args = vec!["lifehash", "--hex", "010203", "--bit-depth", "16"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.bit_depth, 16);
+    let args = vec!["lifehash", "--hex", "010203", "--bit-depth", "12"];
+    assert!(lifehash_lib::parse_cli_from(args).is_err());
+}
+
+#[test]
+fn test_parse_cli_png_options() {
+    use lifehash_lib::encoders::png::{PngCompression, PngFilter};
+    let args = vec![
+        "lifehash",
+        "--hex",
+        "010203",
+        "--palette",
+        "--compression",
+        "best",
+        "--filter",
+        "paeth",
+    ];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert!(runtime_args.png_options.palette);
+    assert_eq!(runtime_args.png_options.compression, PngCompression::Best);
+    assert_eq!(runtime_args.png_options.filter, PngFilter::Paeth);
+}
+
+#[test]
+fn test_parse_cli_format() {
+    use lifehash_lib::encoders::ImageFormat;
+    let args = vec!["lifehash", "--hex", "010203", "-o", "lifehash.webp"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.format, ImageFormat::Webp);
+    let args = vec!["lifehash", "--hex", "010203", "-o", "-", "--format", "qoi"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.format, ImageFormat::Qoi);
+    let args = vec!["lifehash", "--hex", "010203", "-o", "-"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.format, ImageFormat::Png);
+}
+
+#[test]
+fn test_run_format() {
+    use tempfile::tempdir;
+    let dir = tempdir().expect("failed to create temp dir");
+    let output = dir.path().join("lifehash.bmp");
+    let args = vec![
+        "lifehash",
+        "--hex",
+        "185f8db32271fe25f561a6fc938b2e264306ec304eda518007d1764826381969",
+        "-o",
+        output.to_str().expect("failed to convert to str"),
+    ];
+    run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).unwrap();
+    let written = std::fs::read(output).unwrap();
+    assert_eq!(written[0..2], *b"BM");
+}
+
+#[test]
+fn test_parse_cli_icons() {
+    use lifehash_lib::encoders::ImageFormat;
+    use lifehash_lib::icons::ICO_SIZES;
+    let args = vec!["lifehash", "--hex", "010203", "-o", "favicon.ico"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.format, ImageFormat::Ico);
+    assert_eq!(runtime_args.icon_sizes, ICO_SIZES.to_vec());
+    let args = vec![
+        "lifehash",
+        "--hex",
+        "010203",
+        "--icon-sizes",
+        "16,32",
+        "--icon-set",
+        "icons",
+        "--icon-base-size",
+        "40",
+    ];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.icon_sizes, vec![16, 32]);
+    assert_eq!(runtime_args.icon_set, Some(PathBuf::from("icons")));
+    assert_eq!(runtime_args.icon_base_size, 40);
+}
+
+#[test]
+fn test_parse_cli_size() {
+    use lifehash_lib::Fit;
+    let args = vec![
+        "lifehash",
+        "--hex",
+        "010203",
+        "--size",
+        "150",
+        "--fit",
+        "letterbox",
+        "--module-size",
+        "300",
+    ];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.size, Some(150));
+    assert_eq!(runtime_args.fit, Fit::Letterbox);
+    assert_eq!(runtime_args.module_size, 300);
+    let args = vec!["lifehash", "--hex", "010203"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.size, None);
+    assert_eq!(runtime_args.fit, Fit::Distribute);
+}
+
+#[test]
+fn test_parse_cli_smooth() {
+    use lifehash_lib::grids::frac_grid::Interpolation;
+    let args = vec!["lifehash", "--hex", "010203", "--smooth", "bilinear"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.smooth, Some(Interpolation::Bilinear));
+    let args = vec!["lifehash", "--hex", "010203"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.smooth, None);
+    let args = vec![
+        "lifehash",
+        "--hex",
+        "010203",
+        "--smooth",
+        "bilinear",
+        "-m",
+        "18446744073709551615",
+        "-o",
+        "-",
+    ];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert!(run(runtime_args).is_err());
+}
+
+#[test]
+fn test_parse_cli_tile() {
+    let args = vec![
+        "lifehash",
+        "--hex",
+        "010203",
+        "--tile",
+        "1920x1080",
+        "--tile-offset",
+        "16,32",
+    ];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    let tile = runtime_args.tile.expect("expected tile dimensions");
+    assert_eq!((tile.width, tile.height), (1920, 1080));
+    assert_eq!(runtime_args.tile_offset, (16, 32));
+}
+
+#[test]
+fn test_parse_cli_contour() {
+    use lifehash_lib::encoders::ImageFormat;
+    let args = vec![
+        "lifehash",
+        "--hex",
+        "010203",
+        "--contour",
+        "--contour-levels",
+        "12",
+        "-o",
+        "poster.svg",
+    ];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert!(runtime_args.contour);
+    assert_eq!(runtime_args.contour_levels, Some(12));
+    assert_eq!(runtime_args.format, ImageFormat::Svg);
+    let args = vec!["lifehash", "--hex", "010203", "-o", "poster.pdf"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert!(!runtime_args.contour);
+    assert_eq!(runtime_args.format, ImageFormat::Pdf);
+}
+
+#[test]
+fn test_run_contour() {
+    use tempfile::tempdir;
+    let dir = tempdir().expect("failed to create temp dir");
+    let output = dir.path().join("lifehash.svg");
+    let hex = "185f8db32271fe25f561a6fc938b2e264306ec304eda518007d1764826381969";
+    let args = vec![
+        "lifehash",
+        "--hex",
+        hex,
+        "--contour",
+        "--size",
+        "500",
+        "-o",
+        output.to_str().expect("failed to convert to str"),
+    ];
+    run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).unwrap();
+    let written = std::fs::read_to_string(&output).unwrap();
+    assert!(
+        written.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="500" height="500""#)
+    );
+    let png = dir.path().join("lifehash.png");
+    let args = vec![
+        "lifehash",
+        "--hex",
+        hex,
+        "--contour",
+        "-o",
+        png.to_str().expect("failed to convert to str"),
+    ];
+    assert!(run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).is_err());
+    assert!(!png.exists());
+}
+
+#[test]
+fn test_parse_cli_mask() {
+    use lifehash_lib::render::mask::{Mask, MaskShape};
+    let args = vec![
+        "lifehash",
+        "--hex",
+        "010203",
+        "--mask",
+        "rounded",
+        "--mask-radius",
+        "0.25",
+        "--margin",
+        "8",
+    ];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(
+        runtime_args.mask,
+        Mask {
+            shape: Some(MaskShape::Rounded),
+            radius: 0.25,
+            margin: 8,
+        }
+    );
+    let args = vec!["lifehash", "--hex", "010203"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert!(runtime_args.mask.is_empty());
+    for (option, value) in [
+        ("--mask-radius", "NaN"),
+        ("--mask-radius", "1.5"),
+        ("--min-opacity", "nan"),
+        ("--max-opacity", "inf"),
+        ("--max-opacity", "2"),
+    ] {
+        let args = vec![
+            "lifehash", "--hex", "010203", "--mask", "rounded", option, value,
+        ];
+        assert!(
+            lifehash_lib::parse_cli_from(args).is_err(),
+            "{option} {value}"
+        );
+    }
+}
+
+#[test]
+fn test_parse_cli_frame() {
+    let args = vec![
+        "lifehash",
+        "--hex",
+        "010203",
+        "--quiet-zone",
+        "4",
+        "--border",
+        "1",
+    ];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.frame.quiet_zone, 4);
+    assert_eq!(runtime_args.frame.border, 1);
+    let args = vec!["lifehash", "--hex", "010203"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert!(runtime_args.frame.is_empty());
+}
+
+#[test]
+fn test_parse_cli_overlay() {
+    use lifehash_lib::render::overlay::{OpacityCurve, Overlay};
+    let args = vec![
+        "lifehash",
+        "--hex",
+        "010203",
+        "--overlay",
+        "--overlay-curve",
+        "sqrt",
+        "--max-opacity",
+        "0.6",
+    ];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(
+        runtime_args.overlay,
+        Some(Overlay {
+            curve: OpacityCurve::Sqrt,
+            min_opacity: 0.0,
+            max_opacity: 0.6,
+        })
+    );
+    let args = vec!["lifehash", "--hex", "010203", "--max-opacity", "0.6"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.overlay, None);
+}
+
+#[test]
+fn test_parse_cli_stamp() {
+    use lifehash_lib::stamp::{Anchor, StampOptions};
+    let args = vec![
+        "lifehash",
+        "--hex",
+        "010203",
+        "--stamp",
+        "photo.png",
+        "--anchor",
+        "top-left",
+        "--stamp-border",
+        "2",
+        "--shadow",
+        "4",
+    ];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.stamp, Some(PathBuf::from("photo.png")));
+    assert_eq!(
+        runtime_args.stamp_options,
+        StampOptions {
+            anchor: Anchor::TopLeft,
+            margin: 16,
+            border: 2,
+            shadow: 4,
+        }
+    );
+    let args = vec!["lifehash", "--hex", "010203"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.stamp, None);
+    assert_eq!(runtime_args.stamp_options.anchor, Anchor::BottomRight);
+}
+
+#[test]
+fn test_run_stamp() {
+    use lifehash_lib::encoders::png::decode_png;
+    use lifehash_lib::{save_image, Image};
+    use tempfile::tempdir;
+    let dir = tempdir().expect("failed to create temp dir");
+    let base = dir.path().join("base.png");
+    let image = Image {
+        width: 100,
+        height: 80,
+        channels: 4,
+        pixels: vec![0u8; 100 * 80 * 4],
+    };
+    save_image(&image, &base).unwrap();
+    let hex = "185f8db32271fe25f561a6fc938b2e264306ec304eda518007d1764826381969";
+    let base_str = base.to_str().expect("failed to convert to str");
+    // Writing over the base image stamps it in place.
+    let args = vec![
+        "lifehash", "--hex", hex, "--stamp", base_str, "--size", "20", "-o", base_str,
+    ];
+    run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).unwrap();
+    let stamped = decode_png::<u8, _>(std::fs::File::open(&base).unwrap()).unwrap();
+    assert_eq!(
+        (stamped.width, stamped.height, stamped.channels),
+        (100, 80, 4)
+    );
+    assert_eq!(stamped.pixels[(50 * 100 + 70) * 4 + 3], 255);
+    assert_eq!(stamped.pixels[(70 * 100 + 70) * 4 + 3], 0);
+    let svg = dir.path().join("stamped.svg");
+    let args = vec![
+        "lifehash",
+        "--hex",
+        hex,
+        "--stamp",
+        base_str,
+        "-o",
+        svg.to_str().expect("failed to convert to str"),
+    ];
+    assert!(run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).is_err());
+    assert!(!svg.exists());
+}
+
+#[test]
+fn test_parse_cli_card() {
+    use lifehash_lib::render::card::Card;
+    let args = vec!["lifehash", "--hex", "010203", "--card"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.card, Some(Card::default()));
+    let args = vec!["lifehash", "--hex", "010203", "--label", "Alice"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(
+        runtime_args.card,
+        Some(Card {
+            label: Some("Alice".to_string()),
+        })
+    );
+    let args = vec!["lifehash", "--hex", "010203"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.card, None);
+}
+
+#[test]
+fn test_parse_cli_sheet() {
+    use lifehash_lib::colors::rgb::Color;
+    use lifehash_lib::render::sheet::{Sheet, SheetInput};
+    let args = vec![
+        "lifehash",
+        "--hex",
+        "010203",
+        "--sheet",
+        "hex",
+        "--columns",
+        "6",
+        "--spacing",
+        "2",
+        "--background",
+        "#102030",
+    ];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.sheet, Some(SheetInput::Hex));
+    assert_eq!(
+        runtime_args.sheet_options,
+        Sheet {
+            columns: Some(6),
+            spacing: 2,
+            background: Color::new_u8(0x10, 0x20, 0x30),
+        }
+    );
+    let args = vec![
+        "lifehash",
+        "--hex",
+        "010203",
+        "--sheet",
+        "hex",
+        "--columns",
+        "0",
+    ];
+    assert!(lifehash_lib::parse_cli_from(args).is_err());
+}
+
+#[test]
+fn test_run_sheet() {
+    use lifehash_lib::encoders::png::decode_png;
+    use tempfile::tempdir;
+    let dir = tempdir().expect("failed to create temp dir");
+    let input = dir.path().join("keys.txt");
+    let hex = "185f8db32271fe25f561a6fc938b2e264306ec304eda518007d1764826381969";
+    std::fs::write(&input, format!("{hex}\n\n{hex}\n{hex}\n")).unwrap();
+    let output = dir.path().join("sheet.png");
+    let args = vec![
+        "lifehash",
+        "-i",
+        input.to_str().expect("failed to convert to str"),
+        "--sheet",
+        "hex",
+        "--columns",
+        "2",
+        "-o",
+        output.to_str().expect("failed to convert to str"),
+    ];
+    run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).unwrap();
+    let sheet = decode_png::<u8, _>(std::fs::File::open(&output).unwrap()).unwrap();
+    assert_eq!((sheet.width, sheet.height), (2 * 40 + 8, 2 * 40 + 8));
+    let svg = dir.path().join("sheet.svg");
+    let args = vec![
+        "lifehash",
+        "-i",
+        input.to_str().expect("failed to convert to str"),
+        "--sheet",
+        "hex",
+        "--contour",
+        "-o",
+        svg.to_str().expect("failed to convert to str"),
+    ];
+    run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).unwrap();
+    assert!(std::fs::read_to_string(&svg)
+        .unwrap()
+        .starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="88" height="88""#));
+    let args = vec![
+        "lifehash",
+        "--hex",
+        hex,
+        "--sheet",
+        "hex",
+        "-o",
+        output.to_str().expect("failed to convert to str"),
+    ];
+    assert!(run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).is_err());
+}
+
+#[test]
+fn test_parse_cli_compare() {
+    use lifehash_lib::render::compare::CompareWith;
+    let args = vec![
+        "lifehash",
+        "--hex",
+        "010203",
+        "--compare-hex",
+        "0a0b",
+        "--compare-version",
+        "3",
+    ];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(
+        runtime_args.compare,
+        Some(CompareWith {
+            digest: Some(vec![10, 11]),
+            version: Some(Version::Detailed),
+        })
+    );
+    let args = vec!["lifehash", "--hex", "010203", "--compare-version", "1"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(
+        runtime_args.compare,
+        Some(CompareWith {
+            digest: None,
+            version: Some(Version::Version1),
+        })
+    );
+    let args = vec!["lifehash", "--hex", "010203", "--compare-hex", "xyz"];
+    assert!(lifehash_lib::parse_cli_from(args).is_err());
+    let args = vec!["lifehash", "--hex", "010203"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.compare, None);
+}
+
+#[test]
+fn test_run_compare() {
+    use lifehash_lib::encoders::png::decode_png;
+    use tempfile::tempdir;
+    let dir = tempdir().expect("failed to create temp dir");
+    let output = dir.path().join("compare.png");
+    let hex = "185f8db32271fe25f561a6fc938b2e264306ec304eda518007d1764826381969";
+    let args = vec![
+        "lifehash",
+        "--hex",
+        hex,
+        "--compare-version",
+        "3",
+        "-m",
+        "2",
+        "-o",
+        output.to_str().expect("failed to convert to str"),
+    ];
+    run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).unwrap();
+    let image = decode_png::<u8, _>(std::fs::File::open(&output).unwrap()).unwrap();
+    // Three panels at the size of the detailed version, with the default spacing.
+    assert_eq!((image.width, image.height), (3 * 128 + 4 * 8, 128 + 2 * 8));
+    let args = vec![
+        "lifehash",
+        "--hex",
+        hex,
+        "--compare-version",
+        "3",
+        "-m",
+        "18446744073709551615",
+        "-o",
+        "-",
+    ];
+    assert!(run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).is_err());
+}
+
+#[test]
+fn test_parse_cli_gallery() {
+    use lifehash_lib::gallery::GalleryImages;
+    use lifehash_lib::render::sheet::SheetInput;
+    let args = vec!["lifehash", "--hex", "010203", "--gallery", "text"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.gallery, Some(SheetInput::Text));
+    assert_eq!(runtime_args.gallery_images, GalleryImages::Embed);
+    let args = vec![
+        "lifehash",
+        "--hex",
+        "010203",
+        "--gallery",
+        "hex",
+        "--gallery-images",
+        "link",
+    ];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.gallery, Some(SheetInput::Hex));
+    assert_eq!(runtime_args.gallery_images, GalleryImages::Link);
+}
+
+#[test]
+fn test_run_gallery() {
+    use lifehash_lib::encoders::png::decode_png;
+    use tempfile::tempdir;
+    let dir = tempdir().expect("failed to create temp dir");
+    let input = dir.path().join("names.txt");
+    std::fs::write(&input, "alice\n\nbob\n").unwrap();
+    let output = dir.path().join("gallery.html");
+    let args = vec![
+        "lifehash",
+        "-i",
+        input.to_str().expect("failed to convert to str"),
+        "--gallery",
+        "text",
+        "-o",
+        output.to_str().expect("failed to convert to str"),
+    ];
+    run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).unwrap();
+    let html = std::fs::read_to_string(&output).unwrap();
+    assert_eq!(
+        html.matches(r#"<img src="data:image/png;base64,"#).count(),
+        2
+    );
+    assert!(html.contains(r#"data-name="alice""#));
+    assert!(html.contains(r#"data-name="bob""#));
+
+    let output = dir.path().join("linked.html");
+    let args = vec![
+        "lifehash",
+        "-i",
+        input.to_str().expect("failed to convert to str"),
+        "--gallery",
+        "text",
+        "--gallery-images",
+        "link",
+        "-o",
+        output.to_str().expect("failed to convert to str"),
+    ];
+    run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).unwrap();
+    let html = std::fs::read_to_string(&output).unwrap();
+    assert!(html.contains(r#"<img src="linked-0001.png""#));
+    assert!(html.contains(r#"<img src="linked-0002.png""#));
+    let image =
+        decode_png::<u8, _>(std::fs::File::open(dir.path().join("linked-0002.png")).unwrap())
+            .unwrap();
+    assert_eq!((image.width, image.height), (32, 32));
+
+    let args = vec![
+        "lifehash",
+        "-i",
+        input.to_str().expect("failed to convert to str"),
+        "--gallery",
+        "text",
+        "--gallery-images",
+        "link",
+        "-o",
+        "-",
+    ];
+    assert!(run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).is_err());
+}
+
+#[test]
+fn test_parse_cli_data_uri() {
+    let args = vec!["lifehash", "--hex", "010203"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert!(!runtime_args.data_uri);
+    let args = vec!["lifehash", "--hex", "010203", "--data-uri"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert!(runtime_args.data_uri);
+}
+
+#[test]
+fn test_run_data_uri() {
+    use lifehash_lib::encoders::png::encode_png_to_data_uri;
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::Version;
+    use tempfile::tempdir;
+    let dir = tempdir().expect("failed to create temp dir");
+    let hex = "185f8db32271fe25f561a6fc938b2e264306ec304eda518007d1764826381969";
+    let output = dir.path().join("lifehash.txt");
+    let args = vec![
+        "lifehash",
+        "--hex",
+        hex,
+        "--data-uri",
+        "--format",
+        "png",
+        "-o",
+        output.to_str().expect("failed to convert to str"),
+    ];
+    run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).unwrap();
+    let lifehash = LifeHash::from_digest(&hex::decode(hex).unwrap(), Version::Version2).unwrap();
+    let expected = encode_png_to_data_uri(&lifehash.image(1, false).unwrap()).unwrap();
+    assert_eq!(std::fs::read_to_string(&output).unwrap(), expected + "\n");
+
+    let args = vec![
+        "lifehash",
+        "--hex",
+        hex,
+        "--contour",
+        "--data-uri",
+        "--format",
+        "svg",
+        "-o",
+        output.to_str().expect("failed to convert to str"),
+    ];
+    run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).unwrap();
+    assert!(std::fs::read_to_string(&output)
+        .unwrap()
+        .starts_with("data:image/svg+xml;base64,PHN2Zy"));
+}
+
+#[test]
+fn test_parse_cli_mesh() {
+    use lifehash_lib::encoders::MeshFormat;
+    use lifehash_lib::mesh::ReliefOptions;
+    let args = vec!["lifehash", "--hex", "010203", "-o", "token.ply"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.mesh, Some(MeshFormat::Ply));
+    assert_eq!(runtime_args.relief, ReliefOptions::default());
+    let args = vec![
+        "lifehash",
+        "--hex",
+        "010203",
+        "--mesh",
+        "obj",
+        "--relief-module",
+        "0.5",
+        "--relief-base",
+        "1.5",
+        "--relief-height",
+        "4",
+        "--vertex-colors",
+    ];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.mesh, Some(MeshFormat::Obj));
+    assert_eq!(
+        runtime_args.relief,
+        ReliefOptions {
+            module_size: 0.5,
+            base: 1.5,
+            height: 4.0,
+            colors: true,
+        }
+    );
+    let args = vec!["lifehash", "--hex", "010203"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.mesh, None);
+}
+
+#[test]
+fn test_run_mesh() {
+    use tempfile::tempdir;
+    let dir = tempdir().expect("failed to create temp dir");
+    let hex = "185f8db32271fe25f561a6fc938b2e264306ec304eda518007d1764826381969";
+    let output = dir.path().join("token.stl");
+    let args = vec![
+        "lifehash",
+        "--hex",
+        hex,
+        "-o",
+        output.to_str().expect("failed to convert to str"),
+    ];
+    run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).unwrap();
+    let stl = std::fs::read(&output).unwrap();
+    let triangles = u32::from_le_bytes(stl[80..84].try_into().unwrap()) as usize;
+    assert!(triangles > 0);
+    assert_eq!(stl.len(), 84 + 50 * triangles);
+    let args = vec![
+        "lifehash",
+        "--hex",
+        hex,
+        "--vertex-colors",
+        "-o",
+        output.to_str().expect("failed to convert to str"),
+    ];
+    assert!(run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).is_err());
+}
+
+#[test]
+fn test_parse_cli_export() {
+    use lifehash_lib::encoders::DataFormat;
+    use lifehash_lib::grids::array::GridData;
+    let args = vec![
+        "lifehash", "--hex", "010203", "--export", "frac", "-o", "frac.npy",
+    ];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.export, Some(GridData::Frac));
+    assert_eq!(runtime_args.data_format, DataFormat::Npy);
+    let args = vec![
+        "lifehash",
+        "--hex",
+        "010203",
+        "--export",
+        "history",
+        "--data-format",
+        "json",
+    ];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.export, Some(GridData::History));
+    assert_eq!(runtime_args.data_format, DataFormat::Json);
+    let args = vec!["lifehash", "--hex", "010203", "--export", "color"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.data_format, DataFormat::Csv);
+}
+
+#[test]
+fn test_run_export() {
+    use tempfile::tempdir;
+    let dir = tempdir().expect("failed to create temp dir");
+    let hex = "185f8db32271fe25f561a6fc938b2e264306ec304eda518007d1764826381969";
+    let output = dir.path().join("colors.csv");
+    let args = vec![
+        "lifehash",
+        "--hex",
+        hex,
+        "--export",
+        "color",
+        "-o",
+        output.to_str().expect("failed to convert to str"),
+    ];
+    run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).unwrap();
+    let csv = std::fs::read_to_string(&output).unwrap();
+    assert!(csv.starts_with("y,x,r,g,b\n0,0,"));
+    assert_eq!(csv.lines().count(), 1 + 32 * 32);
+    let output = dir.path().join("frac.npy");
+    let args = vec![
+        "lifehash",
+        "--hex",
+        hex,
+        "--export",
+        "frac",
+        "-o",
+        output.to_str().expect("failed to convert to str"),
+    ];
+    run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).unwrap();
+    let npy = std::fs::read(&output).unwrap();
+    assert!(npy.starts_with(b"\x93NUMPY"));
+    assert_eq!(npy.len(), 128 + 16 * 16 * 8);
+}
+
+#[test]
+fn test_parse_cli_life() {
+    use lifehash_lib::encoders::LifeFormat;
+    let args = vec!["lifehash", "--hex", "010203", "-o", "seed.rle"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.life, Some(LifeFormat::Rle));
+    assert_eq!(runtime_args.generation, 0);
+    let args = vec![
+        "lifehash",
+        "--hex",
+        "010203",
+        "--life",
+        "life106",
+        "--generation",
+        "12",
+    ];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.life, Some(LifeFormat::Life106));
+    assert_eq!(runtime_args.generation, 12);
+}
+
+#[test]
+fn test_run_life() {
+    use lifehash_lib::encoders::rle::decode_rle;
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::Version;
+    use tempfile::tempdir;
+    let dir = tempdir().expect("failed to create temp dir");
+    let hex = "185f8db32271fe25f561a6fc938b2e264306ec304eda518007d1764826381969";
+    let output = dir.path().join("generation.rle");
+    let args = vec![
+        "lifehash",
+        "--hex",
+        hex,
+        "--generation",
+        "3",
+        "-o",
+        output.to_str().expect("failed to convert to str"),
+    ];
+    run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).unwrap();
+    let text = std::fs::read_to_string(&output).unwrap();
+    assert!(text.starts_with(&format!("#C LifeHash Version2 of digest {hex}\n")));
+    let lifehash = LifeHash::from_digest(&hex::decode(hex).unwrap(), Version::Version2).unwrap();
+    let decoded = decode_rle(text.as_bytes()).unwrap();
+    assert_eq!(decoded.storage, lifehash.generation(3).unwrap().storage);
+    let args = vec![
+        "lifehash",
+        "--hex",
+        hex,
+        "--generation",
+        "100000",
+        "-o",
+        output.to_str().expect("failed to convert to str"),
+    ];
+    assert!(run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).is_err());
+}
+
+#[test]
+fn test_parse_cli_print() {
+    use lifehash_lib::encoders::print::PrintOptions;
+    let args = vec![
+        "lifehash",
+        "--hex",
+        "010203",
+        "--print-size",
+        "1in",
+        "--caption",
+        "Vault key 1",
+        "--crop-marks",
+        "-o",
+        "backup.pdf",
+    ];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(
+        runtime_args.print,
+        Some(PrintOptions {
+            size: 72.0,
+            caption: Some("Vault key 1".to_string()),
+            crop_marks: true,
+            cmyk: None,
+        })
+    );
+    let args = vec!["lifehash", "--hex", "010203"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.print, None);
+    let args = vec!["lifehash", "--hex", "010203", "--crop-marks"];
+    assert!(lifehash_lib::parse_cli_from(args).is_err());
+}
+
+#[test]
+fn test_run_print() {
+    use tempfile::tempdir;
+    let dir = tempdir().expect("failed to create temp dir");
+    let hex = "185f8db32271fe25f561a6fc938b2e264306ec304eda518007d1764826381969";
+    let output = dir.path().join("backup.pdf");
+    let args = vec![
+        "lifehash",
+        "--hex",
+        hex,
+        "--print-size",
+        "40mm",
+        "--caption",
+        "Vault key 1",
+        "-o",
+        output.to_str().expect("failed to convert to str"),
+    ];
+    run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).unwrap();
+    let pdf = std::fs::read(&output).unwrap();
+    let text = String::from_utf8_lossy(&pdf);
+    assert!(text.contains("/TrimBox [0 16 113.386 129.386]"));
+    assert!(text.contains(&format!("/LifeHashDigest ({hex})")));
+    let png = dir.path().join("backup.png");
+    let args = vec![
+        "lifehash",
+        "--hex",
+        hex,
+        "--print-size",
+        "40mm",
+        "-o",
+        png.to_str().expect("failed to convert to str"),
+    ];
+    assert!(run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).is_err());
+}
+
+#[test]
+fn test_parse_cli_cmyk() {
+    use lifehash_lib::colors::cmyk::CmykProfile;
+    use lifehash_lib::encoders::ImageFormat;
+    let args = vec![
+        "lifehash",
+        "--hex",
+        "010203",
+        "--print-size",
+        "1in",
+        "--cmyk",
+        "--gamut-report",
+        "-o",
+        "backup.pdf",
+    ];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(
+        runtime_args.print.map(|print| print.cmyk),
+        Some(Some(CmykProfile::default()))
+    );
+    assert_eq!(runtime_args.gamut_report, Some(5.0));
+    let args = vec!["lifehash", "--hex", "010203", "--max-delta-e", "2.5"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.gamut_report, Some(2.5));
+    let args = vec!["lifehash", "--hex", "010203", "-o", "print.tiff"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.format, ImageFormat::Tiff);
+    assert_eq!(runtime_args.gamut_report, None);
+    let args = vec!["lifehash", "--hex", "010203", "--cmyk"];
+    assert!(lifehash_lib::parse_cli_from(args).is_err());
+}
+
+#[test]
+fn test_run_cmyk() {
+    use tempfile::tempdir;
+    let dir = tempdir().expect("failed to create temp dir");
+    let hex = "185f8db32271fe25f561a6fc938b2e264306ec304eda518007d1764826381969";
+    let output = dir.path().join("lifehash.tif");
+    let args = vec![
+        "lifehash",
+        "--hex",
+        hex,
+        "-m",
+        "2",
+        "--gamut-report",
+        "-o",
+        output.to_str().expect("failed to convert to str"),
+    ];
+    run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).unwrap();
+    let tiff = std::fs::read(&output).unwrap();
+    assert!(tiff.starts_with(b"II*\0"));
+    // 64 by 64 pixels of four inks after the header.
+    assert_eq!(tiff.len(), 206 + 64 * 64 * 4);
+    let output = dir.path().join("backup.pdf");
+    let args = vec![
+        "lifehash",
+        "--hex",
+        hex,
+        "--print-size",
+        "40mm",
+        "--cmyk",
+        "-o",
+        output.to_str().expect("failed to convert to str"),
+    ];
+    run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).unwrap();
+    let pdf = std::fs::read(&output).unwrap();
+    let text = String::from_utf8_lossy(&pdf);
+    assert!(text.contains(" k\n"));
+    assert!(!text.contains(" rg\n"));
+}
+
+#[test]
+fn test_parse_cli_dither() {
+    use lifehash_lib::encoders::ImageFormat;
+    use lifehash_lib::render::dither::Dither;
+    let args = vec![
+        "lifehash",
+        "--hex",
+        "010203",
+        "--dither",
+        "floyd-steinberg",
+        "-o",
+        "eink.pbm",
+    ];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.dither, Some(Dither::FloydSteinberg));
+    assert_eq!(runtime_args.format, ImageFormat::Pbm);
+    let args = vec!["lifehash", "--hex", "010203", "--format", "mono"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.dither, None);
+    assert_eq!(runtime_args.format, ImageFormat::Mono);
+}
+
+#[test]
+fn test_run_dither() {
+    use lifehash_lib::encoders::png::{decode_png, png_bit_depth};
+    use tempfile::tempdir;
+    let dir = tempdir().expect("failed to create temp dir");
+    let hex = "185f8db32271fe25f561a6fc938b2e264306ec304eda518007d1764826381969";
+    let output = dir.path().join("eink.png");
+    let args = vec![
+        "lifehash",
+        "--hex",
+        hex,
+        "-m",
+        "4",
+        "--dither",
+        "atkinson",
+        "-o",
+        output.to_str().expect("failed to convert to str"),
+    ];
+    run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).unwrap();
+    let png = std::fs::read(&output).unwrap();
+    assert_eq!(png_bit_depth(png.as_slice()).unwrap(), 1);
+    let image = decode_png::<u8, _>(png.as_slice()).unwrap();
+    assert_eq!((image.width, image.height), (128, 128));
+    assert!(image.pixels.iter().all(|p| *p == 0 || *p == 255));
+    let output = dir.path().join("thermal.bin");
+    let args = vec![
+        "lifehash",
+        "--hex",
+        hex,
+        "--dither",
+        "bayer",
+        "--format",
+        "mono",
+        "-o",
+        output.to_str().expect("failed to convert to str"),
+    ];
+    run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).unwrap();
+    // 32 rows of 4 bytes.
+    assert_eq!(std::fs::read(&output).unwrap().len(), 128);
+    // Options for the color image are rejected instead of silently dropped.
+    for option in [
+        &["--size", "100"][..],
+        &["--quiet-zone", "2"],
+        &["--mask", "circle"],
+        &["--card"],
+        &["--overlay"],
+        &["--bit-depth", "16"],
+    ] {
+        let mut args = vec!["lifehash", "--hex", hex, "--dither", "bayer", "-o", "-"];
+        args.extend_from_slice(option);
+        let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+        let error = run(runtime_args).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput, "{option:?}");
+    }
+}
+
+#[test]
+fn test_run_mode_options() {
+    use std::io::ErrorKind;
+    let hex = "185f8db32271fe25f561a6fc938b2e264306ec304eda518007d1764826381969";
+    // Modes that don't render the color image reject the options that decorate it.
+    for mode in [
+        &["--export", "frac", "--data-format", "json"][..],
+        &["--life", "rle"],
+        &["--mesh", "obj"],
+        &["--print-size", "40mm", "--format", "pdf"],
+        &["--dither", "bayer"],
+    ] {
+        for option in [&["--mask", "circle"][..], &["--card"], &["--border", "1"]] {
+            let mut args = vec!["lifehash", "--hex", hex, "-o", "-"];
+            args.extend_from_slice(mode);
+            args.extend_from_slice(option);
+            let runtime_args =
+                lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+            let error = run(runtime_args).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidInput, "{mode:?} {option:?}");
+        }
+    }
+    // Framebuffers are rendered like images, but in 8 bits.
+    let args = vec![
+        "lifehash",
+        "--hex",
+        hex,
+        "--pixel-format",
+        "rgb888",
+        "--bit-depth",
+        "16",
+        "-o",
+        "-",
+    ];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(
+        run(runtime_args).unwrap_err().kind(),
+        ErrorKind::InvalidInput
+    );
+}
+
+#[test]
+fn test_parse_cli_framebuffer() {
+    use lifehash_lib::encoders::framebuffer::{FramebufferOptions, PixelFormat};
+    let args = vec![
+        "lifehash",
+        "--hex",
+        "010203",
+        "--pixel-format",
+        "rgb565-be",
+        "--stride",
+        "128",
+    ];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(
+        runtime_args.framebuffer,
+        Some(FramebufferOptions {
+            format: PixelFormat::Rgb565Be,
+            stride: Some(128),
+        })
+    );
+    let args = vec!["lifehash", "--hex", "010203"];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(runtime_args.framebuffer, None);
+    let args = vec!["lifehash", "--hex", "010203", "--stride", "128"];
+    assert!(lifehash_lib::parse_cli_from(args).is_err());
+    for option in [&["--dither", "bayer"][..], &["--data-uri"]] {
+        let mut args = vec!["lifehash", "--hex", "010203", "--pixel-format", "rgb888"];
+        args.extend_from_slice(option);
+        assert!(lifehash_lib::parse_cli_from(args).is_err(), "{option:?}");
+    }
+}
+
+#[test]
+fn test_run_framebuffer() {
+    use tempfile::tempdir;
+    let dir = tempdir().expect("failed to create temp dir");
+    let hex = "185f8db32271fe25f561a6fc938b2e264306ec304eda518007d1764826381969";
+    let output = dir.path().join("lifehash.raw");
+    let args = vec![
+        "lifehash",
+        "--hex",
+        hex,
+        "-m",
+        "2",
+        "--pixel-format",
+        "argb8888",
+        "--stride",
+        "320",
+        "-o",
+        output.to_str().expect("failed to convert to str"),
+    ];
+    run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).unwrap();
+    let framebuffer = std::fs::read(&output).unwrap();
+    assert_eq!(framebuffer.len(), 64 * 320);
+    assert_eq!(framebuffer[0], 255);
+    assert!(framebuffer[256..320].iter().all(|b| *b == 0));
+    let args = vec![
+        "lifehash",
+        "--hex",
+        hex,
+        "--pixel-format",
+        "rgb888",
+        "--stride",
+        "10",
+        "-o",
+        output.to_str().expect("failed to convert to str"),
+    ];
+    assert!(run(lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args")).is_err());
+    let args = vec![
+        "lifehash",
+        "--hex",
+        hex,
+        "--pixel-format",
+        "rgb888",
+        "--stride",
+        "18446744073709551615",
+        "-o",
+        "-",
+    ];
+    let runtime_args = lifehash_lib::parse_cli_from(args).expect("Failed to parse CLI args");
+    assert_eq!(
+        run(runtime_args).unwrap_err().kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+}
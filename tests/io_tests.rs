@@ -23,3 +23,1908 @@ pub fn test_save_image_alpha() {
         std::process::exit(1);
     }
 }
+
+#[test]
+pub fn test_encode_png_to_vec() {
+    use lifehash_lib::encoders::png::encode_png_to_vec;
+    use lifehash_lib::lifehash::from_data;
+    use lifehash_lib::Version::*;
+    let (image, _) = from_data(b"Hello", Version2, 2, false).unwrap();
+    let encoded = encode_png_to_vec(&image).unwrap();
+    assert_eq!(encoded[0..8], [137, 80, 78, 71, 13, 10, 26, 10]);
+    let decoder = png::Decoder::new(encoded.as_slice());
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!(info.width, 64);
+    assert_eq!(info.height, 64);
+    assert_eq!(pixels[0..info.buffer_size()], image.pixels);
+}
+
+#[test]
+pub fn test_encode_png_to_writer() {
+    use lifehash_lib::encoders::png::{encode_png, encode_png_to_vec};
+    use lifehash_lib::lifehash::from_data;
+    use lifehash_lib::Version::*;
+    let (image, _) = from_data(b"Hello", Fiducial, 1, true).unwrap();
+    let mut cursor = std::io::Cursor::new(vec![]);
+    encode_png(&image, &mut cursor).unwrap();
+    assert_eq!(cursor.into_inner(), encode_png_to_vec(&image).unwrap());
+}
+
+#[test]
+pub fn test_encode_png_grayscale() {
+    use lifehash_lib::encoders::png::{encode_png_to_vec, encode_png_with, PngOptions};
+    use lifehash_lib::lifehash::from_data;
+    use lifehash_lib::Version::*;
+    let (image, _) = from_data(b"Hello", GrayscaleFiducial, 1, false).unwrap();
+    assert!(image.is_grayscale());
+    let encoded = encode_png_to_vec(&image).unwrap();
+    let reader = png::Decoder::new(encoded.as_slice()).read_info().unwrap();
+    assert_eq!(reader.info().color_type, png::ColorType::Grayscale);
+    let mut rgb = vec![];
+    let options = PngOptions {
+        reduce_grayscale: false,
+        ..PngOptions::default()
+    };
+    encode_png_with(&image, &mut rgb, &options).unwrap();
+    assert!(encoded.len() < rgb.len());
+    let (image, _) = from_data(b"Hello", Version2, 1, false).unwrap();
+    assert!(!image.is_grayscale());
+}
+
+#[test]
+pub fn test_encode_png_16_bit() {
+    use lifehash_lib::encoders::png::encode_png_to_vec;
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::Version::*;
+    let lifehash = LifeHash::from_data(b"Hello", Version2).unwrap();
+    let image = lifehash.image(1, false).unwrap();
+    let image16 = lifehash.image16(1, true).unwrap();
+    assert_eq!(image16.channels, 4);
+    assert_eq!(image16.pixels[3], u16::MAX);
+    assert!((image16.pixels[0] / 257).abs_diff(image.pixels[0] as u16) <= 1);
+    let encoded = encode_png_to_vec(&image16).unwrap();
+    let mut reader = png::Decoder::new(encoded.as_slice()).read_info().unwrap();
+    assert_eq!(reader.info().bit_depth, png::BitDepth::Sixteen);
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut pixels).unwrap();
+    assert_eq!(
+        u16::from_be_bytes([pixels[0], pixels[1]]),
+        image16.pixels[0]
+    );
+}
+
+#[test]
+pub fn test_encode_png_palette() {
+    use lifehash_lib::encoders::png::{encode_png_to_vec, PngOptions};
+    use lifehash_lib::encoders::png::{encode_png_with, IndexedImage, PngCompression, PngFilter};
+    use lifehash_lib::lifehash::from_data;
+    use lifehash_lib::Version::*;
+    let (image, _) = from_data(b"Hello", Version2, 4, true).unwrap();
+    let indexed = IndexedImage::from_image(&image).unwrap();
+    assert!(indexed.palette.len() <= 256);
+    let options = PngOptions {
+        palette: true,
+        compression: PngCompression::Best,
+        filter: PngFilter::Adaptive,
+        ..PngOptions::default()
+    };
+    let mut encoded = vec![];
+    encode_png_with(&image, &mut encoded, &options).unwrap();
+    assert!(encoded.len() < encode_png_to_vec(&image).unwrap().len());
+    let mut decoder = png::Decoder::new(encoded.as_slice());
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info().unwrap();
+    assert_eq!(reader.info().color_type, png::ColorType::Indexed);
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    // Fully opaque palettes are stored without transparency, the alpha channel is dropped.
+    assert_eq!(info.color_type, png::ColorType::Rgb);
+    let rgb: Vec<u8> = image
+        .pixels
+        .chunks_exact(4)
+        .flat_map(|p| p[0..3].to_vec())
+        .collect();
+    assert_eq!(pixels[0..info.buffer_size()], rgb);
+}
+
+#[test]
+pub fn test_indexed_image_packing() {
+    use lifehash_lib::encoders::png::IndexedImage;
+    use lifehash_lib::Image;
+    let image = Image {
+        width: 3,
+        height: 2,
+        channels: 1,
+        pixels: vec![0, 255, 0, 255, 255, 0],
+    };
+    let indexed = IndexedImage::from_image(&image).unwrap();
+    assert_eq!(indexed.palette, vec![vec![0], vec![255]]);
+    assert_eq!(indexed.bit_depth(), 1);
+    assert_eq!(indexed.packed_rows(1), vec![0b0100_0000, 0b1100_0000]);
+    let gradient = Image {
+        width: 300,
+        height: 1,
+        channels: 1,
+        pixels: (0..300).map(|i| (i % 256) as u8).collect(),
+    };
+    assert_eq!(
+        IndexedImage::from_image(&gradient).unwrap().palette.len(),
+        256
+    );
+    let colors = Image {
+        width: 257,
+        height: 1,
+        channels: 3,
+        pixels: (0..257u32)
+            .flat_map(|i| [(i % 256) as u8, (i / 256) as u8, 0])
+            .collect(),
+    };
+    assert!(IndexedImage::from_image(&colors).is_none());
+}
+
+#[test]
+pub fn test_image_format_from_path() {
+    use lifehash_lib::encoders::ImageFormat;
+    use std::path::Path;
+    assert_eq!(
+        ImageFormat::from_path(Path::new("a.png")),
+        Some(ImageFormat::Png)
+    );
+    assert_eq!(
+        ImageFormat::from_path(Path::new("a.PGM")),
+        Some(ImageFormat::Ppm)
+    );
+    assert_eq!(
+        ImageFormat::from_path(Path::new("a.bmp")),
+        Some(ImageFormat::Bmp)
+    );
+    assert_eq!(
+        ImageFormat::from_path(Path::new("a.qoi")),
+        Some(ImageFormat::Qoi)
+    );
+    assert_eq!(
+        ImageFormat::from_path(Path::new("a.ff")),
+        Some(ImageFormat::Farbfeld)
+    );
+    assert_eq!(
+        ImageFormat::from_path(Path::new("a.webp")),
+        Some(ImageFormat::Webp)
+    );
+    assert_eq!(ImageFormat::from_path(Path::new("a.txt")), None);
+    assert_eq!(ImageFormat::from_path(Path::new("-")), None);
+}
+
+#[test]
+pub fn test_encode_raster_formats() {
+    use lifehash_lib::encoders::png::PngOptions;
+    use lifehash_lib::encoders::ImageFormat;
+    use lifehash_lib::lifehash::from_data;
+    use lifehash_lib::Version::*;
+    let (image, _) = from_data(b"Hello", Version2, 1, false).unwrap();
+    let encode = |format: ImageFormat| {
+        let mut encoded = vec![];
+        format
+            .encoder(PngOptions::default())
+            .encode(&image, &mut encoded)
+            .unwrap();
+        encoded
+    };
+    let ppm = encode(ImageFormat::Ppm);
+    assert!(ppm.starts_with(b"P6\n32 32\n255\n"));
+    assert_eq!(ppm.len(), 13 + 32 * 32 * 3);
+    assert_eq!(ppm[13..16], image.pixels[0..3]);
+    let ascii = String::from_utf8(encode(ImageFormat::PpmAscii)).unwrap();
+    assert!(ascii.starts_with("P3\n32 32\n255\n146 126 130 178"));
+    assert!(ascii.lines().all(|line| line.len() <= 70));
+    let bmp = encode(ImageFormat::Bmp);
+    assert_eq!(bmp[0..2], *b"BM");
+    assert_eq!(bmp.len(), 54 + 32 * 32 * 3);
+    // Bottom-up BGR, the first pixel of the image is the first pixel of the last row.
+    let top_left = 54 + 31 * 32 * 3;
+    assert_eq!(bmp[top_left..top_left + 3], [130, 126, 146]);
+    let qoi = encode(ImageFormat::Qoi);
+    assert_eq!(qoi[0..14], *b"qoif\0\0\0\x20\0\0\0\x20\x03\0");
+    assert_eq!(qoi[qoi.len() - 8..], [0, 0, 0, 0, 0, 0, 0, 1]);
+    let farbfeld = encode(ImageFormat::Farbfeld);
+    assert_eq!(farbfeld[0..16], *b"farbfeld\0\0\0\x20\0\0\0\x20");
+    assert_eq!(farbfeld.len(), 16 + 32 * 32 * 8);
+    assert_eq!(farbfeld[16..24], [146, 146, 126, 126, 130, 130, 255, 255]);
+    let webp = encode(ImageFormat::Webp);
+    assert_eq!(webp[0..4], *b"RIFF");
+    assert_eq!(webp[8..16], *b"WEBPVP8L");
+    assert_eq!(
+        u32::from_le_bytes(webp[4..8].try_into().unwrap()) as usize,
+        webp.len() - 8
+    );
+    assert_eq!(webp[20], 0x2f);
+    // Repeated module pixels are coded as copies, so larger modules barely grow the file.
+    let (large, _) = from_data(b"Hello", Version2, 8, false).unwrap();
+    let mut large_webp = vec![];
+    ImageFormat::Webp
+        .encoder(PngOptions::default())
+        .encode(&large, &mut large_webp)
+        .unwrap();
+    assert!(large_webp.len() < 2 * webp.len(), "{}", large_webp.len());
+}
+
+#[test]
+pub fn test_encode_pgm_16_bit() {
+    use lifehash_lib::encoders::pnm::PnmEncoder;
+    use lifehash_lib::encoders::ImageEncoder;
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::Version::*;
+    let image = LifeHash::from_data(b"Hello", GrayscaleFiducial)
+        .unwrap()
+        .image16(1, true)
+        .unwrap();
+    let mut encoded = vec![];
+    PnmEncoder { ascii: false }
+        .encode16(&image, &mut encoded)
+        .unwrap();
+    assert!(encoded.starts_with(b"P5\n32 32\n65535\n"));
+    assert_eq!(encoded.len(), 15 + 32 * 32 * 2);
+    assert_eq!(encoded[15..17], image.pixels[0].to_be_bytes());
+}
+
+#[test]
+pub fn test_encode_ico() {
+    use lifehash_lib::encoders::ico::encode_ico;
+    use lifehash_lib::icons::{render_icon_sizes, ICO_SIZES};
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::Version::*;
+    let lifehash = LifeHash::from_data(b"Hello", Version2).unwrap();
+    let images = render_icon_sizes(&lifehash, &ICO_SIZES, false).unwrap();
+    assert_eq!(images[0].width, 16);
+    assert_eq!(images[5].height, 256);
+    let mut encoded = vec![];
+    encode_ico(&images, &mut encoded).unwrap();
+    assert_eq!(encoded[0..6], [0, 0, 1, 0, 6, 0]);
+    // The 256 pixel entry stores its size as 0.
+    assert_eq!(encoded[6 + 5 * 16..6 + 5 * 16 + 2], [0, 0]);
+    let offset = u32::from_le_bytes(encoded[18..22].try_into().unwrap()) as usize;
+    assert_eq!(offset, 6 + 6 * 16);
+    let reader = png::Decoder::new(&encoded[offset..]).read_info().unwrap();
+    assert_eq!(reader.info().width, 16);
+    assert_eq!(reader.info().color_type, png::ColorType::Rgba);
+    let too_large = render_icon_sizes(&lifehash, &[512], false).unwrap();
+    assert!(encode_ico(&too_large, &mut vec![]).is_err());
+}
+
+#[test]
+pub fn test_image_sized() {
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::Version::*;
+    let lifehash = LifeHash::from_data(b"Hello", Version2).unwrap();
+    let image = lifehash.image(1, false).unwrap();
+    assert_eq!(
+        lifehash.image_sized(32, false).unwrap().pixels,
+        image.pixels
+    );
+    let enlarged = lifehash.image_sized(64, true).unwrap();
+    assert_eq!(enlarged.pixels, lifehash.image(2, true).unwrap().pixels);
+    let shrunk = lifehash.image_sized(16, false).unwrap();
+    assert_eq!(shrunk.width, 16);
+    // Each pixel takes the top left cell of its 2x2 block, without blending.
+    for (x, y) in [(0, 0), (5, 9), (15, 15)] {
+        let cell = (y * 2 * 32 + x * 2) * 3;
+        assert_eq!(
+            shrunk.pixels[(y * 16 + x) * 3..][..3],
+            image.pixels[cell..cell + 3]
+        );
+    }
+    assert!(lifehash.image_sized(0, false).is_err());
+}
+
+#[test]
+pub fn test_export_icon_set() {
+    use lifehash_lib::icons::export_icon_set;
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::Version::*;
+    let dir = tempfile::tempdir().unwrap();
+    let lifehash = LifeHash::from_data(b"Hello", Version2).unwrap();
+    export_icon_set(&lifehash, dir.path(), "wallet", 48, true).unwrap();
+    for file in [
+        "wallet.png",
+        "wallet@2x.png",
+        "wallet@3x.png",
+        "wallet.imageset/wallet@3x.png",
+        "mipmap-hdpi/wallet.png",
+    ] {
+        assert!(dir.path().join(file).exists(), "{file} is missing");
+    }
+    let contents =
+        std::fs::read_to_string(dir.path().join("wallet.imageset/Contents.json")).unwrap();
+    assert!(contents.contains("\"filename\" : \"wallet@2x.png\""));
+    assert!(contents.contains("\"scale\" : \"3x\""));
+    let file = std::fs::File::open(dir.path().join("mipmap-xxxhdpi/wallet.png")).unwrap();
+    let reader = png::Decoder::new(file).read_info().unwrap();
+    assert_eq!(reader.info().width, 192);
+    assert!(export_icon_set(&lifehash, dir.path(), "My Wallet", 48, true).is_err());
+}
+
+#[test]
+pub fn test_image_fit() {
+    use lifehash_lib::lifehash::{LifeHash, MAX_IMAGE_SIZE};
+    use lifehash_lib::Fit;
+    use lifehash_lib::Version::*;
+    let lifehash = LifeHash::from_data(b"Hello", Version2).unwrap();
+    let cells = lifehash.image(1, false).unwrap();
+    let distributed = lifehash.image_fit(100, Fit::Distribute, false).unwrap();
+    assert_eq!(distributed.width, 100);
+    // Nearest-neighbor: every cell is 3 or 4 pixels wide, without blending.
+    for x in 0..100 {
+        let cell = x * 32 / 100;
+        assert_eq!(
+            distributed.pixels[x * 3..x * 3 + 3],
+            cells.pixels[cell * 3..cell * 3 + 3]
+        );
+    }
+    let letterboxed = lifehash.image_fit(100, Fit::Letterbox, true).unwrap();
+    // 3 pixel modules, centered with a 2 pixel transparent border.
+    assert_eq!(letterboxed.pixels[0..4], [0, 0, 0, 0]);
+    let inside = (2 * 100 + 2) * 4;
+    assert_eq!(letterboxed.pixels[inside..inside + 3], cells.pixels[0..3]);
+    assert_eq!(letterboxed.pixels[inside + 3], 255);
+    let last = (97 * 100 + 97) * 4;
+    assert_eq!(letterboxed.pixels[last + 3], 255);
+    assert_eq!(letterboxed.pixels[last + 7], 0);
+    let opaque = lifehash.image_fit(100, Fit::Letterbox, false).unwrap();
+    assert_eq!(opaque.pixels[0..3], [255, 255, 255]);
+    assert!(lifehash.image_fit(16, Fit::Letterbox, false).is_err());
+    // Below twice the grid the modules are one pixel, padded on every side.
+    for size in [34, 40, 63] {
+        let small = lifehash.image_fit(size, Fit::Letterbox, true).unwrap();
+        let offset = (size - 32) / 2;
+        assert_eq!(small.pixels[0..4], [0, 0, 0, 0]);
+        let inside = (offset * size + offset) * 4;
+        assert_eq!(small.pixels[inside..inside + 3], cells.pixels[0..3]);
+        assert_eq!(small.pixels[inside + 3], 255);
+        let after = ((offset + 31) * size + offset + 32) * 4;
+        assert_eq!(small.pixels[after + 3], 0);
+        assert_eq!(small.pixels[small.pixels.len() - 1], 0);
+    }
+    assert!(lifehash
+        .image_fit(MAX_IMAGE_SIZE + 1, Fit::Distribute, false)
+        .is_err());
+    let image16 = lifehash.image16_fit(150, Fit::Distribute, false).unwrap();
+    assert_eq!(image16.width, 150);
+    assert!(lifehash.image(MAX_IMAGE_SIZE / 32 + 1, false).is_err());
+    assert!(lifehash.image(usize::MAX, false).is_err());
+    assert!(lifehash.image16(usize::MAX, false).is_err());
+}
+
+#[test]
+pub fn test_smooth_image() {
+    use lifehash_lib::grids::frac_grid::Interpolation;
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::render::smooth::{smooth_image, smooth_image16};
+    use lifehash_lib::{Pattern, Version::*};
+    let mut patterns = vec![];
+    for (data, version) in [
+        (&b"Hello"[..], Version2),
+        (b"Hello Hello Hello", Version2),
+        (b"Hello", Detailed),
+        (b"Hello", Fiducial),
+    ] {
+        let lifehash = LifeHash::from_data(data, version).unwrap();
+        patterns.push(lifehash.pattern);
+        let cells = lifehash.image(1, false).unwrap();
+        // Sampling at the cell centers reproduces the cells, through every pattern transform.
+        for interpolation in [Interpolation::Bilinear, Interpolation::Bicubic] {
+            let smooth = smooth_image(&lifehash, cells.width, interpolation, false).unwrap();
+            assert_eq!(smooth.pixels, cells.pixels);
+        }
+        let large = smooth_image(&lifehash, 100, Interpolation::Bicubic, true).unwrap();
+        assert_eq!(large.width, 100);
+        assert_eq!(large.pixels[3], 255);
+    }
+    assert!(patterns.contains(&Pattern::Snowflake));
+    assert!(patterns.contains(&Pattern::Pinwheel));
+    assert!(patterns.contains(&Pattern::Fiducial));
+    let lifehash = LifeHash::from_data(b"Hello", Version2).unwrap();
+    let image16 = smooth_image16(&lifehash, 50, Interpolation::Bilinear, false).unwrap();
+    assert_eq!(image16.pixels.len(), 50 * 50 * 3);
+    assert!(smooth_image(&lifehash, 0, Interpolation::Bilinear, false).is_err());
+}
+
+#[test]
+pub fn test_frac_grid_sample_wraps() {
+    use lifehash_lib::grids::frac_grid::Interpolation;
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::Version::*;
+    let lifehash = LifeHash::from_data(b"Hello", Version2).unwrap();
+    let grid = &lifehash.frac_grid;
+    for interpolation in [Interpolation::Bilinear, Interpolation::Bicubic] {
+        for (x, y) in [(0.1, 3.3), (15.9, 7.0), (4.25, 0.0)] {
+            let value = grid.sample(x, y, interpolation);
+            assert!((value - grid.sample(x + 16.0, y - 16.0, interpolation)).abs() < 1e-9);
+            assert!((0.0..=1.0).contains(&value));
+        }
+    }
+}
+
+#[test]
+pub fn test_tile() {
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::render::tile::tile;
+    use lifehash_lib::Version::*;
+    let lifehash = LifeHash::from_data(b"Hello", Version2).unwrap();
+    let image = lifehash.image(2, false).unwrap();
+    let banner = tile(&image, 150, 40, 0, 0).unwrap();
+    assert_eq!((banner.width, banner.height), (150, 40));
+    let pixel = |image: &lifehash_lib::Image, x: usize, y: usize| {
+        image.pixels[(y * image.width + x) * 3..][..3].to_vec()
+    };
+    for (x, y) in [(0, 0), (70, 13), (149, 39)] {
+        assert_eq!(pixel(&banner, x, y), pixel(&image, x % 64, y % 64));
+    }
+    let shifted = tile(&image, 100, 100, 32, 10).unwrap();
+    assert_eq!(pixel(&shifted, 0, 0), pixel(&image, 32, 10));
+    assert_eq!(pixel(&shifted, 40, 60), pixel(&image, 8, 6));
+    // Offsets larger than the image wrap, usize::MAX is one short of a multiple of 64.
+    let wrapped = tile(&image, 64, 64, usize::MAX, 0).unwrap();
+    assert_eq!(pixel(&wrapped, 0, 0), pixel(&image, 63, 0));
+    assert_eq!(pixel(&wrapped, 1, 5), pixel(&image, 0, 5));
+    assert!(tile(&image, 0, 10, 0, 0).is_err());
+    let image16 = lifehash.image16(1, true).unwrap();
+    assert_eq!(tile(&image16, 10, 10, 0, 0).unwrap().channels, 4);
+}
+
+#[test]
+pub fn test_contour_image() {
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::render::contour::contour_image;
+    use lifehash_lib::Version::*;
+    for (data, version) in [
+        (&b"Hello"[..], Version2),
+        (b"Hello Hello Hello", Version2),
+        (b"Hello", Fiducial),
+    ] {
+        let lifehash = LifeHash::from_data(data, version).unwrap();
+        let image = contour_image(&lifehash, None).unwrap();
+        let size = lifehash.color_grid.dimensions.width as f64;
+        assert_eq!((image.width, image.height), (size, size));
+        assert_eq!(
+            image.shapes[0].paths,
+            vec![vec![(0.0, 0.0), (size, 0.0), (size, size), (0.0, size)]]
+        );
+        assert!(image.shapes.len() > 2);
+        for shape in &image.shapes {
+            assert!(shape.paths.iter().all(|path| path.len() >= 3));
+        }
+    }
+    // Snowflakes are mirrored, so every outline has a reflected twin.
+    let lifehash = LifeHash::from_data(b"Hello", Version2).unwrap();
+    let image = contour_image(&lifehash, Some(6)).unwrap();
+    assert!(image.shapes.len() <= 6);
+    for shape in &image.shapes[1..] {
+        let points: Vec<(f64, f64)> = shape.paths.iter().flatten().copied().collect();
+        for (x, y) in &points {
+            assert!(points
+                .iter()
+                .any(|(rx, ry)| (rx - (32.0 - x)).abs() < 1e-6 && (ry - y).abs() < 1e-6));
+        }
+    }
+    assert!(contour_image(&lifehash, Some(1)).is_err());
+}
+
+#[test]
+pub fn test_encode_vector() {
+    use lifehash_lib::encoders::pdf::encode_pdf;
+    use lifehash_lib::encoders::svg::encode_svg_to_string;
+    use lifehash_lib::encoders::ImageFormat;
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::vector::VectorImage;
+    use lifehash_lib::Image;
+    use lifehash_lib::Version::*;
+    let image = Image {
+        width: 3,
+        height: 2,
+        channels: 4,
+        pixels: vec![
+            255, 0, 0, 255, 255, 0, 0, 255, 0, 0, 255, 128, //
+            0, 0, 0, 0, 255, 0, 0, 255, 255, 0, 0, 255,
+        ],
+    };
+    let vector = VectorImage::from_image(&image);
+    assert_eq!(vector.shapes.len(), 2);
+    assert_eq!(vector.shapes[0].paths.len(), 2);
+    assert_eq!(
+        vector.shapes[1].paths,
+        vec![vec![(2.0, 0.0), (3.0, 0.0), (3.0, 1.0), (2.0, 1.0)]]
+    );
+    let svg = encode_svg_to_string(&vector, 10.0).unwrap();
+    assert!(svg.starts_with(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="30" height="20" viewBox="0 0 3 2""#
+    ));
+    assert!(svg.contains(
+        r##"<path fill="#ff0000" fill-rule="evenodd" d="M0 0L2 0L2 1L0 1ZM1 1L3 1L3 2L1 2Z"/>"##
+    ));
+    assert!(svg.contains(r#"fill-opacity="0.502""#));
+    assert!(svg.trim_end().ends_with("</svg>"));
+
+    let lifehash = LifeHash::from_data(b"Hello", Version2).unwrap();
+    let mut pdf = vec![];
+    let contours = lifehash_lib::render::contour::contour_image(&lifehash, None).unwrap();
+    encode_pdf(&contours, 10.0, &mut pdf).unwrap();
+    let text = String::from_utf8_lossy(&pdf);
+    assert!(text.starts_with("%PDF-1.4"));
+    assert!(text.contains("/MediaBox [0 0 320 320]"));
+    assert!(text.ends_with("%%EOF\n"));
+    // The cross-reference table points at every object.
+    let startxref: usize = text
+        .rsplit("startxref\n")
+        .next()
+        .unwrap()
+        .lines()
+        .next()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let xref = String::from_utf8_lossy(&pdf[startxref..]);
+    assert!(xref.starts_with("xref\n0 5\n"));
+    for (number, line) in xref.lines().skip(3).take(4).enumerate() {
+        let offset: usize = line[..10].parse().unwrap();
+        assert!(pdf[offset..].starts_with(format!("{} 0 obj", number + 1).as_bytes()));
+    }
+
+    let mut output = vec![];
+    ImageFormat::Svg
+        .encoder(Default::default())
+        .encode(&lifehash.image(1, false).unwrap(), &mut output)
+        .unwrap();
+    assert!(output.starts_with(b"<svg"));
+}
+
+#[test]
+pub fn test_mask_image() {
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::render::mask::{mask_image, mask_image16, Mask, MaskShape};
+    use lifehash_lib::Image;
+    use lifehash_lib::Version::*;
+    let lifehash = LifeHash::from_data(b"Hello", Version2).unwrap();
+    let image = lifehash.image(2, false).unwrap();
+    let alpha = |image: &Image, x: usize, y: usize| image.pixels[(y * image.width + x) * 4 + 3];
+    for shape in [MaskShape::Circle, MaskShape::Rounded, MaskShape::Squircle] {
+        let mask = Mask {
+            shape: Some(shape),
+            margin: 4,
+            ..Mask::default()
+        };
+        let masked = mask_image(&image, &mask).unwrap();
+        assert_eq!((masked.width, masked.height, masked.channels), (72, 72, 4));
+        // Transparent margin and corners, opaque center with the original colors.
+        assert_eq!(alpha(&masked, 0, 0), 0);
+        assert_eq!(alpha(&masked, 5, 5), 0);
+        assert_eq!(alpha(&masked, 36, 36), 255);
+        assert_eq!(
+            masked.pixels[(36 * 72 + 36) * 4..][..3],
+            image.pixels[(32 * 64 + 32) * 3..][..3]
+        );
+        // Anti-aliased edges have partial coverage.
+        assert!(masked.pixels.chunks(4).any(|p| (1..255).contains(&p[3])));
+    }
+    let circle = Mask {
+        shape: Some(MaskShape::Circle),
+        ..Mask::default()
+    };
+    let masked = mask_image(&image, &circle).unwrap();
+    assert_eq!(alpha(&masked, 32, 2), 255);
+    assert_eq!(alpha(&masked, 0, 0), 0);
+    let margin_only = Mask {
+        margin: 2,
+        ..Mask::default()
+    };
+    let framed = mask_image(&image, &margin_only).unwrap();
+    assert_eq!((alpha(&framed, 1, 1), alpha(&framed, 2, 2)), (0, 255));
+    let wide = Mask {
+        margin: usize::MAX / 2,
+        ..Mask::default()
+    };
+    assert!(mask_image(&image, &wide).is_err());
+    let masked16 = mask_image16(&lifehash.image16(1, true).unwrap(), &circle).unwrap();
+    assert_eq!(
+        (masked16.channels, masked16.pixels[16 * 4 * 32 + 16 * 4 + 3]),
+        (4, u16::MAX)
+    );
+    let gray = Image {
+        width: 2,
+        height: 2,
+        channels: 1,
+        pixels: vec![7u16; 4],
+    };
+    assert_eq!(mask_image16(&gray, &margin_only).unwrap().channels, 2);
+    assert!(Mask::default().is_empty());
+}
+
+#[test]
+pub fn test_frame() {
+    use lifehash_lib::colors::functions::ColorFunction;
+    use lifehash_lib::colors::{BLACK, WHITE};
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::render::contour::contour_image;
+    use lifehash_lib::render::frame::{frame_image, frame_image16, frame_vector, Frame};
+    use lifehash_lib::Version::*;
+    assert_eq!(ColorFunction::Blend(WHITE, WHITE).contrast_color(), BLACK);
+    assert_eq!(ColorFunction::Blend(BLACK, BLACK).contrast_color(), WHITE);
+    let reversed = ColorFunction::Reverse(Box::new(ColorFunction::Blend(BLACK, WHITE)));
+    assert_eq!(reversed.key_colors(), vec![WHITE, BLACK]);
+
+    let lifehash = LifeHash::from_data(b"Hello", Fiducial).unwrap();
+    let frame = Frame {
+        quiet_zone: 2,
+        border: 1,
+    };
+    let (border, quiet) = frame.colors(&lifehash.gradient);
+    assert_ne!(border, quiet);
+    assert!([BLACK, WHITE].contains(&border));
+    let image = lifehash.image(3, true).unwrap();
+    let framed = frame_image(&image, &lifehash, &frame).unwrap();
+    assert_eq!(
+        (framed.width, framed.height, framed.channels),
+        (114, 114, 4)
+    );
+    let pixel = |x: usize, y: usize| framed.pixels[(y * 114 + x) * 4..][..4].to_vec();
+    let rgba = |color: lifehash_lib::colors::rgb::Color| {
+        let channel = |c: f64| (c * 255.0) as u8;
+        vec![channel(color.r), channel(color.g), channel(color.b), 255]
+    };
+    assert_eq!(pixel(0, 0), rgba(quiet));
+    assert_eq!(pixel(5, 30), rgba(quiet));
+    assert_eq!(pixel(6, 30), rgba(border));
+    assert_eq!(pixel(106, 50), rgba(border));
+    assert_eq!(pixel(108, 50), rgba(quiet));
+    assert_eq!(pixel(9, 9), image.pixels[..4].to_vec());
+    let framed16 = frame_image16(&lifehash.image16(1, false).unwrap(), &lifehash, &frame).unwrap();
+    assert_eq!(framed16.width, 32 + 6);
+    for huge in [
+        Frame {
+            quiet_zone: usize::MAX,
+            border: 1,
+        },
+        Frame {
+            quiet_zone: 0,
+            border: usize::MAX / 2,
+        },
+        Frame {
+            quiet_zone: 8192,
+            border: 0,
+        },
+    ] {
+        assert!(frame_image(&image, &lifehash, &huge).is_err());
+    }
+
+    let contours = contour_image(&lifehash, Some(4)).unwrap();
+    let framed = frame_vector(&contours, &lifehash, &frame);
+    assert_eq!(framed.width, contours.width + 6.0);
+    assert_eq!(framed.shapes.len(), contours.shapes.len() + 2);
+    assert_eq!(framed.shapes[0].paths[0][0], (3.0, 3.0));
+    let ring = framed.shapes.last().unwrap();
+    assert_eq!(ring.color, border);
+    assert_eq!(ring.paths[1][0], (3.0, 3.0));
+}
+
+#[test]
+pub fn test_overlay() {
+    use lifehash_lib::grids::frac_grid::Interpolation;
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::render::overlay::{
+        apply_opacity, apply_opacity16, opacity_map, OpacityCurve, Overlay,
+    };
+    use lifehash_lib::render::smooth::smooth_image;
+    use lifehash_lib::Fit;
+    use lifehash_lib::Version::*;
+    let overlay = Overlay {
+        curve: OpacityCurve::Linear,
+        min_opacity: 0.2,
+        max_opacity: 0.8,
+    };
+    assert!((overlay.opacity(0.0) - 0.2).abs() < 1e-9);
+    assert!((overlay.opacity(0.5) - 0.5).abs() < 1e-9);
+    assert!((overlay.opacity(1.0) - 0.8).abs() < 1e-9);
+    let smooth = Overlay {
+        curve: OpacityCurve::Smoothstep,
+        ..Overlay::default()
+    };
+    assert!(smooth.opacity(0.1) < 0.1 && smooth.opacity(0.9) > 0.9);
+    let inverted = Overlay {
+        min_opacity: 1.0,
+        max_opacity: 0.0,
+        ..Overlay::default()
+    };
+    assert_eq!(inverted.opacity(1.0), 0.0);
+
+    let lifehash = LifeHash::from_data(b"Hello", Version2).unwrap();
+    let map = opacity_map(&lifehash, &overlay);
+    let image = lifehash.image(2, false).unwrap();
+    let translucent = apply_opacity(&image, &map.image(2).unwrap()).unwrap();
+    assert_eq!(translucent.channels, 4);
+    let expanded = lifehash.frac_grid.expand(lifehash.pattern);
+    for (x, y) in [(0, 0), (13, 40), (63, 63)] {
+        let pixel = &translucent.pixels[(y * 64 + x) * 4..][..4];
+        assert_eq!(pixel[..3], image.pixels[(y * 64 + x) * 3..][..3]);
+        let value = expanded.storage[(y / 2) * 32 + x / 2];
+        assert!((pixel[3] as f64 / 255.0 - overlay.opacity(value)).abs() < 0.01);
+    }
+    // The opacity comes from the frac values directly, one per module of the color grid.
+    let grid = map.grid();
+    assert_eq!(grid.dimensions, lifehash.color_grid.dimensions);
+    for (opacity, value) in grid.storage.iter().zip(&expanded.storage) {
+        assert_eq!(*opacity, overlay.opacity(*value));
+    }
+    let fitted = map.image_fit(50, Fit::Letterbox).unwrap();
+    assert_eq!((fitted.width, fitted.height), (50, 50));
+    // The opacity map renders through every mode, including smooth interpolation.
+    let soft = smooth_image(&lifehash, 100, Interpolation::Bicubic, true).unwrap();
+    let soft_map = map.smooth_image(100, Interpolation::Bicubic).unwrap();
+    let alpha = apply_opacity(&soft, &soft_map).unwrap();
+    assert!(alpha.pixels.chunks(4).all(|p| (50..=204).contains(&p[3])));
+    assert!(apply_opacity(&soft, &image).is_err());
+    let image16 = lifehash.image16(1, true).unwrap();
+    let map16 = map.image16(1).unwrap();
+    assert_eq!(apply_opacity16(&image16, &map16).unwrap().channels, 4);
+}
+
+#[test]
+pub fn test_stamp() {
+    use lifehash_lib::colors::WHITE;
+    use lifehash_lib::encoders::png::{decode_png, encode_png_to_vec, png_bit_depth};
+    use lifehash_lib::stamp::{stamp_image, stamp_png, Anchor, StampOptions};
+    use lifehash_lib::Image;
+    let base = Image {
+        width: 40,
+        height: 30,
+        channels: 1,
+        pixels: vec![0u8; 40 * 30],
+    };
+    let encoded = encode_png_to_vec(&base).unwrap();
+    assert_eq!(png_bit_depth(encoded.as_slice()).unwrap(), 8);
+    let decoded = decode_png::<u8, _>(encoded.as_slice()).unwrap();
+    assert_eq!(
+        (decoded.width, decoded.height, decoded.channels),
+        (40, 30, 1)
+    );
+    assert_eq!(decoded.pixels, base.pixels);
+    assert_eq!(
+        decode_png::<u16, _>(encoded.as_slice()).unwrap().pixels,
+        base.to_u16().pixels
+    );
+
+    let stamp = Image {
+        width: 4,
+        height: 4,
+        channels: 3,
+        pixels: vec![200u8; 4 * 4 * 3],
+    };
+    let options = StampOptions {
+        anchor: Anchor::BottomRight,
+        margin: 2,
+        border: 0,
+        shadow: 0,
+    };
+    let stamped = stamp_image(&base, &stamp, &options, WHITE).unwrap();
+    assert_eq!(
+        (stamped.width, stamped.height, stamped.channels),
+        (40, 30, 3)
+    );
+    let pixel = |image: &Image, x: usize, y: usize| image.pixels[(y * 40 + x) * 3..][..3].to_vec();
+    assert_eq!(pixel(&stamped, 34, 24), vec![200; 3]);
+    assert_eq!(pixel(&stamped, 37, 27), vec![200; 3]);
+    assert_eq!(pixel(&stamped, 33, 24), vec![0; 3]);
+    assert_eq!(pixel(&stamped, 38, 28), vec![0; 3]);
+    let centered = StampOptions {
+        anchor: Anchor::Center,
+        ..options
+    };
+    let stamped = stamp_image(&base, &stamp, &centered, WHITE).unwrap();
+    assert_eq!(pixel(&stamped, 18, 13), vec![200; 3]);
+    assert_eq!(pixel(&stamped, 17, 13), vec![0; 3]);
+
+    // The border surrounds the stamp and the shadow falls below and to the right of it.
+    let decorated = StampOptions {
+        anchor: Anchor::TopLeft,
+        margin: 4,
+        border: 2,
+        shadow: 3,
+    };
+    let stamped = stamp_image(&base, &stamp, &decorated, WHITE).unwrap();
+    assert_eq!(pixel(&stamped, 6, 6), vec![200; 3]);
+    assert_eq!(pixel(&stamped, 4, 7), vec![255; 3]);
+    assert_eq!(pixel(&stamped, 3, 3), vec![0; 3]);
+    assert_eq!(pixel(&stamped, 1, 1), vec![0; 3]);
+    assert!(pixel(&stamped, 12, 12).iter().all(|c| *c == 0));
+    let lit = stamp_image(
+        &Image {
+            pixels: vec![255; 40 * 30],
+            ..base
+        },
+        &stamp,
+        &decorated,
+        WHITE,
+    )
+    .unwrap();
+    assert!(pixel(&lit, 13, 13)[0] < 255);
+    assert_eq!(pixel(&lit, 1, 1), vec![255; 3]);
+
+    // Sixteen bit bases stay at sixteen bits.
+    let encoded16 = encode_png_to_vec(&base.to_u16()).unwrap();
+    let mut output = vec![];
+    stamp_png(encoded16.as_slice(), &stamp, &options, WHITE, &mut output).unwrap();
+    assert_eq!(png_bit_depth(output.as_slice()).unwrap(), 16);
+    let result = decode_png::<u16, _>(output.as_slice()).unwrap();
+    assert_eq!(result.pixels[(24 * 40 + 34) * result.channels], 200 * 257);
+    let empty = Image {
+        width: 0,
+        height: 0,
+        channels: 3,
+        pixels: vec![],
+    };
+    assert!(stamp_image(&base, &empty, &options, WHITE).is_err());
+    // Borders and shadows too large for an image are rejected before anything is allocated.
+    for (border, shadow) in [
+        (usize::MAX, 0),
+        (0, usize::MAX / 2 + 1),
+        (8192, 0),
+        (0, 4096),
+    ] {
+        let huge = StampOptions {
+            border,
+            shadow,
+            ..options
+        };
+        assert!(stamp_image(&base, &stamp, &huge, WHITE).is_err());
+    }
+    let far = StampOptions {
+        margin: usize::MAX,
+        ..options
+    };
+    let stamped = stamp_image(&base, &stamp, &far, WHITE).unwrap();
+    assert!(stamped.pixels.iter().all(|p| *p == 0));
+}
+
+#[test]
+pub fn test_card() {
+    use lifehash_lib::colors::{BLACK, WHITE};
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::render::card::{
+        caption_colors, card_image, card_image16, card_vector, fingerprint, Card,
+    };
+    use lifehash_lib::render::contour::contour_image;
+    use lifehash_lib::render::font::{glyph, text_bitmap, text_width, GLYPH_HEIGHT};
+    use lifehash_lib::Version::*;
+    assert_eq!(glyph('I'), [0x00, 0x41, 0x7f, 0x41, 0x00]);
+    assert_eq!(glyph('\u{e9}'), glyph('?'));
+    assert_eq!(text_width(""), 0);
+    assert_eq!(text_width("ab"), 11);
+    let bitmap = text_bitmap("-");
+    assert_eq!(bitmap.len(), GLYPH_HEIGHT);
+    assert_eq!(bitmap[3], vec![true; 5]);
+    assert!(bitmap[2].iter().all(|dot| !dot));
+    assert_eq!(fingerprint(&[1, 2, 3]), "010203");
+    assert_eq!(
+        fingerprint(&(0..32).collect::<Vec<u8>>()),
+        "00010203..1c1d1e1f"
+    );
+
+    let lifehash = LifeHash::from_data(b"Hello", Version2).unwrap();
+    let card = Card::default();
+    assert_eq!(card.caption(&lifehash), fingerprint(&lifehash.digest));
+    let (background, colors) = caption_colors(&lifehash.gradient, 101);
+    assert_eq!(background, lifehash.gradient.contrast_color());
+    assert_eq!(colors.len(), 101);
+    assert!(colors
+        .iter()
+        .all(|color| (color.luminance() - background.luminance()).abs() > 0.25));
+    // Each key color of the gradient gets an equal band of the columns.
+    let keys = lifehash.gradient.key_colors();
+    let (_, colors) = caption_colors(&lifehash.gradient, keys.len() * 3);
+    let opposite = if background == WHITE { BLACK } else { WHITE };
+    for (i, color) in colors.iter().enumerate() {
+        assert!((0..=10).any(|step| keys[i / 3].lerp_to(&opposite, step as f64 / 10.0) == *color));
+    }
+
+    // At 8 pixels per module the 18 character fingerprint fits at two pixels per dot.
+    let image = lifehash.image(8, false).unwrap();
+    let carded = card_image(&image, &lifehash, &card).unwrap();
+    assert_eq!(
+        (carded.width, carded.height, carded.channels),
+        (256, 278, 3)
+    );
+    assert_eq!(carded.pixels[..256 * 3], image.pixels[..256 * 3]);
+    let pixel = |x: usize, y: usize| carded.pixels[(y * 256 + x) * 3..][..3].to_vec();
+    let quantize = |c: f64| (c * 255.0) as u8;
+    let strip = vec![
+        quantize(background.r),
+        quantize(background.g),
+        quantize(background.b),
+    ];
+    assert_eq!(pixel(0, 256), strip);
+    assert_eq!(pixel(255, 277), strip);
+    // The first glyph is a '1' centered in the strip, its stem is the third column of dots.
+    let text_x = (256 - 107 * 2) / 2;
+    assert_ne!(pixel(text_x + 4, 260 + 6), strip);
+    assert_eq!(pixel(text_x, 260 + 6), strip);
+
+    // Small images widen the card to fit the caption at one pixel per dot.
+    let label = Card {
+        label: Some("Alice".to_string()),
+    };
+    let small = lifehash.image16(1, true).unwrap();
+    let carded = card_image16(&small, &lifehash, &label).unwrap();
+    assert_eq!((carded.width, carded.height, carded.channels), (33, 43, 4));
+    assert_eq!(carded.pixels[..4], small.pixels[..4]);
+    assert_eq!(carded.pixels[32 * 4 + 3], u16::MAX);
+
+    let contours = contour_image(&lifehash, Some(4)).unwrap();
+    let carded = card_vector(&contours, &lifehash, &card);
+    assert_eq!(carded.width, contours.width);
+    assert!(carded.height > contours.height);
+    let backdrop = &carded.shapes[contours.shapes.len()];
+    assert_eq!(backdrop.color, background);
+    assert_eq!(backdrop.paths[1][2], (contours.width, contours.height));
+    assert!(carded.shapes[contours.shapes.len() + 1..]
+        .iter()
+        .all(|shape| shape
+            .paths
+            .iter()
+            .flatten()
+            .all(|(_, y)| *y > contours.height)));
+}
+
+#[test]
+pub fn test_sheet() {
+    use lifehash_lib::colors::rgb::Color;
+    use lifehash_lib::colors::BLACK;
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::render::contour::contour_image;
+    use lifehash_lib::render::sheet::{sheet_image, sheet_image16, sheet_vector, Sheet};
+    use lifehash_lib::Image;
+    use lifehash_lib::Version::*;
+    assert_eq!(Color::from_hex("#ff8000"), Some(Color::new_u8(255, 128, 0)));
+    assert_eq!(Color::from_hex("000000"), Some(BLACK));
+    assert_eq!(Color::from_hex("#fff"), None);
+    let sheet = Sheet::default();
+    assert_eq!(sheet.grid(1), (1, 1));
+    assert_eq!(sheet.grid(5), (3, 2));
+    assert_eq!(sheet.grid(9), (3, 3));
+    let wide = Sheet {
+        columns: Some(4),
+        ..sheet
+    };
+    assert_eq!(wide.grid(9), (4, 3));
+    assert_eq!(wide.grid(2), (2, 1));
+
+    let lifehashes: Vec<LifeHash> = ["a", "b", "c", "d", "e"]
+        .iter()
+        .map(|text| LifeHash::from_data(text.as_bytes(), Version2).unwrap())
+        .collect();
+    let images: Vec<Image> = lifehashes
+        .iter()
+        .map(|lifehash| lifehash.image(2, false).unwrap())
+        .collect();
+    let black = Sheet {
+        columns: None,
+        spacing: 4,
+        background: BLACK,
+    };
+    let result = sheet_image(&images, &black).unwrap();
+    assert_eq!(
+        (result.width, result.height, result.channels),
+        (208, 140, 3)
+    );
+    let pixel = |x: usize, y: usize| result.pixels[(y * 208 + x) * 3..][..3].to_vec();
+    assert_eq!(pixel(0, 0), vec![0; 3]);
+    assert_eq!(pixel(4, 4), images[0].pixels[..3].to_vec());
+    assert_eq!(pixel(72, 4), images[1].pixels[..3].to_vec());
+    assert_eq!(pixel(72, 72), images[4].pixels[..3].to_vec());
+    assert_eq!(pixel(140, 72), vec![0; 3]);
+
+    // Transparent pixels show the background and smaller images are centered in their cell.
+    let clear = Image {
+        width: 2,
+        height: 2,
+        channels: 4,
+        pixels: [u16::MAX, 0, 0, 0].repeat(4),
+    };
+    let solid = Image {
+        width: 4,
+        height: 4,
+        channels: 1,
+        pixels: vec![u16::MAX; 16],
+    };
+    let result = sheet_image16(&[solid, clear], &black).unwrap();
+    assert_eq!((result.width, result.height), (20, 12));
+    assert_eq!(result.pixels[(4 * 20 + 4) * 3..][..3], [u16::MAX; 3]);
+    assert_eq!(result.pixels[(5 * 20 + 13) * 3..][..3], [0; 3]);
+    assert!(sheet_image(&[], &sheet).is_err());
+    let spaced = Sheet {
+        spacing: usize::MAX,
+        ..sheet
+    };
+    assert!(sheet_image(&images, &spaced).is_err());
+
+    let contours: Vec<_> = lifehashes
+        .iter()
+        .map(|lifehash| contour_image(lifehash, Some(3)).unwrap())
+        .collect();
+    let result = sheet_vector(&contours, &black, 2.0);
+    assert_eq!(
+        (result.width, result.height),
+        (3.0 * 34.0 + 2.0, 2.0 * 34.0 + 2.0)
+    );
+    assert_eq!(result.shapes[0].color, BLACK);
+    assert!(result.shapes.len() > 1);
+    // Contours reach past their images but are cut to their cells, which start every 34 units.
+    for shape in &result.shapes[1..] {
+        for path in &shape.paths {
+            for (x, y) in path {
+                assert!((x - 2.0).rem_euclid(34.0) <= 32.0 + 1e-9, "{x}");
+                assert!((y - 2.0).rem_euclid(34.0) <= 32.0 + 1e-9, "{y}");
+            }
+        }
+    }
+}
+
+#[test]
+pub fn test_compare() {
+    use lifehash_lib::colors::functions::ColorFunction;
+    use lifehash_lib::colors::{BLACK, WHITE};
+    use lifehash_lib::grids::color_grid::ColorGrid;
+    use lifehash_lib::grids::frac_grid::FracGrid;
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::render::compare::{difference, difference_image};
+    use lifehash_lib::Fit;
+    use lifehash_lib::Version::*;
+    let lifehash = LifeHash::from_data(b"Hello", Version2).unwrap();
+    let same = difference(&lifehash, &LifeHash::from_data(b"Hello", Version2).unwrap());
+    assert_eq!(same.modules(), 32 * 32);
+    assert_eq!(
+        (same.count(), same.frac_count(), same.color_count()),
+        (0, 0, 0)
+    );
+    assert_eq!(
+        same.summary(),
+        "0 of 1024 modules differ (0.0%): frac values in 0 (0.0%), colors in 0 (0.0%)"
+    );
+
+    // Same grid, different gradient.
+    let mut frac_grid = FracGrid::new(lifehash.frac_grid.dimensions);
+    frac_grid.storage.clone_from(&lifehash.frac_grid.storage);
+    let gradient = ColorFunction::Blend(BLACK, WHITE);
+    let gray = LifeHash {
+        digest: lifehash.digest.clone(),
+        version: lifehash.version,
+        pattern: lifehash.pattern,
+        color_grid: ColorGrid::create(&frac_grid, &gradient, lifehash.pattern),
+        gradient,
+        history: lifehash.history.clone(),
+        frac_grid,
+    };
+    let gradient = difference(&lifehash, &gray);
+    assert_eq!(gradient.frac_count(), 0);
+    assert!(gradient.color_count() > 0);
+    let image = difference_image(&lifehash, &gradient, 64, Fit::Distribute).unwrap();
+    assert_eq!((image.width, image.height, image.channels), (64, 64, 3));
+    let index = gradient.color.iter().position(|differs| *differs).unwrap();
+    let (x, y) = (index % 32 * 2, index / 32 * 2);
+    assert_eq!(image.pixels[(y * 64 + x) * 3..][..3], [0, 0, 255]);
+    let index = gradient.color.iter().position(|differs| !differs);
+    if let Some(index) = index {
+        let (x, y) = (index % 32 * 2, index / 32 * 2);
+        let pixel = &image.pixels[(y * 64 + x) * 3..][..3];
+        assert!(pixel[0] == pixel[1] && pixel[1] == pixel[2] && pixel[0] >= 153);
+    }
+
+    // The detailed version has twice the modules, the other grid is sampled to match.
+    let detailed = LifeHash::from_data(b"Hello", Detailed).unwrap();
+    let versions = difference(&lifehash, &detailed);
+    assert_eq!(versions.modules(), 64 * 64);
+    assert!(versions.frac_count() > 0);
+    assert_eq!(difference(&detailed, &lifehash).modules(), 64 * 64);
+    let image = difference_image(&lifehash, &versions, 128, Fit::Distribute).unwrap();
+    let index = versions.frac.iter().position(|differs| *differs).unwrap();
+    let (x, y) = (index % 64 * 2, index / 64 * 2);
+    assert_eq!(image.pixels[(y * 128 + x) * 3..][..3], [255, 0, 0]);
+}
+
+#[test]
+pub fn test_gallery() {
+    use lifehash_lib::colors::gradient::GradientFamily::*;
+    use lifehash_lib::gallery::{escape_html, write_gallery, GalleryEntry};
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::utils::base64;
+    use lifehash_lib::Version::*;
+    assert_eq!(base64::encode(b""), "");
+    assert_eq!(base64::encode(b"f"), "Zg==");
+    assert_eq!(base64::encode(b"fo"), "Zm8=");
+    assert_eq!(base64::encode(b"foo"), "Zm9v");
+    assert_eq!(base64::encode(b"foobar"), "Zm9vYmFy");
+    assert_eq!(base64::encode(&[0xfb, 0xff]), "+/8=");
+    assert_eq!(
+        escape_html(r#"<a href="x">Tom & Jerry's</a>"#),
+        "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;"
+    );
+
+    let family = |text: &str, version| {
+        LifeHash::from_data(text.as_bytes(), version)
+            .unwrap()
+            .gradient_family()
+            .unwrap()
+    };
+    assert_eq!(family("Hello", Version1), Monochromatic);
+    assert_eq!(family("Hello", Version2), Complementary);
+    assert_eq!(family("a", Detailed), Triadic);
+    assert_eq!(family("b", Version2), Analogous);
+    assert_eq!(family("Hello", GrayscaleFiducial), Grayscale);
+    assert_eq!(
+        LifeHash::from_data(b"Hello", Version2)
+            .unwrap()
+            .generations(),
+        66
+    );
+
+    let lifehashes = [
+        LifeHash::from_data(b"Hello", Version2).unwrap(),
+        LifeHash::from_data(b"a", Detailed).unwrap(),
+    ];
+    let entries = [
+        GalleryEntry {
+            name: "Hello".to_string(),
+            lifehash: &lifehashes[0],
+            src: "hello.png".to_string(),
+        },
+        GalleryEntry {
+            name: "<a>".to_string(),
+            lifehash: &lifehashes[1],
+            src: "data:image/png;base64,AAAA".to_string(),
+        },
+    ];
+    let mut html = vec![];
+    write_gallery("Keys & more", &entries, &mut html).unwrap();
+    let html = String::from_utf8(html).unwrap();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<title>Keys &amp; more</title>"));
+    assert!(html.contains(&format!(
+        r#"data-name="Hello" data-digest="{}" data-version="Version2" data-family="Complementary" data-pattern="Snowflake" data-generations="66""#,
+        hex::encode(&lifehashes[0].digest)
+    )));
+    assert!(html.contains(r#"data-name="&lt;a&gt;""#));
+    assert!(html.contains(r#"<img src="data:image/png;base64,AAAA""#));
+    assert!(html.contains(
+        r#"<select id="version"><option value="">All versions</option><option>Detailed</option><option>Version2</option></select>"#
+    ));
+    assert!(html.contains("<option>Complementary</option><option>Triadic</option>"));
+}
+
+#[test]
+pub fn test_data_uri() {
+    use lifehash_lib::encoders::png::{encode_png_to_data_uri, encode_png_to_vec};
+    use lifehash_lib::encoders::svg::encode_svg_to_data_uri;
+    use lifehash_lib::encoders::{data_uri, ImageFormat};
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::render::contour::contour_image;
+    use lifehash_lib::utils::base64;
+    use lifehash_lib::Version::*;
+    assert_eq!(
+        data_uri("text/plain", b"foo"),
+        "data:text/plain;base64,Zm9v"
+    );
+    assert_eq!(ImageFormat::Png.mime_type(), "image/png");
+    assert_eq!(ImageFormat::Svg.mime_type(), "image/svg+xml");
+    assert_eq!(ImageFormat::Pdf.mime_type(), "application/pdf");
+
+    let lifehash = LifeHash::from_data(b"Hello", Version2).unwrap();
+    let image = lifehash.image(1, false).unwrap();
+    let uri = encode_png_to_data_uri(&image).unwrap();
+    // The PNG signature.
+    assert!(uri.starts_with("data:image/png;base64,iVBORw0KGgo"));
+    assert_eq!(
+        uri,
+        format!(
+            "data:image/png;base64,{}",
+            base64::encode(&encode_png_to_vec(&image).unwrap())
+        )
+    );
+    let uri = encode_svg_to_data_uri(&contour_image(&lifehash, None).unwrap(), 1.0).unwrap();
+    // "<svg " in base64.
+    assert!(uri.starts_with("data:image/svg+xml;base64,PHN2Zy"));
+}
+
+#[test]
+pub fn test_relief_mesh() {
+    use lifehash_lib::encoders::obj::encode_obj;
+    use lifehash_lib::encoders::ply::encode_ply;
+    use lifehash_lib::encoders::stl::encode_stl;
+    use lifehash_lib::encoders::MeshFormat;
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::mesh::{relief_mesh, ReliefOptions};
+    use lifehash_lib::Version::*;
+    use std::collections::HashMap;
+    use std::path::Path;
+    assert_eq!(
+        MeshFormat::from_path(Path::new("token.STL")),
+        Some(MeshFormat::Stl)
+    );
+    assert_eq!(MeshFormat::from_path(Path::new("token.png")), None);
+
+    let lifehash = LifeHash::from_data(b"Hello", Version2).unwrap();
+    let options = ReliefOptions {
+        module_size: 0.5,
+        base: 1.0,
+        height: 2.0,
+        colors: false,
+    };
+    let mesh = relief_mesh(&lifehash, &options).unwrap();
+    assert!(mesh.colors.is_none());
+    // Closed and consistently wound: every edge is used as often in each direction. Columns
+    // that only touch diagonally share an edge between four triangles.
+    let mut edges: HashMap<(usize, usize), usize> = HashMap::new();
+    for [a, b, c] in &mesh.triangles {
+        for edge in [(*a, *b), (*b, *c), (*c, *a)] {
+            *edges.entry(edge).or_default() += 1;
+        }
+    }
+    for ((a, b), count) in &edges {
+        assert_eq!(edges.get(&(*b, *a)), Some(count));
+    }
+    for triangle in &mesh.triangles {
+        assert_ne!(mesh.normal(*triangle), [0.0; 3]);
+    }
+    // The enclosed volume, by the divergence theorem, is the sum of the columns.
+    let volume: f64 = mesh
+        .triangles
+        .iter()
+        .map(|triangle| {
+            let [a, b, c] = triangle.map(|i| mesh.vertices[i]);
+            (a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0])
+                + a[2] * (b[0] * c[1] - b[1] * c[0]))
+                / 6.0
+        })
+        .sum();
+    let grid = lifehash.frac_grid.expand(lifehash.pattern);
+    let expected: f64 = grid
+        .storage
+        .iter()
+        .map(|frac| 0.25 * (1.0 + 2.0 * frac))
+        .sum();
+    assert!((volume - expected).abs() < 1e-6);
+    let max = |axis: usize| mesh.vertices.iter().map(|v| v[axis]).fold(0.0, f64::max);
+    assert_eq!((max(0), max(1)), (16.0, 16.0));
+    assert!(max(2) <= 3.0);
+    // The top row of the image is at the far end of the y axis.
+    let color = *lifehash
+        .color_grid
+        .get_value(lifehash_lib::Point { x: 0, y: 0 });
+    let frac = grid.storage[0];
+    let colored = relief_mesh(
+        &lifehash,
+        &ReliefOptions {
+            colors: true,
+            ..options
+        },
+    )
+    .unwrap();
+    let colors = colored.colors.as_ref().unwrap();
+    assert_eq!(colors.len(), colored.vertices.len());
+    let corner = colored
+        .vertices
+        .iter()
+        .position(|v| *v == [0.0, 16.0, 1.0 + 2.0 * frac])
+        .unwrap();
+    assert_eq!(colors[corner], color);
+
+    let mut stl = vec![];
+    encode_stl(&mesh, &mut stl).unwrap();
+    assert_eq!(stl.len(), 84 + 50 * mesh.triangles.len());
+    assert!(!stl.starts_with(b"solid"));
+    assert_eq!(
+        u32::from_le_bytes(stl[80..84].try_into().unwrap()) as usize,
+        mesh.triangles.len()
+    );
+    let mut obj = vec![];
+    encode_obj(&colored, &mut obj).unwrap();
+    let obj = String::from_utf8(obj).unwrap();
+    assert_eq!(
+        obj.lines().filter(|l| l.starts_with("v ")).count(),
+        colored.vertices.len()
+    );
+    assert_eq!(
+        obj.lines().filter(|l| l.starts_with("f ")).count(),
+        colored.triangles.len()
+    );
+    assert_eq!(obj.lines().nth(1).unwrap().split(' ').count(), 7);
+    let mut ply = vec![];
+    encode_ply(&colored, &mut ply).unwrap();
+    let header_end = ply.windows(11).position(|w| w == b"end_header\n").unwrap() + 11;
+    let header = std::str::from_utf8(&ply[..header_end]).unwrap();
+    assert!(header.contains(&format!("element vertex {}", colored.vertices.len())));
+    assert!(header.contains("property uchar red"));
+    assert_eq!(
+        ply.len() - header_end,
+        15 * colored.vertices.len() + 13 * colored.triangles.len()
+    );
+    assert!(relief_mesh(
+        &lifehash,
+        &ReliefOptions {
+            base: 0.0,
+            ..options
+        }
+    )
+    .is_err());
+    for invalid in [
+        ReliefOptions {
+            height: f64::INFINITY,
+            ..options
+        },
+        ReliefOptions {
+            base: f64::INFINITY,
+            ..options
+        },
+        ReliefOptions {
+            module_size: f64::NAN,
+            ..options
+        },
+        ReliefOptions {
+            module_size: f64::MAX,
+            ..options
+        },
+    ] {
+        assert!(relief_mesh(&lifehash, &invalid).is_err());
+    }
+}
+
+#[test]
+pub fn test_grid_arrays() {
+    use lifehash_lib::encoders::csv::encode_csv;
+    use lifehash_lib::encoders::json::encode_json;
+    use lifehash_lib::encoders::npy::encode_npy;
+    use lifehash_lib::encoders::DataFormat;
+    use lifehash_lib::grids::array::{
+        color_array, frac_array, grid_array, history_array, ArrayValues, GridData,
+    };
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::Version::*;
+    use std::path::Path;
+    assert_eq!(
+        DataFormat::from_path(Path::new("grid.NPY")),
+        Some(DataFormat::Npy)
+    );
+    assert_eq!(DataFormat::from_path(Path::new("grid.txt")), None);
+
+    let lifehash = LifeHash::from_data(b"Hello", Version2).unwrap();
+    let frac = frac_array(&lifehash);
+    assert_eq!(frac.shape(), vec![16, 16]);
+    assert_eq!(
+        frac.values,
+        ArrayValues::Float(lifehash.frac_grid.storage.clone())
+    );
+    let history = history_array(&lifehash);
+    assert_eq!(history.shape(), vec![lifehash.generations(), 16, 16]);
+    let ArrayValues::Bits(bits) = &history.values else {
+        panic!("history is not bits");
+    };
+    // The first generation is the digest of the input, one bit per cell.
+    let first: Vec<u8> = bits[..256]
+        .chunks(8)
+        .map(|byte| byte.iter().fold(0, |acc, bit| acc << 1 | u8::from(*bit)))
+        .collect();
+    assert_eq!(first, lifehash.history[0]);
+    let color = color_array(&lifehash);
+    assert_eq!(color.shape(), vec![32, 32, 3]);
+    assert_eq!(color.len(), 32 * 32 * 3);
+    let c = lifehash.color_grid.storage[33];
+    let ArrayValues::Float(values) = &color.values else {
+        panic!("colors are not floats");
+    };
+    assert_eq!(values[99..102], [c.r, c.g, c.b]);
+    assert_eq!(grid_array(&lifehash, GridData::Color), color);
+
+    let mut csv = vec![];
+    encode_csv(&color, &mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 1 + 32 * 32);
+    assert_eq!(lines[0], "y,x,r,g,b");
+    assert_eq!(lines[34], format!("1,1,{},{},{}", c.r, c.g, c.b));
+    let mut csv = vec![];
+    encode_csv(&history, &mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert!(csv.starts_with("generation,y,x,alive\n0,0,0,"));
+    assert!(csv.ends_with(&format!(
+        "{},15,15,{}\n",
+        lifehash.generations() - 1,
+        u8::from(bits[bits.len() - 1])
+    )));
+
+    let mut json = vec![];
+    encode_json(&frac, &mut json).unwrap();
+    let json = String::from_utf8(json).unwrap();
+    assert!(json.starts_with(&format!(
+        r#"{{"axes":["y","x"],"fields":["frac"],"shape":[16,16],"data":[[{},"#,
+        lifehash.frac_grid.storage[0]
+    )));
+    assert!(json.ends_with("]]}\n"));
+    assert_eq!(json.matches('[').count(), 3 + 1 + 16);
+    let mut json = vec![];
+    encode_json(&color, &mut json).unwrap();
+    assert_eq!(
+        String::from_utf8(json).unwrap().matches('[').count(),
+        3 + 1 + 32 + 32 * 32
+    );
+
+    let mut npy = vec![];
+    encode_npy(&color, &mut npy).unwrap();
+    assert!(npy.starts_with(b"\x93NUMPY\x01\x00"));
+    let header_length = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+    assert_eq!((10 + header_length) % 64, 0);
+    let header = std::str::from_utf8(&npy[10..10 + header_length]).unwrap();
+    assert_eq!(
+        header.trim_end(),
+        "{'descr': '<f8', 'fortran_order': False, 'shape': (32, 32, 3), }"
+    );
+    assert!(header.ends_with('\n'));
+    assert_eq!(npy.len(), 10 + header_length + 32 * 32 * 3 * 8);
+    let offset = 10 + header_length + 99 * 8;
+    assert_eq!(
+        f64::from_le_bytes(npy[offset..offset + 8].try_into().unwrap()),
+        c.r
+    );
+    let mut npy = vec![];
+    encode_npy(&history, &mut npy).unwrap();
+    let header_length = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+    assert!(std::str::from_utf8(&npy[10..10 + header_length])
+        .unwrap()
+        .starts_with("{'descr': '|b1'"));
+    assert_eq!(npy.len(), 10 + header_length + bits.len());
+}
+
+#[test]
+pub fn test_life_patterns() {
+    use lifehash_lib::encoders::life106::encode_life106;
+    use lifehash_lib::encoders::rle::{decode_rle, encode_rle};
+    use lifehash_lib::encoders::LifeFormat;
+    use lifehash_lib::grids::cell_grid::Cellgrid;
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::Version::*;
+    use lifehash_lib::{Dimensions, Point};
+    use std::io::ErrorKind;
+    use std::path::Path;
+    assert_eq!(
+        LifeFormat::from_path(Path::new("seed.rle")),
+        Some(LifeFormat::Rle)
+    );
+    assert_eq!(
+        LifeFormat::from_path(Path::new("seed.lif")),
+        Some(LifeFormat::Life106)
+    );
+
+    let glider =
+        decode_rle("#C glider\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!".as_bytes()).unwrap();
+    assert_eq!(
+        glider.dimensions,
+        Dimensions {
+            width: 3,
+            height: 3
+        }
+    );
+    let alive: Vec<(usize, usize)> = glider
+        .get_points()
+        .into_iter()
+        .filter(|p| *glider.get_value(*p))
+        .map(|p| (p.x, p.y))
+        .collect();
+    assert_eq!(alive, vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+    let bounded = decode_rle("x = 3, y = 3, rule = B3/S23:T8,6\nbo$2bo$3o!".as_bytes()).unwrap();
+    assert_eq!(
+        bounded.dimensions,
+        Dimensions {
+            width: 8,
+            height: 6
+        }
+    );
+    assert!(*bounded.get_value(Point { x: 2, y: 2 }));
+    assert!(decode_rle("x = 2, y = 2\n3o!".as_bytes()).is_err());
+    assert!(decode_rle("#C nothing".as_bytes()).is_err());
+    // Malformed files are errors, not overflows or huge allocations.
+    for malformed in [
+        "x = 1, y = 1\n99999999999999999999999o!",
+        "x = 1, y = 1\n18446744073709551615b1o!",
+        "x = 1, y = 1\n18446744073709551615$18446744073709551615$o!",
+        "x = 100000000, y = 100000000\no!",
+        "x = 1, y = 1, rule = B3/S23:T18446744073709551615,2\no!",
+    ] {
+        let result = decode_rle(malformed.as_bytes());
+        assert!(matches!(result, Err(e) if e.kind() == ErrorKind::InvalidData));
+    }
+
+    let mut grid = Cellgrid::new(Dimensions {
+        width: 5,
+        height: 4,
+    });
+    grid.set_value(true, Point { x: 4, y: 0 });
+    grid.set_value(true, Point { x: 0, y: 3 });
+    let mut rle = vec![];
+    encode_rle(&grid, &["two cells".to_string()], &mut rle).unwrap();
+    assert_eq!(
+        String::from_utf8(rle).unwrap(),
+        "#C two cells\n#R 0 0\nx = 5, y = 4, rule = B3/S23:T5,4\n4bo3$o!\n"
+    );
+    let mut life = vec![];
+    encode_life106(&grid, &mut life).unwrap();
+    assert_eq!(String::from_utf8(life).unwrap(), "#Life 1.06\n4 0\n0 3\n");
+
+    let lifehash = LifeHash::from_data(b"Hello", Version2).unwrap();
+    let seed = lifehash.generation(0).unwrap();
+    assert_eq!(seed.get_data(), lifehash.history[0]);
+    assert!(lifehash.generation(lifehash.generations()).is_none());
+    let mut rle = vec![];
+    encode_rle(&seed, &[], &mut rle).unwrap();
+    let text = String::from_utf8(rle).unwrap();
+    assert!(text.lines().all(|line| line.len() <= 70));
+    let decoded = decode_rle(text.as_bytes()).unwrap();
+    assert_eq!(decoded.dimensions, seed.dimensions);
+    assert_eq!(decoded.storage, seed.storage);
+}
+
+#[test]
+pub fn test_print_pdf() {
+    use lifehash_lib::encoders::print::{encode_print_pdf, parse_length, PrintOptions};
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::Version::*;
+    assert_eq!(parse_length("1in"), Some(72.0));
+    assert_eq!(parse_length("25.4mm"), Some(72.0));
+    assert_eq!(parse_length("2.54 cm"), Some(72.0));
+    assert_eq!(parse_length("100pt"), Some(100.0));
+    assert_eq!(parse_length("100"), None);
+    assert_eq!(parse_length("-1in"), None);
+    assert_eq!(parse_length("1ft"), None);
+
+    let lifehash = LifeHash::from_data(b"Hello", Version2).unwrap();
+    let digest = hex::encode(&lifehash.digest);
+    let mut pdf = vec![];
+    let plain = PrintOptions {
+        size: 144.0,
+        caption: None,
+        crop_marks: false,
+        cmyk: None,
+    };
+    encode_print_pdf(&lifehash, &plain, &mut pdf).unwrap();
+    let text = String::from_utf8_lossy(&pdf);
+    assert!(text.starts_with("%PDF-1.4"));
+    assert!(text.contains("/MediaBox [0 0 144 144] /TrimBox [0 0 144 144]"));
+    // 32 modules of 4.5 points.
+    assert!(text.contains("q 4.5 0 0 -4.5 0 144 cm"));
+    assert!(!text.contains(" RG "));
+    assert!(!text.contains("BT"));
+    assert!(text.contains(&format!("/Subject (Digest {digest})")));
+    assert!(text.contains(&format!("/LifeHashDigest ({digest})")));
+    assert!(text.contains("/LifeHashVersion (Version2)"));
+    assert!(text.contains("/Info 6 0 R"));
+    let startxref: usize = text
+        .rsplit("startxref\n")
+        .next()
+        .unwrap()
+        .lines()
+        .next()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let xref = String::from_utf8_lossy(&pdf[startxref..]);
+    assert!(xref.starts_with("xref\n0 7\n"));
+    for (number, line) in xref.lines().skip(3).take(6).enumerate() {
+        let offset: usize = line[..10].parse().unwrap();
+        assert!(pdf[offset..].starts_with(format!("{} 0 obj", number + 1).as_bytes()));
+    }
+
+    let mut pdf = vec![];
+    let options = PrintOptions {
+        size: 144.0,
+        caption: Some("Seed (backup) \\ café".to_string()),
+        crop_marks: true,
+        cmyk: None,
+    };
+    encode_print_pdf(&lifehash, &options, &mut pdf).unwrap();
+    let text = String::from_utf8_lossy(&pdf);
+    // 18 points for the crop marks on each side and 16 for the caption.
+    assert!(text.contains("/MediaBox [0 0 180 196] /TrimBox [18 34 162 178]"));
+    assert!(text.contains("q 1 0 0 1 18 34 cm"));
+    assert_eq!(text.matches(" m ").count(), 8);
+    assert!(text.contains("/BaseFont /Courier"));
+    // 20 characters of 4.8 points, centered.
+    assert!(text.contains("BT /F1 8 Tf 42 24 Td (Seed \\(backup\\) \\\\ caf?) Tj ET"));
+    assert!(
+        encode_print_pdf(&lifehash, &PrintOptions { size: 0.0, ..plain }, &mut vec![]).is_err()
+    );
+}
+
+#[test]
+pub fn test_cmyk() {
+    use lifehash_lib::colors::cmyk::{gamut_report, CmykProfile, GamutReport};
+    use lifehash_lib::colors::rgb::Color;
+    use lifehash_lib::colors::{BLACK, SPECTRUM, SPECTRUM_CMYK_SAFE, WHITE};
+    use lifehash_lib::encoders::print::{encode_print_pdf, PrintOptions};
+    use lifehash_lib::encoders::tiff::encode_cmyk_tiff;
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::Image;
+    use lifehash_lib::Version::Version2;
+
+    let near = |a: [f64; 3], b: [f64; 3]| a.iter().zip(b).all(|(a, b)| (a - b).abs() < 0.05);
+    assert!(near(WHITE.lab(), [100.0, 0.0, 0.0]));
+    assert!(near(BLACK.lab(), [0.0, 0.0, 0.0]));
+    // sRGB red.
+    assert!(near(Color::new(1.0, 0.0, 0.0).lab(), [53.24, 80.09, 67.2]));
+    assert!((WHITE.delta_e(&BLACK) - 100.0).abs() < 0.01);
+
+    let profile = CmykProfile::default();
+    assert_eq!(profile.separate(WHITE).total(), 0.0);
+    let black = profile.separate(BLACK);
+    assert_eq!(black.k, 1.0);
+    assert!((black.total() - profile.total_ink).abs() < 1e-9);
+    // Colors inside the gamut come back unchanged.
+    for color in [Color::new_u8(200, 180, 150), Color::new_u8(210, 59, 130)] {
+        assert!(color.delta_e(&profile.round_trip(color)) < 0.01);
+    }
+    assert!(Color::new_u8(0, 255, 0).delta_e(&profile.round_trip(Color::new_u8(0, 255, 0))) > 20.0);
+    // The table prints colors inside the gamut as closely as separating one by one.
+    let table = profile.table();
+    for color in [
+        Color::new_u8(200, 180, 150),
+        Color::new_u8(210, 59, 130),
+        Color::new_u8(178, 110, 151),
+        Color::new_u8(22, 13, 57),
+    ] {
+        assert!(
+            profile
+                .print(table.separate(color))
+                .delta_e(&profile.round_trip(color))
+                < 0.5
+        );
+    }
+    assert_eq!(table.separate(WHITE).total(), 0.0);
+
+    // The print safe spectrum stays closer to its colors than the screen one.
+    let total = |report: GamutReport| report.entries.iter().map(|e| e.delta_e).sum::<f64>();
+    let screen = GamutReport::new(&SPECTRUM.key_colors(), &profile, 5.0);
+    let safe = GamutReport::new(&SPECTRUM_CMYK_SAFE.key_colors(), &profile, 5.0);
+    assert_eq!(screen.entries.len(), 7);
+    assert!(total(safe) < total(screen.clone()));
+    assert_eq!(screen.out_of_gamut().len(), 2);
+    let summary = screen.summary();
+    assert_eq!(summary.lines().count(), 8);
+    assert!(summary.starts_with("#00a8de C 100% M 0% Y 8% K 13% prints #00a2d9 delta E 2.0\n"));
+    assert!(
+        summary.contains("#333391 C 93% M 88% Y 0% K 43% prints #333174 delta E 16.6 out of gamut")
+    );
+    assert!(summary
+        .ends_with("2 of 7 colors change by more than delta E 5.0 in CMYK with 300% total ink"));
+
+    let lifehash = LifeHash::from_data(b"Hello", Version2).unwrap();
+    let report = gamut_report(&lifehash, &profile, 5.0);
+    let keys = lifehash.gradient.key_colors();
+    assert_eq!(report.entries[0].color, keys[0]);
+    let mut hexes: Vec<String> = report.entries.iter().map(|e| e.color.hex()).collect();
+    let count = hexes.len();
+    hexes.sort();
+    hexes.dedup();
+    assert_eq!(hexes.len(), count);
+    assert!(report.out_of_gamut().is_empty());
+    assert!(!gamut_report(&lifehash, &profile, 1.0)
+        .out_of_gamut()
+        .is_empty());
+
+    // A white, a half transparent black and a red pixel.
+    let image = Image {
+        width: 3,
+        height: 1,
+        channels: 4,
+        pixels: vec![255, 255, 255, 255, 0, 0, 0, 128, 255, 0, 0, 255],
+    };
+    let mut tiff = vec![];
+    encode_cmyk_tiff(&image, &profile, &mut tiff).unwrap();
+    assert!(tiff.starts_with(b"II*\0\x08\0\0\0\x0e\0"));
+    let tag = |tag: u16| {
+        (0..14)
+            .map(|i| &tiff[10 + i * 12..][..12])
+            .find(|entry| u16::from_le_bytes([entry[0], entry[1]]) == tag)
+            .map(|entry| u32::from_le_bytes([entry[8], entry[9], entry[10], entry[11]]))
+            .unwrap()
+    };
+    assert_eq!((tag(256), tag(257)), (3, 1));
+    // Separated into the CMYK ink set.
+    assert_eq!((tag(262), tag(277), tag(332)), (5, 4, 1));
+    assert_eq!(tag(279), 12);
+    let pixels = &tiff[tag(273) as usize..];
+    assert_eq!(pixels.len(), 12);
+    assert_eq!(&pixels[..4], &[0, 0, 0, 0]);
+    assert!(pixels[7] > 0 && pixels[7] < 255);
+    assert!(pixels[9] > 200 && pixels[10] > 200);
+
+    let mut pdf = vec![];
+    let options = PrintOptions {
+        size: 144.0,
+        caption: Some("Vault key 1".to_string()),
+        crop_marks: true,
+        cmyk: Some(profile),
+    };
+    encode_print_pdf(&lifehash, &options, &mut pdf).unwrap();
+    let text = String::from_utf8_lossy(&pdf);
+    assert!(text.contains(" k\n"));
+    assert!(!text.contains(" rg\n"));
+    assert!(text.contains("1 1 1 1 K 0.25 w"));
+    assert!(text.contains("0 0 0 1 k BT"));
+}
+
+#[test]
+pub fn test_dither() {
+    use lifehash_lib::colors::rgb::Color;
+    use lifehash_lib::encoders::png::{decode_png, encode_mono_png, png_bit_depth};
+    use lifehash_lib::encoders::pnm::encode_pbm;
+    use lifehash_lib::grids::color_grid::ColorGrid;
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::render::dither::{dither_grid, dither_image, Dither, MonoImage};
+    use lifehash_lib::Version::Version2;
+    use lifehash_lib::{Dimensions, Image};
+
+    // A flat gray of a quarter luminance is a quarter white.
+    let mut grid = ColorGrid::new(Dimensions {
+        width: 16,
+        height: 16,
+    });
+    let gray = (0.25f64.powf(1.0 / 2.4) * 1.055 - 0.055).max(0.0);
+    grid.storage.fill(Color::new(gray, gray, gray));
+    let white = |method| {
+        let image = dither_grid(&grid, method);
+        image.black.iter().filter(|black| !**black).count()
+    };
+    for method in [Dither::FloydSteinberg, Dither::Bayer] {
+        assert!((60..=68).contains(&white(method)));
+    }
+    // Atkinson drops part of the error, so the shadows come out darker.
+    assert!((1..60).contains(&white(Dither::Atkinson)));
+    // The ordered dither repeats every four pixels.
+    let bayer = dither_grid(&grid, Dither::Bayer);
+    for y in 0..16 {
+        for x in 0..16 {
+            assert_eq!(bayer.black[y * 16 + x], bayer.black[(y % 4) * 16 + x % 4]);
+        }
+    }
+
+    let lifehash = LifeHash::from_data(b"Hello", Version2).unwrap();
+    let image = dither_image(&lifehash, 3, Dither::FloydSteinberg).unwrap();
+    assert_eq!((image.width, image.height), (96, 96));
+    let native = dither_grid(&lifehash.color_grid, Dither::FloydSteinberg);
+    assert_eq!(
+        image.black[(5 * 3 + 2) * 96 + 7 * 3 + 1],
+        native.black[5 * 32 + 7]
+    );
+    assert!(dither_image(&lifehash, 0, Dither::Bayer).is_err());
+    assert!(dither_image(&lifehash, usize::MAX, Dither::Bayer).is_err());
+
+    // Packed rows start on a byte boundary, with the leftmost pixel in the top bit.
+    let mono = MonoImage {
+        width: 10,
+        height: 2,
+        black: (0..20).map(|i| i % 3 == 0).collect(),
+    };
+    assert_eq!(
+        mono.packed_rows(),
+        vec![0b1001_0010, 0b0100_0000, 0b0010_0100, 0b1000_0000]
+    );
+    assert_eq!(MonoImage::threshold(&mono.to_image()), mono);
+    let transparent = Image {
+        width: 1,
+        height: 1,
+        channels: 2,
+        pixels: vec![0, 0],
+    };
+    assert_eq!(MonoImage::threshold(&transparent).black, vec![false]);
+
+    let mut pbm = vec![];
+    encode_pbm(&mono, &mut pbm).unwrap();
+    assert!(pbm.starts_with(b"P4\n10 2\n"));
+    assert_eq!(&pbm[8..], mono.packed_rows().as_slice());
+
+    let mut png = vec![];
+    encode_mono_png(&mono, &mut png).unwrap();
+    assert_eq!(png_bit_depth(png.as_slice()).unwrap(), 1);
+    let decoded = decode_png::<u8, _>(png.as_slice()).unwrap();
+    assert_eq!(decoded.channels, 1);
+    assert_eq!(decoded.pixels, mono.to_image().pixels);
+}
+
+#[test]
+pub fn test_framebuffer() {
+    use lifehash_lib::encoders::framebuffer::{
+        encode_framebuffer, framebuffer_rows, render_framebuffer_rows, FramebufferOptions,
+        PixelFormat,
+    };
+    use lifehash_lib::lifehash::LifeHash;
+    use lifehash_lib::Image;
+    use lifehash_lib::Version::Version2;
+
+    let mut out = [0u8; 4];
+    let orange = [255, 128, 0, 255];
+    PixelFormat::Rgb565Le.pack(orange, &mut out);
+    // 31 red, 32 green and 0 blue.
+    assert_eq!(&out[..2], &[0x00, 0xfc]);
+    PixelFormat::Rgb565Be.pack(orange, &mut out);
+    assert_eq!(&out[..2], &[0xfc, 0x00]);
+    PixelFormat::Rgb888.pack(orange, &mut out);
+    assert_eq!(&out[..3], &[255, 128, 0]);
+    PixelFormat::Bgr888.pack(orange, &mut out);
+    assert_eq!(&out[..3], &[0, 128, 255]);
+    PixelFormat::Argb8888.pack(orange, &mut out);
+    assert_eq!(out, [255, 255, 128, 0]);
+    // Translucent pixels keep their alpha, or are blended over black without it.
+    let faint = [255, 128, 0, 64];
+    PixelFormat::Argb8888.pack(faint, &mut out);
+    assert_eq!(out, [64, 255, 128, 0]);
+    PixelFormat::Rgb888.pack(faint, &mut out);
+    assert_eq!(&out[..3], &[64, 32, 0]);
+    PixelFormat::Rgb565Le.pack([255, 255, 255, 0], &mut out);
+    assert_eq!(&out[..2], &[0, 0]);
+    PixelFormat::Rgb565Be.pack([255, 255, 255, 255], &mut out);
+    assert_eq!(&out[..2], &[0xff, 0xff]);
+
+    // Two gray pixels a row, padded to a stride of 8.
+    let image = Image {
+        width: 2,
+        height: 2,
+        channels: 1,
+        pixels: vec![0, 255, 255, 0],
+    };
+    let options = FramebufferOptions {
+        format: PixelFormat::Rgb888,
+        stride: Some(8),
+    };
+    let mut framebuffer = vec![];
+    encode_framebuffer(&image, &options, &mut framebuffer).unwrap();
+    assert_eq!(
+        framebuffer,
+        vec![0, 0, 0, 255, 255, 255, 0, 0, 255, 255, 255, 0, 0, 0, 0, 0]
+    );
+    let narrow = FramebufferOptions {
+        stride: Some(5),
+        ..options
+    };
+    assert!(encode_framebuffer(&image, &narrow, &mut vec![]).is_err());
+    let huge = FramebufferOptions {
+        stride: Some(usize::MAX),
+        ..options
+    };
+    let error = encode_framebuffer(&image, &huge, &mut vec![]).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(options.stride(usize::MAX).is_err());
+
+    // Streaming the rows gives the same framebuffer as rendering the image first.
+    let lifehash = LifeHash::from_data(b"Hello", Version2).unwrap();
+    let options = FramebufferOptions {
+        format: PixelFormat::Rgb565Le,
+        stride: Some(200),
+    };
+    let mut expected = vec![];
+    encode_framebuffer(&lifehash.image(3, false).unwrap(), &options, &mut expected).unwrap();
+    assert_eq!(expected.len(), 96 * 200);
+    let mut streamed = vec![];
+    let mut longest = 0;
+    render_framebuffer_rows(&lifehash, 3, &options, |y, row| {
+        assert_eq!(y * 200, streamed.len());
+        longest = longest.max(row.len());
+        streamed.extend_from_slice(row);
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(longest, 200);
+    assert_eq!(streamed, expected);
+    let mut rows = 0;
+    framebuffer_rows(&lifehash.image(1, true).unwrap(), &options, |_, _| {
+        rows += 1;
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(rows, 32);
+    assert!(render_framebuffer_rows(&lifehash, 0, &options, |_, _| Ok(())).is_err());
+    assert!(render_framebuffer_rows(&lifehash, usize::MAX, &options, |_, _| Ok(())).is_err());
+}